@@ -0,0 +1,263 @@
+//! Derive macros for PicoCraft, re-exported from `picocraft-protocol`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Error, Expr, Fields, Ident,
+    LitInt, Type,
+};
+
+/// Generates `PacketInfo`, `ReadPacket` and `WritePacket` for a packet struct.
+///
+/// ```ignore
+/// #[derive(McPacket)]
+/// #[packet(id = 0x00, state = Handshake, bound = Server)]
+/// pub struct HandshakePacket {
+///     #[varint]
+///     pub protocol_version: i32,
+///     #[string(max = 255)]
+///     pub server_address: String,
+///     pub server_port: u16,
+///     pub next_state: State,
+/// }
+/// ```
+///
+/// Fields are read and written in order. By default a field uses its `ReadType`/`WriteType`
+/// impl, which can be changed with:
+/// - `#[varint]`/`#[varlong]` for an `i32`/`i64` sent as a VarInt/VarLong
+/// - `#[string(max = N)]` for a `String` with a maximum length other than 32767
+/// - `#[prefixed]` for a `Vec<T>` prefixed with its length, or `#[prefixed(max = N)]` to allow
+///   at most `N` values
+/// - `#[optional]` for an `Option<T>` prefixed with a boolean
+/// - `#[rest]` for a `Vec<u8>` that takes up the rest of the packet
+#[proc_macro_derive(
+    McPacket,
+    attributes(packet, varint, varlong, string, prefixed, optional, rest)
+)]
+pub fn derive_mc_packet(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct PacketAttr {
+    id: LitInt,
+    state: Ident,
+    bound: Ident,
+}
+
+enum Encoding {
+    Type,
+    VarInt,
+    VarLong,
+    String(Expr),
+    Prefixed(Option<Expr>),
+    Optional,
+    Rest,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let attr = packet_attr(&input)?;
+
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            input.span(),
+            "McPacket can only be derived for structs",
+        ));
+    };
+
+    let mut reads = Vec::new();
+    let mut writes = Vec::new();
+    match &data.fields {
+        Fields::Named(fields) => {
+            for field in &fields.named {
+                let ident = field.ident.as_ref().unwrap();
+                let ty = &field.ty;
+                let (read, write) = field_code(&field.attrs, ident, ty)?;
+                reads.push(quote! { #ident: #read });
+                writes.push(write);
+            }
+        }
+        Fields::Unit => {}
+        Fields::Unnamed(fields) => {
+            return Err(Error::new(
+                fields.span(),
+                "McPacket needs named fields, or none at all",
+            ));
+        }
+    }
+
+    let construct = match &data.fields {
+        Fields::Unit => quote! { #name },
+        _ => quote! { #name { #(#reads,)* } },
+    };
+
+    let PacketAttr { id, state, bound } = attr;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::picocraft_protocol::packets::PacketInfo for #name #ty_generics #where_clause {
+            const ID: i32 = #id;
+            const STATE: ::picocraft_protocol::packets::State =
+                ::picocraft_protocol::packets::State::#state;
+            const BOUND: ::picocraft_protocol::packets::Bound =
+                ::picocraft_protocol::packets::Bound::#bound;
+        }
+
+        impl #impl_generics ::picocraft_protocol::packets::ReadPacket for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            async fn read_packet<R: ::picocraft_protocol::__private::Read>(
+                socket: &mut R,
+            ) -> ::core::result::Result<Self, ::picocraft_protocol::error::ProtocolError> {
+                ::core::result::Result::Ok(#construct)
+            }
+        }
+
+        impl #impl_generics ::picocraft_protocol::packets::WritePacket for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            async fn write_packet<W: ::picocraft_protocol::__private::Write>(
+                &self,
+                socket: &mut W,
+            ) -> ::core::result::Result<(), ::picocraft_protocol::error::ProtocolError> {
+                #(#writes)*
+                ::core::result::Result::Ok(())
+            }
+        }
+    })
+}
+
+fn packet_attr(input: &DeriveInput) -> syn::Result<PacketAttr> {
+    let attr = input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("packet"))
+        .ok_or_else(|| {
+            Error::new(
+                input.ident.span(),
+                "missing #[packet(id = .., state = .., bound = ..)]",
+            )
+        })?;
+
+    let mut id = None;
+    let mut state = None;
+    let mut bound = None;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("id") {
+            id = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("state") {
+            state = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("bound") {
+            bound = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("expected `id`, `state` or `bound`"));
+        }
+        Ok(())
+    })?;
+
+    let missing = |what| Error::new(attr.span(), format!("missing `{}` in #[packet]", what));
+    Ok(PacketAttr {
+        id: id.ok_or_else(|| missing("id"))?,
+        state: state.ok_or_else(|| missing("state"))?,
+        bound: bound.ok_or_else(|| missing("bound"))?,
+    })
+}
+
+fn encoding(attrs: &[Attribute]) -> syn::Result<Encoding> {
+    let mut encoding = Encoding::Type;
+
+    for attr in attrs {
+        let found = if attr.path().is_ident("varint") {
+            Encoding::VarInt
+        } else if attr.path().is_ident("varlong") {
+            Encoding::VarLong
+        } else if attr.path().is_ident("prefixed") {
+            let mut max = None;
+            // The max is optional, so a bare #[prefixed] has no list to parse
+            if !matches!(attr.meta, syn::Meta::Path(_)) {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("max") {
+                        max = Some(meta.value()?.parse()?);
+                        Ok(())
+                    } else {
+                        Err(meta.error("expected `max`"))
+                    }
+                })?;
+            }
+            Encoding::Prefixed(max)
+        } else if attr.path().is_ident("optional") {
+            Encoding::Optional
+        } else if attr.path().is_ident("rest") {
+            Encoding::Rest
+        } else if attr.path().is_ident("string") {
+            let mut max = None;
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("max") {
+                    max = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `max`"))
+                }
+            })?;
+            Encoding::String(max.ok_or_else(|| Error::new(attr.span(), "missing `max`"))?)
+        } else {
+            continue;
+        };
+
+        if !matches!(encoding, Encoding::Type) {
+            return Err(Error::new(
+                attr.span(),
+                "a field can only have one encoding attribute",
+            ));
+        }
+        encoding = found;
+    }
+
+    Ok(encoding)
+}
+
+fn field_code(
+    attrs: &[Attribute],
+    ident: &Ident,
+    ty: &Type,
+) -> syn::Result<(TokenStream2, TokenStream2)> {
+    let read_ext = quote! { ::picocraft_protocol::read::ReadExtension };
+    let write_ext = quote! { ::picocraft_protocol::write::WriteExtension };
+
+    Ok(match encoding(attrs)? {
+        Encoding::Type => (
+            quote! { <#ty as ::picocraft_protocol::types::ReadType>::read_type(socket).await? },
+            quote! { ::picocraft_protocol::types::WriteType::write_type(&self.#ident, socket).await?; },
+        ),
+        Encoding::VarInt => (
+            quote! { #read_ext::read_varint(socket).await? },
+            quote! { #write_ext::write_varint(socket, self.#ident).await?; },
+        ),
+        Encoding::VarLong => (
+            quote! { #read_ext::read_varlong(socket).await? },
+            quote! { #write_ext::write_varlong(socket, self.#ident).await?; },
+        ),
+        Encoding::String(max) => (
+            quote! { #read_ext::read_string_max(socket, #max).await? },
+            quote! { #write_ext::write_string(socket, &self.#ident).await?; },
+        ),
+        Encoding::Prefixed(None) => (
+            quote! { #read_ext::read_prefixed(socket).await? },
+            quote! { #write_ext::write_prefixed(socket, &self.#ident).await?; },
+        ),
+        Encoding::Prefixed(Some(max)) => (
+            quote! { #read_ext::read_prefixed_max(socket, #max).await? },
+            quote! { #write_ext::write_prefixed(socket, &self.#ident).await?; },
+        ),
+        Encoding::Optional => (
+            quote! { #read_ext::read_optional(socket).await? },
+            quote! { #write_ext::write_optional(socket, &self.#ident).await?; },
+        ),
+        Encoding::Rest => (
+            quote! { #read_ext::read_to_end(socket).await? },
+            quote! { #write_ext::write_byte_array(socket, &self.#ident).await?; },
+        ),
+    })
+}
@@ -1,6 +1,8 @@
 MEMORY {
     BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
-    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100
+    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100 - 128K
+    /* Kept for the block store, which the program doesn't link anything into */
+    STORE : ORIGIN = 0x101E0000, LENGTH = 128K
 
     /* Pick one of the two options for RAM layout     */
 
@@ -0,0 +1,9 @@
+use picocraft_server::game::Game;
+
+use crate::net::Store;
+
+/// Runs the game loop, which every connection's world is kept in step by.
+#[embassy_executor::task]
+pub async fn event_loop(game: &'static Game<Store>) -> ! {
+    game.run().await
+}
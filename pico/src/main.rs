@@ -7,8 +7,6 @@
 
 extern crate alloc;
 
-use core::str::from_utf8;
-
 use cyw43_pio::PioSpi;
 use defmt::*;
 use defmt_rtt as _;
@@ -17,16 +15,25 @@ use embassy_net::tcp::TcpSocket;
 use embassy_net::{Config, Stack, StackResources};
 use embassy_rp::bind_interrupts;
 use embassy_rp::clocks::RoscRng;
+use embassy_rp::flash::Flash;
 use embassy_rp::gpio::{Level, Output};
 use embassy_rp::peripherals::{DMA_CH0, PIN_23, PIN_25, PIO0, USB};
 use embassy_rp::pio::{InterruptHandler, Pio};
 use embassy_rp::usb::Driver;
 use embassy_time::{Duration, Timer};
 use embedded_alloc::Heap;
-use embedded_io_async::Write;
+use events::event_loop;
 use log::{info, warn};
-use net::handle_conn;
+use net::{free, free_slot, handle_conn, take_slot, Store, CONNECTIONS};
+use picocraft_server::{
+    encryption::ServerKey,
+    game::Game,
+    store::FlashStore,
+    world::{Generator, World},
+    Config as ServerConfig,
+};
 use rand::RngCore;
+use rng::Rosc;
 use static_cell::StaticCell; //, panic_probe as _};
                              //use rp2040_panic_usb_boot as _;
 
@@ -37,10 +44,12 @@ bind_interrupts!(struct Irqs {
 
 mod events;
 mod net;
-mod packets;
 mod panic;
-mod read;
-mod write;
+mod rng;
+
+/// Where the block store starts in flash, which has to match the STORE region in memory.x.
+const STORE_START: u32 = 0x1E_0000;
+const STORE_SIZE: u32 = 128 * 1024;
 
 // We use the heap to size packets
 #[global_allocator]
@@ -71,7 +80,8 @@ async fn logger_task(driver: Driver<'static, USB>) {
 async fn main(spawner: Spawner) {
     {
         use core::mem::MaybeUninit;
-        const HEAP_SIZE: usize = 1024;
+        // Enough for a connection to compress and decompress packets
+        const HEAP_SIZE: usize = 64 * 1024;
         static mut HEAP_MEM: [MaybeUninit<u8>; HEAP_SIZE] = [MaybeUninit::uninit(); HEAP_SIZE];
         unsafe { HEAP.init(HEAP_MEM.as_ptr() as usize, HEAP_SIZE) }
     }
@@ -90,6 +100,52 @@ async fn main(spawner: Spawner) {
 
     let mut rng = RoscRng;
 
+    // Takes a while, but only once per boot
+    info!("Generating the server's key pair...");
+    let key = match ServerKey::generate(&mut Rosc) {
+        Ok(key) => Some(key),
+        Err(_) => {
+            warn!("Couldn't generate a key pair, connections won't be encrypted");
+            None
+        }
+    };
+    static SERVER_CONFIG: StaticCell<ServerConfig> = StaticCell::new();
+    let mut server_config = ServerConfig {
+        key,
+        // Set when building to check players with Mojang
+        check_sessions: option_env!("CHECK_SESSIONS").is_some(),
+        ..ServerConfig::default()
+    };
+    if let Some(session_server) = option_env!("SESSION_SERVER") {
+        server_config.session_server = session_server.into();
+    }
+    // The edits players make are kept in the STORE region of memory.x
+    info!("Opening the block store...");
+    static GAME: StaticCell<Game<Store>> = StaticCell::new();
+    let flash = Flash::new_blocking(p.FLASH);
+    let mut store = FlashStore::open(flash, STORE_START, STORE_SIZE, rng.next_u64())
+        .expect("couldn't open the block store");
+    info!("{} edits until the block store is compacted", store.space());
+
+    // Set when building to pick the world, or the one the edits were made in is used again
+    if let Some(seed) = option_env!("SEED") {
+        let seed = seed.parse().expect("SEED isn't a number");
+        if seed != store.seed() {
+            warn!("SEED has changed, forgetting the edits made under the old one");
+            store.reset(seed).expect("couldn't reset the block store");
+        }
+    }
+    server_config.world = World {
+        generator: option_env!("WORLD")
+            .map(|name| Generator::from_name(name).expect("WORLD isn't a generator"))
+            .unwrap_or(Generator::Terrain),
+        seed: store.seed(),
+    };
+    info!("Generating worlds from seed {}", server_config.world.seed);
+    let server_config = &*SERVER_CONFIG.init(server_config);
+    let game = &*GAME.init(Game::new(store));
+    spawner.spawn(event_loop(game)).unwrap();
+
     let fw = include_bytes!("../firmware/43439A0.bin");
     let clm = include_bytes!("../firmware/43439A0_clm.bin");
 
@@ -169,49 +225,52 @@ async fn main(spawner: Spawner) {
         "DHCP is up with IP {}",
         stack.config_v4().unwrap().address.address()
     );
-    //Timer::after_millis(100).await;
 
     // And now we can use it!
 
     info!("Created bufs");
-    //Timer::after_millis(100).await;
 
-    static mut RX_BUF: [[u8; 1024]; 4] = [[0; 1024]; 4];
-    static mut TX_BUF: [[u8; 1024]; 4] = [[0; 1024]; 4];
-
-    let mut i = 0;
+    // One pair for each connection, and a spare for turning connections away when they're all
+    // in use
+    static mut RX_BUF: [[u8; 1024]; CONNECTIONS + 1] = [[0; 1024]; CONNECTIONS + 1];
+    static mut TX_BUF: [[u8; 1024]; CONNECTIONS + 1] = [[0; 1024]; CONNECTIONS + 1];
 
     loop {
-        let rx_buffer = unsafe { &mut RX_BUF[i] };
-        let tx_buffer = unsafe { &mut TX_BUF[i] };
+        let slot = free_slot().unwrap_or(CONNECTIONS);
+        let rx_buffer = unsafe { &mut RX_BUF[slot] };
+        let tx_buffer = unsafe { &mut TX_BUF[slot] };
 
         rx_buffer.fill(0);
-        rx_buffer.fill(0);
+        tx_buffer.fill(0);
 
         let mut socket = TcpSocket::new(stack, rx_buffer, tx_buffer);
         socket.set_timeout(Some(Duration::from_secs(10)));
 
         control.gpio_set(0, false).await;
         info!("Listening on TCP:25565...");
-        //Timer::after_millis(100).await;
         if let Err(e) = socket.accept(25565).await {
             warn!("accept error: {:?}", e);
-            //Timer::after_millis(100).await;
             continue;
         }
 
         control.gpio_set(0, true).await;
         info!("Received connection from {:?}", socket.remote_endpoint());
-        //Timer::after_millis(100).await;
 
-        spawner.spawn(handle_conn(socket)).unwrap();
-
-        info!("Creating a new thingy majigy");
-        //Timer::after_millis(100).await;
+        if slot == CONNECTIONS {
+            warn!("Too many connections, closing this one");
+            socket.close();
+            let _ = socket.flush().await;
+            continue;
+        }
 
-        i += 1;
-        if i >= 4 {
-            i = 0;
+        take_slot(slot);
+        if let Err(err) = spawner.spawn(handle_conn(socket, slot, server_config, game, stack)) {
+            // Can't happen with a slot for every task, but the socket's gone either way
+            warn!("Couldn't handle the connection: {:?}", err);
+            free(slot);
+            continue;
         }
+
+        info!("Handling the connection in slot {}", slot);
     }
 }
@@ -0,0 +1,98 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+use embassy_net::{dns::DnsQueryType, tcp::TcpSocket, Stack};
+use embassy_rp::{
+    flash::{Blocking, Flash},
+    peripherals::FLASH,
+};
+use embassy_time::Duration;
+use embedded_io_async::ErrorKind;
+use log::{info, warn};
+use picocraft_server::{
+    game::Game, serve, session::Connect, store::FlashStore, Config, MAX_PLAYERS,
+};
+
+use crate::rng::Rosc;
+
+/// How big the Pico W's flash is.
+const FLASH_SIZE: usize = 2 * 1024 * 1024;
+
+/// Where block edits are kept, which every connection shares.
+pub type Store = FlashStore<Flash<'static, FLASH, Blocking, FLASH_SIZE>>;
+
+/// How many connections can be open at once: one for every player, and a couple more so the
+/// server list can still ping a full server.
+pub const CONNECTIONS: usize = MAX_PLAYERS as usize + 2;
+
+/// Which connections are open, so their socket buffers aren't handed out again.
+static IN_USE: [AtomicBool; CONNECTIONS] = [const { AtomicBool::new(false) }; CONNECTIONS];
+
+/// A connection that isn't open, or `None` if they all are.
+pub fn free_slot() -> Option<usize> {
+    IN_USE
+        .iter()
+        .position(|in_use| !in_use.load(Ordering::Acquire))
+}
+
+/// Keeps `slot`'s buffers for a connection until it's freed.
+pub fn take_slot(slot: usize) {
+    IN_USE[slot].store(true, Ordering::Release);
+}
+
+/// Lets `slot`'s buffers be used for another connection.
+pub fn free(slot: usize) {
+    IN_USE[slot].store(false, Ordering::Release);
+}
+
+#[embassy_executor::task(pool_size = CONNECTIONS)]
+pub async fn handle_conn(
+    mut socket: TcpSocket<'static>,
+    slot: usize,
+    config: &'static Config,
+    game: &'static Game<Store>,
+    stack: &'static Stack<cyw43::NetDriver<'static>>,
+) {
+    info!("Handling connection");
+
+    let mut connector = NetConnect {
+        stack,
+        rx_buffer: [0; 1024],
+        tx_buffer: [0; 1024],
+    };
+    // A bad packet only takes down the connection it came from
+    if let Err(err) = serve(&mut socket, config, game, &mut Rosc, &mut connector).await {
+        warn!("Closing connection: {}", err);
+    }
+
+    socket.close();
+    let _ = socket.flush().await;
+    drop(socket);
+    free(slot);
+}
+
+/// Reaches the session server over Wi-Fi, looking it up with the DNS server DHCP gave us.
+struct NetConnect {
+    stack: &'static Stack<cyw43::NetDriver<'static>>,
+    rx_buffer: [u8; 1024],
+    tx_buffer: [u8; 1024],
+}
+
+impl Connect for NetConnect {
+    type Connection<'a> = TcpSocket<'a>;
+
+    async fn connect(&mut self, host: &str, port: u16) -> Result<TcpSocket<'_>, ErrorKind> {
+        let addresses = self
+            .stack
+            .dns_query(host, DnsQueryType::A)
+            .await
+            .map_err(|_| ErrorKind::NotFound)?;
+        let address = *addresses.first().ok_or(ErrorKind::NotFound)?;
+
+        let mut socket = TcpSocket::new(self.stack, &mut self.rx_buffer, &mut self.tx_buffer);
+        socket.set_timeout(Some(Duration::from_secs(10)));
+        socket
+            .connect((address, port))
+            .await
+            .map_err(|_| ErrorKind::ConnectionRefused)?;
+        Ok(socket)
+    }
+}
@@ -0,0 +1,30 @@
+//! Random numbers for keys and verify tokens.
+
+use embassy_rp::clocks::RoscRng;
+use rand::{CryptoRng, RngCore};
+
+/// [`RoscRng`], trusted to be good enough for cryptography.
+///
+/// Jitter in the ring oscillator is the only entropy the RP2040 has. embassy doesn't promise it's
+/// secure, but there's nothing better to use, and the key pair only lasts until the next boot.
+pub struct Rosc;
+
+impl RngCore for Rosc {
+    fn next_u32(&mut self) -> u32 {
+        RoscRng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        RoscRng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        RoscRng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        RoscRng.try_fill_bytes(dest)
+    }
+}
+
+impl CryptoRng for Rosc {}
@@ -0,0 +1,101 @@
+//! Generates the packet id tables from `data/packets.txt`.
+//!
+//! The data files are plain text so they are easy to update from vanilla's reports, while the
+//! tables they turn into are small enough to keep in flash.
+
+use std::{env, fmt::Write, fs, path::PathBuf};
+
+fn main() {
+    println!("cargo:rerun-if-changed=data/packets.txt");
+
+    let data = fs::read_to_string("data/packets.txt").unwrap();
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::write(out.join("packet_ids.rs"), packet_ids(&data)).unwrap();
+}
+
+fn packet_ids(data: &str) -> String {
+    let mut lines = data
+        .lines()
+        .enumerate()
+        .map(|(number, line)| (number + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    let (_, header) = lines.next().expect("packets.txt has no header");
+    let versions: Vec<i32> = header
+        .split_whitespace()
+        .skip(3)
+        .map(|version| version.parse().expect("invalid protocol version"))
+        .collect();
+    assert!(!versions.is_empty(), "packets.txt has no versions");
+
+    let mut rows = Vec::new();
+    for (number, line) in lines {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        assert_eq!(
+            fields.len(),
+            versions.len() + 3,
+            "packets.txt:{}: expected an id for every version",
+            number
+        );
+
+        let state = match fields[0] {
+            "handshake" => "Handshake",
+            "status" => "Status",
+            "login" => "Login",
+            "configuration" => "Configuration",
+            "play" => "Play",
+            state => panic!("packets.txt:{}: unknown state {}", number, state),
+        };
+        let bound = match fields[1] {
+            "client" => "Client",
+            "server" => "Server",
+            bound => panic!("packets.txt:{}: unknown bound {}", number, bound),
+        };
+        let ids: Vec<i32> = fields[3..]
+            .iter()
+            .map(|id| match *id {
+                "-" => -1,
+                id => i32::from_str_radix(id.trim_start_matches("0x"), 16)
+                    .unwrap_or_else(|_| panic!("packets.txt:{}: invalid id {}", number, id)),
+            })
+            .collect();
+        assert!(
+            ids[0] >= 0,
+            "packets.txt:{}: packets need an id in the first version",
+            number
+        );
+
+        let key = (state, bound, ids[0]);
+        assert!(
+            rows.iter().all(|(other, _)| *other != key),
+            "packets.txt:{}: duplicate packet",
+            number
+        );
+        rows.push((key, ids));
+    }
+
+    let mut code = String::new();
+    writeln!(
+        code,
+        "/// Every protocol version in `data/packets.txt`, in column order."
+    )
+    .unwrap();
+    writeln!(
+        code,
+        "pub const PROTOCOL_VERSIONS: &[i32] = &{:?};",
+        versions
+    )
+    .unwrap();
+    writeln!(code).unwrap();
+    writeln!(
+        code,
+        "static PACKET_IDS: &[(State, Bound, [i32; {}])] = &[",
+        versions.len()
+    )
+    .unwrap();
+    for ((state, bound, _), ids) in rows {
+        writeln!(code, "    (State::{}, Bound::{}, {:?}),", state, bound, ids).unwrap();
+    }
+    writeln!(code, "];").unwrap();
+    code
+}
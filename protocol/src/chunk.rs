@@ -0,0 +1,429 @@
+//! Chunk sections, heightmaps and light, as sent in Chunk Data and Update Light.
+//!
+//! A chunk is around 10 KiB once encoded, too much to build in the Pico's heap, so everything
+//! here is written straight to the socket from a [`ChunkColumn`], which works out each block as
+//! it's asked for. The palettes and light masks are worked out once into a [`ChunkLayout`], which
+//! also gives the lengths the format needs up front without encoding things twice.
+
+use alloc::vec::Vec;
+use embedded_io_async::Write;
+
+use crate::{error::ProtocolError, nbt::NbtWriter, types::BitSet, write::WriteExtension};
+
+/// The width, depth and height of a section, in blocks.
+pub const SECTION_SIZE: usize = 16;
+const BLOCKS_PER_SECTION: usize = SECTION_SIZE * SECTION_SIZE * SECTION_SIZE;
+/// Biomes are stored for each 4×4×4 cell rather than each block.
+const BIOMES_PER_SECTION: usize = 4 * 4 * 4;
+
+/// Bits per block in the global palette, enough for every one of 1.21.1's 26684 block states.
+pub const BLOCK_STATE_BITS: u32 = 15;
+
+/// How many bytes of light there are in a section, half a byte per block.
+pub const LIGHT_ARRAY_LENGTH: usize = BLOCKS_PER_SECTION / 2;
+
+/// A column of chunk sections, from the bottom of the world to the top.
+///
+/// Coordinates are relative to the column, with `y` counting up from the bottom of the world.
+/// Everything is asked for several times while a chunk is sent, so it should be cheap to work
+/// out.
+pub trait ChunkColumn {
+    /// How many sections tall the column is, which has to match the dimension.
+    fn sections(&self) -> usize;
+
+    /// The block state id at `x`, `y`, `z`.
+    fn block(&self, x: usize, y: usize, z: usize) -> u32;
+
+    /// The biome id of the 4×4×4 cell at `x`, `y`, `z`, counted in cells rather than blocks.
+    fn biome(&self, x: usize, y: usize, z: usize) -> u32;
+
+    /// How far above the bottom of the world the top of the highest block at `x`, `z` is, or 0
+    /// if there are none.
+    fn height(&self, x: usize, z: usize) -> usize;
+
+    /// Whether `block` is one of the airs, which aren't counted as blocks in a section.
+    fn is_air(&self, block: u32) -> bool {
+        block == 0
+    }
+
+    /// The sky light at `x`, `y`, `z`, from 0 to 15.
+    ///
+    /// `y` may be up to a section below or above the column, as light is sent for those too. By
+    /// default there is full light above the highest block and none below, which is right
+    /// everywhere but under overhangs.
+    fn sky_light(&self, x: usize, y: i32, z: usize) -> u8 {
+        if y >= self.height(x, z) as i32 {
+            15
+        } else {
+            0
+        }
+    }
+
+    /// The light from blocks like torches at `x`, `y`, `z`, from 0 to 15.
+    fn block_light(&self, x: usize, y: i32, z: usize) -> u8 {
+        let _ = (x, y, z);
+        0
+    }
+}
+
+/// How one kind of value is packed into a [`PalettedContainer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContainerKind {
+    /// How many values there are in a section.
+    entries: usize,
+    /// The fewest bits an indirect palette uses.
+    min_bits: u32,
+    /// The most bits an indirect palette uses, past which values are stored directly.
+    max_bits: u32,
+    /// The bits each value takes when stored directly.
+    direct_bits: u32,
+}
+
+impl ContainerKind {
+    pub const BLOCKS: ContainerKind = ContainerKind {
+        entries: BLOCKS_PER_SECTION,
+        min_bits: 4,
+        max_bits: 8,
+        direct_bits: BLOCK_STATE_BITS,
+    };
+
+    /// Biomes, when the client was sent `count` of them in the registry.
+    pub const fn biomes(count: usize) -> ContainerKind {
+        ContainerKind {
+            entries: BIOMES_PER_SECTION,
+            min_bits: 1,
+            max_bits: 3,
+            direct_bits: bits_for(count),
+        }
+    }
+}
+
+/// How many bits it takes to tell `count` values apart.
+const fn bits_for(count: usize) -> u32 {
+    if count <= 1 {
+        0
+    } else {
+        usize::BITS - (count - 1).leading_zeros()
+    }
+}
+
+/// The palette for one section's worth of values, picked by looking at every value.
+///
+/// The values themselves aren't kept, they're asked for again when the container is written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PalettedContainer {
+    /// Empty when the values are stored directly.
+    palette: Vec<u32>,
+    bits: u32,
+    entries: usize,
+}
+
+impl PalettedContainer {
+    /// Picks the smallest palette for the `kind.entries` values given by `value`, indexed in
+    /// `y`, `z`, `x` order.
+    pub fn new(kind: ContainerKind, value: impl Fn(usize) -> u32) -> PalettedContainer {
+        let mut palette = Vec::new();
+        for index in 0..kind.entries {
+            let value = value(index);
+            if !palette.contains(&value) {
+                palette.push(value);
+                if palette.len() > 1 << kind.max_bits {
+                    return PalettedContainer {
+                        palette: Vec::new(),
+                        bits: kind.direct_bits,
+                        entries: kind.entries,
+                    };
+                }
+            }
+        }
+
+        let bits = match palette.len() {
+            1 => 0,
+            len => bits_for(len).max(kind.min_bits),
+        };
+        PalettedContainer {
+            palette,
+            bits,
+            entries: kind.entries,
+        }
+    }
+
+    /// How many bits each value takes, 0 when there's only one value.
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// The values in the palette, empty when they're stored directly.
+    pub fn palette(&self) -> &[u32] {
+        &self.palette
+    }
+
+    /// How many longs the values take, which never straddle two longs.
+    fn longs(&self) -> usize {
+        if self.bits == 0 {
+            return 0;
+        }
+        let per_long = 64 / self.bits as usize;
+        self.entries.div_ceil(per_long)
+    }
+
+    /// How many bytes [`PalettedContainer::write`] writes.
+    pub fn encoded_len(&self) -> usize {
+        let palette = match self.bits {
+            0 => varint_len(self.palette[0] as i32),
+            _ if self.palette.is_empty() => 0,
+            _ => {
+                varint_len(self.palette.len() as i32)
+                    + self
+                        .palette
+                        .iter()
+                        .map(|value| varint_len(*value as i32))
+                        .sum::<usize>()
+            }
+        };
+        1 + palette + varint_len(self.longs() as i32) + 8 * self.longs()
+    }
+
+    /// Writes the container, asking `value` for the same values it was made from.
+    pub async fn write<W: Write + ?Sized>(
+        &self,
+        socket: &mut W,
+        value: impl Fn(usize) -> u32,
+    ) -> Result<(), ProtocolError> {
+        socket.write_u8(self.bits as u8).await?;
+        if self.bits == 0 {
+            socket.write_varint(self.palette[0] as i32).await?;
+            return socket.write_varint(0).await;
+        }
+
+        if !self.palette.is_empty() {
+            socket.write_varint(self.palette.len() as i32).await?;
+            for value in &self.palette {
+                socket.write_varint(*value as i32).await?;
+            }
+        }
+
+        socket.write_varint(self.longs() as i32).await?;
+        let per_long = 64 / self.bits as usize;
+        for start in (0..self.entries).step_by(per_long) {
+            let mut long = 0u64;
+            for (i, index) in (start..self.entries.min(start + per_long)).enumerate() {
+                let value = value(index);
+                let entry = match self.palette.iter().position(|v| *v == value) {
+                    Some(entry) => entry as u64,
+                    None if self.palette.is_empty() => value as u64,
+                    // The column gave a different value than when the palette was made
+                    None => return Err(ProtocolError::InvalidEnumValue(value as i32)),
+                };
+                long |= entry << (i * self.bits as usize);
+            }
+            socket.write_u64(long).await?;
+        }
+        Ok(())
+    }
+}
+
+fn varint_len(value: i32) -> usize {
+    let bits = 32 - (value as u32).leading_zeros() as usize;
+    bits.div_ceil(7).max(1)
+}
+
+/// One section's containers and how many blocks in it aren't air.
+#[derive(Debug, Clone)]
+struct Section {
+    blocks: PalettedContainer,
+    biomes: PalettedContainer,
+    block_count: i16,
+}
+
+fn block_index<C: ChunkColumn + ?Sized>(column: &C, section: usize, index: usize) -> u32 {
+    let (x, z, y) = (index % 16, index / 16 % 16, index / 256);
+    column.block(x, section * SECTION_SIZE + y, z)
+}
+
+fn biome_index<C: ChunkColumn + ?Sized>(column: &C, section: usize, index: usize) -> u32 {
+    let (x, z, y) = (index % 4, index / 4 % 4, index / 16);
+    column.biome(x, section * 4 + y, z)
+}
+
+/// What has to be worked out from a column before it's written: each section's palettes, and
+/// which sections need a light array.
+///
+/// Packets are written more than once, to measure and compress them, so this is worked out once
+/// per chunk and shared by every pass, leaving only the values themselves to be asked for again.
+#[derive(Debug, Clone)]
+pub struct ChunkLayout {
+    sections: Vec<Section>,
+    /// The sections with sky light and those without any, see [`light_masks`].
+    sky: (BitSet, BitSet),
+    block: (BitSet, BitSet),
+}
+
+impl ChunkLayout {
+    /// Looks over every section of `column`.
+    ///
+    /// `biomes` is how many biomes the client was sent, which sets how wide directly stored biome
+    /// ids are.
+    pub fn new<C: ChunkColumn + ?Sized>(column: &C, biomes: usize) -> ChunkLayout {
+        let sections = (0..column.sections())
+            .map(|section| {
+                let block = |index| block_index(column, section, index);
+                let block_count = (0..BLOCKS_PER_SECTION)
+                    .filter(|index| !column.is_air(block(*index)))
+                    .count();
+                Section {
+                    blocks: PalettedContainer::new(ContainerKind::BLOCKS, block),
+                    biomes: PalettedContainer::new(ContainerKind::biomes(biomes), |index| {
+                        biome_index(column, section, index)
+                    }),
+                    block_count: block_count as i16,
+                }
+            })
+            .collect();
+        ChunkLayout {
+            sections,
+            sky: light_masks(column, Light::Sky),
+            block: light_masks(column, Light::Block),
+        }
+    }
+}
+
+/// Writes every section of `column`, laid out by `layout`, prefixed with their length in bytes.
+pub async fn write_sections<W: Write + ?Sized, C: ChunkColumn + ?Sized>(
+    socket: &mut W,
+    column: &C,
+    layout: &ChunkLayout,
+) -> Result<(), ProtocolError> {
+    let length: usize = layout
+        .sections
+        .iter()
+        .map(|section| 2 + section.blocks.encoded_len() + section.biomes.encoded_len())
+        .sum();
+    socket.write_varint(length as i32).await?;
+
+    for (i, section) in layout.sections.iter().enumerate() {
+        socket.write_i16(section.block_count).await?;
+        section
+            .blocks
+            .write(socket, |index| block_index(column, i, index))
+            .await?;
+        section
+            .biomes
+            .write(socket, |index| biome_index(column, i, index))
+            .await?;
+    }
+    Ok(())
+}
+
+/// Writes the heightmaps the client needs, as network NBT.
+pub async fn write_heightmaps<W: Write + ?Sized, C: ChunkColumn + ?Sized>(
+    socket: &mut W,
+    column: &C,
+) -> Result<(), ProtocolError> {
+    // Heights go from 0 to the top of the world inclusive
+    let bits = bits_for(column.sections() * SECTION_SIZE + 1) as usize;
+    let per_long = 64 / bits;
+    let mut heights = Vec::with_capacity(256usize.div_ceil(per_long));
+    for start in (0..256).step_by(per_long) {
+        let mut long = 0u64;
+        for (i, index) in (start..256.min(start + per_long)).enumerate() {
+            long |= (column.height(index % 16, index / 16) as u64) << (i * bits);
+        }
+        heights.push(long as i64);
+    }
+
+    let mut nbt = NbtWriter::network(socket).await?;
+    // We don't tell apart blocks that stop movement from any that aren't air
+    nbt.field("MOTION_BLOCKING", &heights[..]).await?;
+    nbt.field("WORLD_SURFACE", &heights[..]).await?;
+    nbt.end().await
+}
+
+/// Which light a light array holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Light {
+    Sky,
+    Block,
+}
+
+fn light_at<C: ChunkColumn + ?Sized>(column: &C, light: Light, section: i32, index: usize) -> u8 {
+    let (x, z, y) = (index % 16, index / 16 % 16, index / 256);
+    let y = section * SECTION_SIZE as i32 + y as i32;
+    match light {
+        Light::Sky => column.sky_light(x, y, z),
+        Light::Block => column.block_light(x, y, z),
+    }
+}
+
+/// Which sections need a light array, and which have no light at all.
+///
+/// The client takes sky light to be full in sections above the highest one it has an array for,
+/// so those are left out of both masks, which saves sending 2 KiB of 15s for each of them.
+fn light_masks<C: ChunkColumn + ?Sized>(column: &C, light: Light) -> (BitSet, BitSet) {
+    // The one level all of each section is at, if there is one
+    let levels: Vec<Option<u8>> = (0..column.sections() + 2)
+        .map(|bit| {
+            let section = bit as i32 - 1;
+            let mut levels =
+                (0..BLOCKS_PER_SECTION).map(|index| light_at(column, light, section, index));
+            let first = levels.next().unwrap_or(0);
+            levels.all(|level| level == first).then_some(first)
+        })
+        .collect();
+
+    let mut top = levels.len();
+    if light == Light::Sky {
+        while top > 0 && levels[top - 1] == Some(15) {
+            top -= 1;
+        }
+    }
+
+    let mut lit = BitSet::new();
+    let mut dark = BitSet::new();
+    for (bit, level) in levels[..top].iter().enumerate() {
+        if *level == Some(0) {
+            dark.set(bit, true);
+        } else {
+            lit.set(bit, true);
+        }
+    }
+    (lit, dark)
+}
+
+/// Writes the light masks and arrays, from the sky light mask to the block light arrays.
+///
+/// Sections without any light are marked as empty in `layout`, so they don't need an array
+/// either.
+pub async fn write_light<W: Write + ?Sized, C: ChunkColumn + ?Sized>(
+    socket: &mut W,
+    column: &C,
+    layout: &ChunkLayout,
+) -> Result<(), ProtocolError> {
+    let ((sky, dark_sky), (block, dark_block)) = (&layout.sky, &layout.block);
+    for mask in [sky, block, dark_sky, dark_block] {
+        socket.write_bitset(mask).await?;
+    }
+
+    for (light, mask) in [(Light::Sky, sky), (Light::Block, block)] {
+        let sections: Vec<usize> = (0..column.sections() + 2)
+            .filter(|bit| mask.get(*bit))
+            .collect();
+        socket.write_varint(sections.len() as i32).await?;
+
+        for bit in sections {
+            let section = bit as i32 - 1;
+            socket.write_varint(LIGHT_ARRAY_LENGTH as i32).await?;
+            let mut chunk = [0; 64];
+            for start in (0..LIGHT_ARRAY_LENGTH).step_by(chunk.len()) {
+                for (i, byte) in chunk.iter_mut().enumerate() {
+                    let index = 2 * (start + i);
+                    // The first block of each pair is in the low half of the byte
+                    *byte = light_at(column, light, section, index)
+                        | light_at(column, light, section, index + 1) << 4;
+                }
+                socket.write_byte_array(&chunk).await?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,257 @@
+//! zlib compression for packets, once Set Compression has been sent.
+//!
+//! Inflating uses `miniz_oxide`, which needs about 11 KiB while a packet is being decompressed.
+//! Its compressor needs over 200 KiB though, which is most of the Pico's RAM, so packets are
+//! compressed by [`Deflater`] instead. That only uses fixed Huffman codes and a small window,
+//! which compresses worse than zlib but fits in about 10 KiB and can be streamed.
+
+use alloc::{boxed::Box, vec, vec::Vec};
+use embedded_io_async::{ErrorType, Write};
+use miniz_oxide::inflate::{
+    core::{decompress, inflate_flags, DecompressorOxide},
+    TINFLStatus,
+};
+
+use crate::error::ProtocolError;
+
+/// How far back the compressor looks for repeats. zlib looks back 32 KiB, which would need
+/// much more RAM, and packets are rarely that long anyway.
+const WINDOW: usize = 4096;
+const HASH_BITS: u32 = 10;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Decompresses a zlib stream that should come out as exactly `length` bytes.
+pub fn inflate(data: &[u8], length: usize) -> Result<Vec<u8>, ProtocolError> {
+    let mut out = vec![0; length];
+    let mut decompressor = Box::<DecompressorOxide>::default();
+    let flags = inflate_flags::TINFL_FLAG_PARSE_ZLIB_HEADER
+        | inflate_flags::TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF;
+
+    let (status, _, written) = decompress(&mut decompressor, data, &mut out, 0, flags);
+    if status != TINFLStatus::Done || written != length {
+        return Err(ProtocolError::InvalidCompression);
+    }
+    Ok(out)
+}
+
+/// Compresses everything written to it into a zlib stream on `inner`.
+///
+/// The stream is only complete once [`Deflater::finish`] has been called.
+pub struct Deflater<'a, W: Write> {
+    inner: &'a mut W,
+    /// The last [`WINDOW`] bytes that were compressed, followed by the ones waiting to be.
+    buf: Box<[u8]>,
+    pos: usize,
+    end: usize,
+    /// Where in `buf` each hash of three bytes was last seen, plus one so zero means never.
+    head: Box<[u16]>,
+    bits: u32,
+    bit_count: u32,
+    out: [u8; 64],
+    out_len: usize,
+    adler: (u32, u32),
+}
+
+impl<'a, W: Write> Deflater<'a, W> {
+    pub fn new(inner: &'a mut W) -> Deflater<'a, W> {
+        let mut deflater = Deflater {
+            inner,
+            buf: vec![0; 2 * WINDOW].into_boxed_slice(),
+            pos: 0,
+            end: 0,
+            head: vec![0; 1 << HASH_BITS].into_boxed_slice(),
+            bits: 0,
+            bit_count: 0,
+            out: [0; 64],
+            out_len: 0,
+            adler: (1, 0),
+        };
+
+        // Deflate with a 32 KiB window and no preset dictionary, as zlib writes it
+        deflater.out[..2].copy_from_slice(&[0x78, 0x01]);
+        deflater.out_len = 2;
+        // Everything goes in one final block with fixed codes
+        deflater.put_bits(1, 1);
+        deflater.put_bits(1, 2);
+        deflater
+    }
+
+    /// Compresses whatever is left and ends the stream.
+    pub async fn finish(mut self) -> Result<(), W::Error> {
+        self.compress(true).await?;
+        self.flush_out().await?;
+        self.put_code(256);
+        if self.bit_count > 0 {
+            self.push_byte(self.bits as u8);
+            self.bits = 0;
+            self.bit_count = 0;
+        }
+
+        let (a, b) = self.adler;
+        for byte in ((b << 16) | a).to_be_bytes() {
+            self.push_byte(byte);
+        }
+        self.flush_out().await
+    }
+
+    /// Compresses the buffered bytes, keeping enough back for a full match unless `finish`.
+    async fn compress(&mut self, finish: bool) -> Result<(), W::Error> {
+        while self.pos < self.end && (finish || self.end - self.pos >= MAX_MATCH) {
+            // The longest symbol is 31 bits, so make sure there's room for a few bytes
+            if self.out_len > self.out.len() - 8 {
+                self.flush_out().await?;
+            }
+
+            let (length, distance) = self.find_match();
+            if length >= MIN_MATCH {
+                self.put_match(length, distance);
+                for pos in self.pos + 1..self.pos + length {
+                    self.insert(pos);
+                }
+                self.pos += length;
+            } else {
+                self.put_code(self.buf[self.pos] as u16);
+                self.pos += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn hash(&self, pos: usize) -> usize {
+        let bytes = u32::from_be_bytes([0, self.buf[pos], self.buf[pos + 1], self.buf[pos + 2]]);
+        (bytes.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+    }
+
+    /// Remembers where the three bytes at `pos` were seen, returning where they were last seen.
+    fn insert(&mut self, pos: usize) -> Option<usize> {
+        if pos + MIN_MATCH > self.end {
+            return None;
+        }
+        let hash = self.hash(pos);
+        let last = self.head[hash];
+        self.head[hash] = pos as u16 + 1;
+        last.checked_sub(1).map(usize::from)
+    }
+
+    /// The length and distance of a repeat of the bytes at `pos`, if there is one.
+    fn find_match(&mut self) -> (usize, usize) {
+        let Some(candidate) = self.insert(self.pos) else {
+            return (0, 0);
+        };
+
+        let max = (self.end - self.pos).min(MAX_MATCH);
+        let length = (0..max)
+            .take_while(|i| self.buf[candidate + i] == self.buf[self.pos + i])
+            .count();
+        (length, self.pos - candidate)
+    }
+
+    fn put_bits(&mut self, value: u32, count: u32) {
+        self.bits |= value << self.bit_count;
+        self.bit_count += count;
+        while self.bit_count >= 8 {
+            self.push_byte(self.bits as u8);
+            self.bits >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    /// Writes a literal/length code from the fixed Huffman table.
+    fn put_code(&mut self, code: u16) {
+        let (huffman, length) = match code {
+            0..=143 => (0x30 + code as u32, 8),
+            144..=255 => (0x190 + code as u32 - 144, 9),
+            256..=279 => (code as u32 - 256, 7),
+            _ => (0xc0 + code as u32 - 280, 8),
+        };
+        // Huffman codes are the only thing packed starting from their most significant bit
+        self.put_bits(huffman.reverse_bits() >> (32 - length), length);
+    }
+
+    fn put_match(&mut self, length: usize, distance: usize) {
+        let index = LENGTH_BASE
+            .iter()
+            .rposition(|base| *base as usize <= length)
+            .unwrap();
+        self.put_code(257 + index as u16);
+        self.put_bits(
+            (length - LENGTH_BASE[index] as usize) as u32,
+            LENGTH_EXTRA[index] as u32,
+        );
+
+        let index = DISTANCE_BASE
+            .iter()
+            .rposition(|base| *base as usize <= distance)
+            .unwrap();
+        self.put_bits((index as u32).reverse_bits() >> 27, 5);
+        self.put_bits(
+            (distance - DISTANCE_BASE[index] as usize) as u32,
+            DISTANCE_EXTRA[index] as u32,
+        );
+    }
+
+    fn push_byte(&mut self, byte: u8) {
+        self.out[self.out_len] = byte;
+        self.out_len += 1;
+    }
+
+    async fn flush_out(&mut self) -> Result<(), W::Error> {
+        self.inner.write_all(&self.out[..self.out_len]).await?;
+        self.out_len = 0;
+        Ok(())
+    }
+
+    /// Drops the oldest half of the buffer to make room for more input.
+    fn slide(&mut self) {
+        self.buf.copy_within(WINDOW.., 0);
+        self.pos -= WINDOW;
+        self.end -= WINDOW;
+        for head in self.head.iter_mut() {
+            *head = head.saturating_sub(WINDOW as u16);
+        }
+    }
+}
+
+impl<W: Write> ErrorType for Deflater<'_, W> {
+    type Error = W::Error;
+}
+
+impl<W: Write> Write for Deflater<'_, W> {
+    async fn write(&mut self, data: &[u8]) -> Result<usize, Self::Error> {
+        // Compressing leaves less than a match behind, so there's always a window to drop
+        if self.end == self.buf.len() {
+            self.slide();
+        }
+
+        let len = data.len().min(self.buf.len() - self.end);
+        self.buf[self.end..self.end + len].copy_from_slice(&data[..len]);
+        self.end += len;
+
+        let (mut a, mut b) = self.adler;
+        for byte in &data[..len] {
+            a = (a + *byte as u32) % 65521;
+            b = (b + a) % 65521;
+        }
+        self.adler = (a, b);
+
+        self.compress(false).await?;
+        Ok(len)
+    }
+}
@@ -0,0 +1,87 @@
+//! AES-128-CFB8, which the whole connection is encrypted with after the Encryption Response.
+//!
+//! The shared secret the client picks is both the key and the IV, and each direction keeps its
+//! own cipher state for the rest of the connection.
+
+use aes::Aes128;
+use cfb8::cipher::{generic_array::GenericArray, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use embedded_io_async::{ErrorType, Read, Write};
+
+/// The length of the shared secret, which vanilla always makes 16 bytes.
+pub const SECRET_LENGTH: usize = 16;
+
+/// Encrypts everything written to and decrypts everything read from `inner`, once it has been
+/// turned on with [`CipherStream::enable`].
+///
+/// Until then bytes go through untouched, so a connection can be wrapped from the start.
+pub struct CipherStream<S> {
+    inner: S,
+    ciphers: Option<(cfb8::Encryptor<Aes128>, cfb8::Decryptor<Aes128>)>,
+}
+
+impl<S> CipherStream<S> {
+    pub fn new(inner: S) -> CipherStream<S> {
+        CipherStream {
+            inner,
+            ciphers: None,
+        }
+    }
+
+    /// Encrypts the connection with `secret` from now on.
+    pub fn enable(&mut self, secret: &[u8; SECRET_LENGTH]) {
+        let key = GenericArray::from_slice(secret);
+        self.ciphers = Some((
+            cfb8::Encryptor::new(key, key),
+            cfb8::Decryptor::new(key, key),
+        ));
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.ciphers.is_some()
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: ErrorType> ErrorType for CipherStream<S> {
+    type Error = S::Error;
+}
+
+impl<S: Read> Read for CipherStream<S> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let read = self.inner.read(buf).await?;
+        if let Some((_, decryptor)) = &mut self.ciphers {
+            // CFB8 works a byte at a time, so there's never a partial block to keep around
+            for byte in &mut buf[..read] {
+                decryptor
+                    .decrypt_block_mut(GenericArray::from_mut_slice(core::slice::from_mut(byte)));
+            }
+        }
+        Ok(read)
+    }
+}
+
+impl<S: Write> Write for CipherStream<S> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let Some((encryptor, _)) = &mut self.ciphers else {
+            return self.inner.write(buf).await;
+        };
+
+        // The cipher has moved on once a byte is encrypted, so everything encrypted has to be
+        // written before returning
+        let mut encrypted = [0; 64];
+        let len = buf.len().min(encrypted.len());
+        encrypted[..len].copy_from_slice(&buf[..len]);
+        for byte in &mut encrypted[..len] {
+            encryptor.encrypt_block_mut(GenericArray::from_mut_slice(core::slice::from_mut(byte)));
+        }
+        self.inner.write_all(&encrypted[..len]).await?;
+        Ok(len)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush().await
+    }
+}
@@ -0,0 +1,88 @@
+use core::fmt;
+
+use embedded_io_async::{Error, ErrorKind, ReadExactError};
+
+/// Everything that can go wrong while reading or writing packets.
+///
+/// None of these are fatal for the server, they only mean the connection they happened on should
+/// be closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolError {
+    /// The connection or packet ended in the middle of a value.
+    UnexpectedEof,
+    /// A VarInt or VarLong had more bytes than it is allowed to.
+    VarIntTooLong,
+    /// A string was not valid UTF-8.
+    InvalidUtf8,
+    /// A string, or its length prefix, was longer than the maximum allowed.
+    StringTooLong { length: i32, max: usize },
+    /// A frame's length prefix was negative or longer than the maximum allowed.
+    PacketTooLong(i32),
+    /// An array's length prefix was negative or longer than the maximum allowed.
+    InvalidLength(i32),
+    /// An identifier had characters that aren't allowed in it.
+    InvalidIdentifier,
+    /// There is no packet with this id in the current state.
+    UnknownPacketId(i32),
+    /// A field that should be one of a fixed set of values wasn't.
+    InvalidEnumValue(i32),
+    /// A JSON field couldn't be serialized or deserialized.
+    InvalidJson,
+    /// An NBT tag type was unknown, or not allowed where it was used.
+    InvalidNbtTag(u8),
+    /// NBT compounds and lists were nested deeper than [`MAX_DEPTH`](crate::nbt::MAX_DEPTH).
+    NbtTooDeep,
+    /// A compressed packet couldn't be decompressed, or was too short to have been compressed.
+    InvalidCompression,
+    /// The shared secret or verify token in an Encryption Response didn't decrypt, or the token
+    /// wasn't the one the client was sent.
+    InvalidEncryption,
+    /// The connection was closed cleanly, between two packets.
+    ConnectionClosed,
+    /// The underlying transport failed.
+    Io(ErrorKind),
+}
+
+impl ProtocolError {
+    /// Wraps a transport error, for use with `map_err`.
+    pub fn io<E: Error>(err: E) -> ProtocolError {
+        ProtocolError::Io(err.kind())
+    }
+}
+
+impl<E: Error> From<ReadExactError<E>> for ProtocolError {
+    fn from(err: ReadExactError<E>) -> Self {
+        match err {
+            ReadExactError::UnexpectedEof => ProtocolError::UnexpectedEof,
+            ReadExactError::Other(err) => ProtocolError::io(err),
+        }
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::UnexpectedEof => write!(f, "unexpected end of data"),
+            ProtocolError::VarIntTooLong => write!(f, "VarInt is too long"),
+            ProtocolError::InvalidUtf8 => write!(f, "string is not valid UTF-8"),
+            ProtocolError::StringTooLong { length, max } => {
+                write!(f, "string of length {} is longer than {}", length, max)
+            }
+            ProtocolError::PacketTooLong(length) => write!(f, "invalid packet length {}", length),
+            ProtocolError::InvalidLength(length) => write!(f, "invalid array length {}", length),
+            ProtocolError::InvalidIdentifier => write!(f, "invalid identifier"),
+            ProtocolError::UnknownPacketId(id) => write!(f, "unknown packet id {:#04x}", id),
+            ProtocolError::InvalidEnumValue(value) => write!(f, "invalid enum value {}", value),
+            ProtocolError::InvalidJson => write!(f, "invalid JSON"),
+            ProtocolError::InvalidNbtTag(tag) => write!(f, "invalid NBT tag {}", tag),
+            ProtocolError::NbtTooDeep => write!(f, "NBT is nested too deep"),
+            ProtocolError::InvalidCompression => write!(f, "badly compressed packet"),
+            ProtocolError::InvalidEncryption => write!(f, "encryption handshake failed"),
+            ProtocolError::ConnectionClosed => write!(f, "connection closed"),
+            ProtocolError::Io(kind) => write!(f, "I/O error: {:?}", kind),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ProtocolError {}
@@ -0,0 +1,265 @@
+use alloc::vec::Vec;
+use embedded_io_async::{ErrorType, Read, Write};
+
+use crate::{
+    compression::{inflate, Deflater},
+    error::ProtocolError,
+    packets::{Packet, PacketInfo, WritePacket, MAX_PACKET_LENGTH},
+    read::{ReadExtension, Slice},
+    registry::packet_id,
+    write::WriteExtension,
+};
+
+/// Writes `packet` uncompressed, with its length prefix and its id in `protocol_version`.
+pub async fn write_frame<W: Write, P: WritePacket + PacketInfo>(
+    socket: &mut W,
+    protocol_version: i32,
+    packet: &P,
+) -> Result<(), ProtocolError> {
+    FrameEncoder::new(protocol_version)
+        .write_packet(socket, packet)
+        .await
+}
+
+/// Turns packets into frames for one connection, the other way to [`FrameDecoder`].
+///
+/// Packets are written twice, once to measure them and once for real, so nothing has to be
+/// buffered on the heap no matter how big they are. Compressed packets are compressed twice for
+/// the same reason. Anything a packet has to work out before it's written, like a chunk's
+/// [`ChunkLayout`](crate::chunk::ChunkLayout), is worked out before then so it's shared by every
+/// pass.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameEncoder {
+    protocol_version: i32,
+    compression: Option<usize>,
+}
+
+impl FrameEncoder {
+    pub fn new(protocol_version: i32) -> FrameEncoder {
+        FrameEncoder {
+            protocol_version,
+            compression: None,
+        }
+    }
+
+    pub fn protocol_version(&self) -> i32 {
+        self.protocol_version
+    }
+
+    /// Compresses packets of at least `threshold` bytes from now on, which starts right after
+    /// Set Compression.
+    pub fn set_compression(&mut self, threshold: Option<usize>) {
+        self.compression = threshold;
+    }
+
+    pub async fn write_packet<W: Write, P: WritePacket + PacketInfo>(
+        &self,
+        socket: &mut W,
+        packet: &P,
+    ) -> Result<(), ProtocolError> {
+        let Some(id) = packet_id::<P>(self.protocol_version) else {
+            return Err(ProtocolError::UnknownPacketId(P::ID));
+        };
+
+        let mut counter = Counter(0);
+        counter.write_varint(id).await?;
+        packet.write_packet(&mut counter).await?;
+        let length = counter.0;
+
+        match self.compression {
+            None => {
+                check_length(length)?;
+                socket.write_varint(length as i32).await?;
+                socket.write_varint(id).await?;
+                packet.write_packet(socket).await
+            }
+            // Too short to be worth it, so it goes after a data length of 0
+            Some(threshold) if length < threshold => {
+                check_length(length + 1)?;
+                socket.write_varint(length as i32 + 1).await?;
+                socket.write_u8(0).await?;
+                socket.write_varint(id).await?;
+                packet.write_packet(socket).await
+            }
+            Some(_) => {
+                let mut counter = Counter(0);
+                counter.write_varint(length as i32).await?;
+                deflate(&mut counter, id, packet).await?;
+                check_length(counter.0)?;
+
+                socket.write_varint(counter.0 as i32).await?;
+                socket.write_varint(length as i32).await?;
+                deflate(socket, id, packet).await
+            }
+        }
+    }
+}
+
+fn check_length(length: usize) -> Result<(), ProtocolError> {
+    if length > MAX_PACKET_LENGTH as usize {
+        return Err(ProtocolError::PacketTooLong(length as i32));
+    }
+    Ok(())
+}
+
+async fn deflate<W: Write, P: WritePacket>(
+    socket: &mut W,
+    id: i32,
+    packet: &P,
+) -> Result<(), ProtocolError> {
+    let mut deflater = Deflater::new(socket);
+    deflater.write_varint(id).await?;
+    packet.write_packet(&mut deflater).await?;
+    deflater.finish().await.map_err(ProtocolError::io)
+}
+
+/// A writer that throws away everything, only keeping count of how much was written.
+pub(crate) struct Counter(pub usize);
+
+impl ErrorType for Counter {
+    type Error = core::convert::Infallible;
+}
+
+impl Write for Counter {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+}
+
+/// Splits a connection's byte stream into length-prefixed frames.
+///
+/// TCP gives no guarantees about how the stream is split up, so a frame can arrive across
+/// several reads, or several frames can arrive in one. Bytes are buffered until a whole frame is
+/// available. Use one decoder per connection.
+#[derive(Debug)]
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+    compression: Option<usize>,
+    /// The longest a packet can be, before or after decompressing.
+    max_length: usize,
+}
+
+impl Default for FrameDecoder {
+    fn default() -> FrameDecoder {
+        FrameDecoder::new()
+    }
+}
+
+impl FrameDecoder {
+    /// A decoder that allows packets up to [`MAX_PACKET_LENGTH`], like vanilla.
+    pub fn new() -> FrameDecoder {
+        FrameDecoder {
+            buf: Vec::new(),
+            compression: None,
+            max_length: MAX_PACKET_LENGTH as usize,
+        }
+    }
+
+    /// Rejects packets longer than `max_length` bytes from now on, whether they're compressed or
+    /// not, so the buffer never has to grow much past it.
+    ///
+    /// Vanilla allows up to [`MAX_PACKET_LENGTH`], which is far more than a small device has.
+    pub fn set_max_length(&mut self, max_length: usize) {
+        self.max_length = max_length.min(MAX_PACKET_LENGTH as usize);
+    }
+
+    /// Expects frames to be in the compressed format from now on, with packets of at least
+    /// `threshold` bytes compressed.
+    pub fn set_compression(&mut self, threshold: Option<usize>) {
+        self.compression = threshold;
+    }
+
+    /// Adds received bytes to the end of the buffer.
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Takes the next frame out of the buffer, or returns `None` if it hasn't fully arrived yet.
+    pub async fn next_frame(&mut self) -> Result<Option<Packet>, ProtocolError> {
+        let Some((length, header)) = self.frame_length()? else {
+            return Ok(None);
+        };
+        if self.buf.len() < header + length {
+            return Ok(None);
+        }
+
+        let mut frame: Vec<u8> = self.buf.drain(..header + length).skip(header).collect();
+        if let Some(threshold) = self.compression {
+            frame = decompress(frame, threshold, self.max_length).await?;
+        }
+        let mut data = Slice::new(frame.into_boxed_slice());
+        let id = data.read_varint().await?;
+
+        Ok(Some(Packet { id, data }))
+    }
+
+    /// Reads from `socket` until a whole frame is available.
+    ///
+    /// If the connection closes between frames, this returns [`ProtocolError::ConnectionClosed`]
+    /// rather than [`ProtocolError::UnexpectedEof`].
+    pub async fn read_packet<R: Read>(&mut self, socket: &mut R) -> Result<Packet, ProtocolError> {
+        let mut chunk = [0; 64];
+
+        loop {
+            if let Some(packet) = self.next_frame().await? {
+                return Ok(packet);
+            }
+
+            let read = socket.read(&mut chunk).await.map_err(ProtocolError::io)?;
+            if read == 0 && self.buf.is_empty() {
+                return Err(ProtocolError::ConnectionClosed);
+            } else if read == 0 {
+                return Err(ProtocolError::UnexpectedEof);
+            }
+            self.push(&chunk[..read]);
+        }
+    }
+
+    /// Decodes the VarInt length prefix at the start of the buffer, returning the length and the
+    /// size of the prefix, or `None` if the prefix is incomplete.
+    fn frame_length(&self) -> Result<Option<(usize, usize)>, ProtocolError> {
+        let mut result = 0;
+
+        for (i, byte) in self.buf.iter().enumerate() {
+            if i == 5 {
+                return Err(ProtocolError::VarIntTooLong);
+            }
+            result |= ((byte & 0b0111_1111) as i32) << (i * 7);
+            if byte & 0b1000_0000 == 0 {
+                if result < 0 || result as usize > self.max_length {
+                    return Err(ProtocolError::PacketTooLong(result));
+                }
+                return Ok(Some((result as usize, i + 1)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Takes the packet out of a frame in the compressed format, if it's at most `max_length` bytes
+/// once decompressed.
+async fn decompress(
+    frame: Vec<u8>,
+    threshold: usize,
+    max_length: usize,
+) -> Result<Vec<u8>, ProtocolError> {
+    let mut data = frame.as_slice();
+    let length = data.read_varint().await?;
+    let header = frame.len() - data.len();
+
+    if length == 0 {
+        let mut frame = frame;
+        frame.drain(..header);
+        return Ok(frame);
+    }
+    // Vanilla would never compress a packet under the threshold
+    if length < 0 || (length as usize) < threshold {
+        return Err(ProtocolError::InvalidCompression);
+    }
+    // Checked before inflating, which allocates the whole length up front
+    if length as usize > max_length {
+        return Err(ProtocolError::PacketTooLong(length));
+    }
+    inflate(data, length as usize)
+}
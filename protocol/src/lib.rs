@@ -0,0 +1,34 @@
+//! The Minecraft protocol layer of PicoCraft.
+//!
+//! This crate is `no_std` so the firmware can use it, but builds on the host as
+//! well so the codec can be tested without a Pico. Enable the `std` feature to
+//! pull in the standard library.
+
+#![no_std]
+#![allow(async_fn_in_trait)]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+// Lets the code generated by `picocraft-macros` refer to this crate by name from inside it
+extern crate self as picocraft_protocol;
+
+pub use picocraft_macros::McPacket;
+
+pub mod chunk;
+pub mod compression;
+pub mod encryption;
+pub mod error;
+pub mod frame;
+pub mod nbt;
+pub mod packets;
+pub mod read;
+pub mod registry;
+pub mod text;
+pub mod types;
+pub mod write;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use embedded_io_async::{Read, Write};
+}
@@ -0,0 +1,636 @@
+//! [NBT](https://wiki.vg/NBT), the tree format used for registry data, heightmaps and text
+//! components.
+//!
+//! Since 1.20.2 the root tag sent over the network has no name, while files (and older versions of
+//! the protocol) give it one. [`ReadExtension`](crate::read::ReadExtension) and
+//! [`WriteExtension`](crate::write::WriteExtension) handle both, and [`NbtWriter`] writes NBT
+//! straight to a socket without building an [`Nbt`] first.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use embedded_io_async::{Read, Write};
+
+use crate::{
+    error::ProtocolError,
+    read::ReadExtension,
+    types::{ReadType, WriteType},
+    write::WriteExtension,
+};
+
+/// How deep compounds and lists can be nested, the same limit as vanilla.
+pub const MAX_DEPTH: usize = 512;
+
+/// The type of a tag, sent as a byte before its name and payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tag {
+    End = 0,
+    Byte = 1,
+    Short = 2,
+    Int = 3,
+    Long = 4,
+    Float = 5,
+    Double = 6,
+    ByteArray = 7,
+    String = 8,
+    List = 9,
+    Compound = 10,
+    IntArray = 11,
+    LongArray = 12,
+}
+
+impl Tag {
+    pub fn from_id(id: u8) -> Result<Tag, ProtocolError> {
+        Ok(match id {
+            0 => Tag::End,
+            1 => Tag::Byte,
+            2 => Tag::Short,
+            3 => Tag::Int,
+            4 => Tag::Long,
+            5 => Tag::Float,
+            6 => Tag::Double,
+            7 => Tag::ByteArray,
+            8 => Tag::String,
+            9 => Tag::List,
+            10 => Tag::Compound,
+            11 => Tag::IntArray,
+            12 => Tag::LongArray,
+            id => return Err(ProtocolError::InvalidNbtTag(id)),
+        })
+    }
+}
+
+/// Any tag except `End`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Nbt {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    /// Every element must have the same tag.
+    List(Vec<Nbt>),
+    Compound(Compound),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Nbt {
+    pub fn tag(&self) -> Tag {
+        match self {
+            Nbt::Byte(_) => Tag::Byte,
+            Nbt::Short(_) => Tag::Short,
+            Nbt::Int(_) => Tag::Int,
+            Nbt::Long(_) => Tag::Long,
+            Nbt::Float(_) => Tag::Float,
+            Nbt::Double(_) => Tag::Double,
+            Nbt::ByteArray(_) => Tag::ByteArray,
+            Nbt::String(_) => Tag::String,
+            Nbt::List(_) => Tag::List,
+            Nbt::Compound(_) => Tag::Compound,
+            Nbt::IntArray(_) => Tag::IntArray,
+            Nbt::LongArray(_) => Tag::LongArray,
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($ty:ty, $variant:ident) => {
+        impl From<$ty> for Nbt {
+            fn from(value: $ty) -> Self {
+                Nbt::$variant(value.into())
+            }
+        }
+    };
+}
+
+impl_from!(i8, Byte);
+impl_from!(bool, Byte);
+impl_from!(i16, Short);
+impl_from!(i32, Int);
+impl_from!(i64, Long);
+impl_from!(f32, Float);
+impl_from!(f64, Double);
+impl_from!(Vec<i8>, ByteArray);
+impl_from!(String, String);
+impl_from!(&str, String);
+impl_from!(Vec<Nbt>, List);
+impl_from!(Compound, Compound);
+impl_from!(Vec<i32>, IntArray);
+impl_from!(Vec<i64>, LongArray);
+
+/// Named tags, kept in the order they were inserted.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Compound(pub Vec<(String, Nbt)>);
+
+impl Compound {
+    pub fn new() -> Compound {
+        Compound(Vec::new())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Nbt> {
+        self.0
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Nbt> {
+        self.0
+            .iter_mut()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Sets `name` to `value`, returning the value it had before.
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<Nbt>) -> Option<Nbt> {
+        let name = name.into();
+        let value = value.into();
+        match self.get_mut(&name) {
+            Some(old) => Some(core::mem::replace(old, value)),
+            None => {
+                self.0.push((name, value));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Nbt> {
+        let index = self.0.iter().position(|(key, _)| key == name)?;
+        Some(self.0.remove(index).1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Nbt)> {
+        self.0.iter().map(|(key, value)| (key.as_str(), value))
+    }
+}
+
+impl ReadType for Nbt {
+    async fn read_type<R: Read + ?Sized>(socket: &mut R) -> Result<Self, ProtocolError> {
+        socket.read_nbt().await
+    }
+}
+
+impl WriteType for Nbt {
+    async fn write_type<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_nbt(self).await
+    }
+}
+
+impl ReadType for Compound {
+    async fn read_type<R: Read + ?Sized>(socket: &mut R) -> Result<Self, ProtocolError> {
+        match socket.read_nbt().await? {
+            Nbt::Compound(compound) => Ok(compound),
+            other => Err(ProtocolError::InvalidNbtTag(other.tag() as u8)),
+        }
+    }
+}
+
+impl WriteType for Compound {
+    async fn write_type<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_u8(Tag::Compound as u8).await?;
+        self.write_payload(socket).await
+    }
+}
+
+macro_rules! read_array {
+    ($socket:expr, $read:ident) => {{
+        let length = read_length($socket).await?;
+        // Don't trust the length for the allocation, the data might not be there
+        let mut values = Vec::with_capacity(length.min(64));
+        for _ in 0..length {
+            values.push($socket.$read().await?);
+        }
+        values
+    }};
+}
+
+/// A list or compound that is still being read.
+enum Container {
+    List {
+        tag: Tag,
+        remaining: usize,
+        values: Vec<Nbt>,
+    },
+    Compound {
+        compound: Compound,
+        /// The name of the value being read into it.
+        name: String,
+    },
+}
+
+impl Container {
+    fn finish(self) -> Nbt {
+        match self {
+            Container::List { values, .. } => Nbt::List(values),
+            Container::Compound { compound, .. } => Nbt::Compound(compound),
+        }
+    }
+}
+
+/// Reads the payload of a tag whose type has already been read.
+///
+/// Nested tags are kept on a stack instead of recursing, so deep NBT can't overflow ours.
+pub(crate) async fn read_payload<R: Read + ?Sized>(
+    socket: &mut R,
+    mut tag: Tag,
+) -> Result<Nbt, ProtocolError> {
+    let mut stack = Vec::new();
+
+    loop {
+        let mut value = match tag {
+            Tag::End => return Err(ProtocolError::InvalidNbtTag(Tag::End as u8)),
+            Tag::Byte => Some(Nbt::Byte(socket.read_i8().await?)),
+            Tag::Short => Some(Nbt::Short(socket.read_i16().await?)),
+            Tag::Int => Some(Nbt::Int(socket.read_i32().await?)),
+            Tag::Long => Some(Nbt::Long(socket.read_i64().await?)),
+            Tag::Float => Some(Nbt::Float(socket.read_f32().await?)),
+            Tag::Double => Some(Nbt::Double(socket.read_f64().await?)),
+            Tag::ByteArray => Some(Nbt::ByteArray(read_array!(socket, read_i8))),
+            Tag::String => Some(Nbt::String(read_string(socket).await?)),
+            Tag::IntArray => Some(Nbt::IntArray(read_array!(socket, read_i32))),
+            Tag::LongArray => Some(Nbt::LongArray(read_array!(socket, read_i64))),
+            Tag::List => {
+                let tag = Tag::from_id(socket.read_u8().await?)?;
+                let length = read_length(socket).await?;
+                // Empty lists are allowed to say their elements are `End`
+                if tag == Tag::End && length > 0 {
+                    return Err(ProtocolError::InvalidNbtTag(Tag::End as u8));
+                }
+
+                stack.push(Container::List {
+                    tag,
+                    remaining: length,
+                    values: Vec::with_capacity(length.min(16)),
+                });
+                None
+            }
+            Tag::Compound => {
+                stack.push(Container::Compound {
+                    compound: Compound::new(),
+                    name: String::new(),
+                });
+                None
+            }
+        };
+        if stack.len() > MAX_DEPTH {
+            return Err(ProtocolError::NbtTooDeep);
+        }
+
+        // Closes every container that is complete, until there is another tag to read
+        tag = loop {
+            if let Some(finished) = value.take() {
+                match stack.last_mut() {
+                    None => return Ok(finished),
+                    Some(Container::List { values, .. }) => values.push(finished),
+                    Some(Container::Compound { compound, name }) => {
+                        compound.0.push((core::mem::take(name), finished))
+                    }
+                }
+            }
+
+            let next = match stack.last_mut() {
+                Some(Container::List { tag, remaining, .. }) => {
+                    if *remaining == 0 {
+                        None
+                    } else {
+                        *remaining -= 1;
+                        Some(*tag)
+                    }
+                }
+                Some(Container::Compound { name, .. }) => {
+                    let tag = Tag::from_id(socket.read_u8().await?)?;
+                    if tag == Tag::End {
+                        None
+                    } else {
+                        *name = read_string(socket).await?;
+                        Some(tag)
+                    }
+                }
+                // Only containers leave the value empty, and they're on the stack
+                None => unreachable!(),
+            };
+
+            match next {
+                Some(tag) => break tag,
+                None => value = stack.pop().map(Container::finish),
+            }
+        };
+    }
+}
+
+async fn read_length<R: Read + ?Sized>(socket: &mut R) -> Result<usize, ProtocolError> {
+    let length = socket.read_i32().await?;
+    if length < 0 {
+        return Err(ProtocolError::InvalidLength(length));
+    }
+    Ok(length as usize)
+}
+
+/// Reads a string in Java's "modified UTF-8", prefixed with its length as a `u16`.
+pub(crate) async fn read_string<R: Read + ?Sized>(socket: &mut R) -> Result<String, ProtocolError> {
+    let length = socket.read_u16().await?;
+    let bytes = socket.read_byte_array(length as usize).await?;
+
+    // Only nulls and characters outside the BMP are encoded differently from UTF-8
+    let bytes = match String::from_utf8(bytes) {
+        Ok(string) => return Ok(string),
+        Err(err) => err.into_bytes(),
+    };
+
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let continuation = |offset: usize| {
+            bytes
+                .get(i + offset)
+                .filter(|byte| *byte & 0b1100_0000 == 0b1000_0000)
+                .map(|byte| (byte & 0b0011_1111) as u16)
+                .ok_or(ProtocolError::InvalidUtf8)
+        };
+
+        let (unit, length) = match bytes[i] {
+            byte @ 0x00..=0x7F => (byte as u16, 1),
+            byte @ 0xC0..=0xDF => (((byte & 0b0001_1111) as u16) << 6 | continuation(1)?, 2),
+            byte @ 0xE0..=0xEF => (
+                ((byte & 0b0000_1111) as u16) << 12 | continuation(1)? << 6 | continuation(2)?,
+                3,
+            ),
+            _ => return Err(ProtocolError::InvalidUtf8),
+        };
+        units.push(unit);
+        i += length;
+    }
+
+    char::decode_utf16(units)
+        .map(|c| c.map_err(|_| ProtocolError::InvalidUtf8))
+        .collect()
+}
+
+/// Writes a string in Java's "modified UTF-8", prefixed with its length as a `u16`.
+pub(crate) async fn write_string<W: Write + ?Sized>(
+    socket: &mut W,
+    value: &str,
+) -> Result<(), ProtocolError> {
+    let length: usize = value
+        .chars()
+        .map(|c| match c as u32 {
+            0 => 2,
+            0x01..=0x7F => 1,
+            0x80..=0x7FF => 2,
+            0x800..=0xFFFF => 3,
+            // Written as a surrogate pair
+            _ => 6,
+        })
+        .sum();
+    if length > u16::MAX as usize {
+        return Err(ProtocolError::StringTooLong {
+            length: length as i32,
+            max: u16::MAX as usize,
+        });
+    }
+    socket.write_u16(length as u16).await?;
+
+    // Nulls add a byte and characters outside the BMP add two, so otherwise it's just UTF-8
+    if length == value.len() {
+        return socket.write_byte_array(value.as_bytes()).await;
+    }
+
+    for c in value.chars() {
+        let mut units = [0; 2];
+        for unit in c.encode_utf16(&mut units) {
+            let unit = *unit;
+            match unit {
+                0x01..=0x7F => socket.write_u8(unit as u8).await?,
+                0x00 | 0x80..=0x7FF => {
+                    socket
+                        .write_byte_array(&[0xC0 | (unit >> 6) as u8, 0x80 | (unit & 0x3F) as u8])
+                        .await?
+                }
+                _ => {
+                    socket
+                        .write_byte_array(&[
+                            0xE0 | (unit >> 12) as u8,
+                            0x80 | ((unit >> 6) & 0x3F) as u8,
+                            0x80 | (unit & 0x3F) as u8,
+                        ])
+                        .await?
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Something that can be written as the payload of a tag, without turning it into an [`Nbt`].
+pub trait NbtPayload {
+    fn tag(&self) -> Tag;
+
+    async fn write_payload<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError>;
+}
+
+macro_rules! impl_payload {
+    ($ty:ty, $tag:ident, $write:ident) => {
+        impl NbtPayload for $ty {
+            fn tag(&self) -> Tag {
+                Tag::$tag
+            }
+
+            async fn write_payload<W: Write + ?Sized>(
+                &self,
+                socket: &mut W,
+            ) -> Result<(), ProtocolError> {
+                socket.$write(*self).await
+            }
+        }
+    };
+}
+
+impl_payload!(i8, Byte, write_i8);
+impl_payload!(bool, Byte, write_bool);
+impl_payload!(i16, Short, write_i16);
+impl_payload!(i32, Int, write_i32);
+impl_payload!(i64, Long, write_i64);
+impl_payload!(f32, Float, write_f32);
+impl_payload!(f64, Double, write_f64);
+
+macro_rules! impl_array_payload {
+    ($ty:ty, $tag:ident, $write:ident) => {
+        impl NbtPayload for [$ty] {
+            fn tag(&self) -> Tag {
+                Tag::$tag
+            }
+
+            async fn write_payload<W: Write + ?Sized>(
+                &self,
+                socket: &mut W,
+            ) -> Result<(), ProtocolError> {
+                socket.write_i32(self.len() as i32).await?;
+                for value in self {
+                    socket.$write(*value).await?;
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_array_payload!(i8, ByteArray, write_i8);
+impl_array_payload!(i32, IntArray, write_i32);
+impl_array_payload!(i64, LongArray, write_i64);
+
+impl NbtPayload for str {
+    fn tag(&self) -> Tag {
+        Tag::String
+    }
+
+    async fn write_payload<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        write_string(socket, self).await
+    }
+}
+
+impl NbtPayload for Nbt {
+    fn tag(&self) -> Tag {
+        Nbt::tag(self)
+    }
+
+    async fn write_payload<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        write_payload(socket, self).await
+    }
+}
+
+impl NbtPayload for Compound {
+    fn tag(&self) -> Tag {
+        Tag::Compound
+    }
+
+    async fn write_payload<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        write_compound(socket, self).await
+    }
+}
+
+async fn write_payload<W: Write + ?Sized>(
+    socket: &mut W,
+    value: &Nbt,
+) -> Result<(), ProtocolError> {
+    match value {
+        Nbt::Byte(value) => value.write_payload(socket).await,
+        Nbt::Short(value) => value.write_payload(socket).await,
+        Nbt::Int(value) => value.write_payload(socket).await,
+        Nbt::Long(value) => value.write_payload(socket).await,
+        Nbt::Float(value) => value.write_payload(socket).await,
+        Nbt::Double(value) => value.write_payload(socket).await,
+        Nbt::ByteArray(value) => value.write_payload(socket).await,
+        Nbt::String(value) => value.write_payload(socket).await,
+        Nbt::IntArray(value) => value.write_payload(socket).await,
+        Nbt::LongArray(value) => value.write_payload(socket).await,
+        Nbt::List(values) => {
+            let tag = values.first().map_or(Tag::End, Nbt::tag);
+            if let Some(other) = values.iter().find(|value| value.tag() != tag) {
+                return Err(ProtocolError::InvalidNbtTag(other.tag() as u8));
+            }
+
+            socket.write_u8(tag as u8).await?;
+            socket.write_i32(values.len() as i32).await?;
+            for value in values {
+                Box::pin(write_payload(socket, value)).await?;
+            }
+            Ok(())
+        }
+        Nbt::Compound(compound) => Box::pin(write_compound(socket, compound)).await,
+    }
+}
+
+async fn write_compound<W: Write + ?Sized>(
+    socket: &mut W,
+    compound: &Compound,
+) -> Result<(), ProtocolError> {
+    for (name, value) in &compound.0 {
+        socket.write_u8(value.tag() as u8).await?;
+        write_string(socket, name).await?;
+        write_payload(socket, value).await?;
+    }
+    socket.write_u8(Tag::End as u8).await
+}
+
+/// Writes NBT as it goes, for when building an [`Nbt`] would take too much memory.
+///
+/// The root is always a compound. Inside a compound, values are written with
+/// [`field`](NbtWriter::field) or opened with [`compound`](NbtWriter::compound) and
+/// [`list`](NbtWriter::list), and the compound is closed with [`end`](NbtWriter::end). Inside a
+/// list, the right number of [`element`](NbtWriter::element)s or
+/// [`element_list`](NbtWriter::element_list)s must follow. A compound element is just its fields
+/// followed by [`end`](NbtWriter::end).
+///
+/// ```ignore
+/// let mut nbt = NbtWriter::network(socket).await?;
+/// nbt.field("MOTION_BLOCKING", &heightmap[..]).await?;
+/// nbt.end().await?;
+/// ```
+pub struct NbtWriter<'a, W: Write + ?Sized> {
+    socket: &'a mut W,
+}
+
+impl<'a, W: Write + ?Sized> NbtWriter<'a, W> {
+    /// Starts a root compound without a name, as sent over the network.
+    pub async fn network(socket: &'a mut W) -> Result<NbtWriter<'a, W>, ProtocolError> {
+        socket.write_u8(Tag::Compound as u8).await?;
+        Ok(NbtWriter { socket })
+    }
+
+    /// Starts a root compound with a name, as stored in files.
+    pub async fn file(socket: &'a mut W, name: &str) -> Result<NbtWriter<'a, W>, ProtocolError> {
+        socket.write_u8(Tag::Compound as u8).await?;
+        write_string(socket, name).await?;
+        Ok(NbtWriter { socket })
+    }
+
+    async fn header(&mut self, tag: Tag, name: &str) -> Result<(), ProtocolError> {
+        self.socket.write_u8(tag as u8).await?;
+        write_string(self.socket, name).await
+    }
+
+    pub async fn field<T: NbtPayload + ?Sized>(
+        &mut self,
+        name: &str,
+        value: &T,
+    ) -> Result<(), ProtocolError> {
+        self.header(value.tag(), name).await?;
+        value.write_payload(self.socket).await
+    }
+
+    pub async fn compound(&mut self, name: &str) -> Result<(), ProtocolError> {
+        self.header(Tag::Compound, name).await
+    }
+
+    pub async fn list(&mut self, name: &str, tag: Tag, length: usize) -> Result<(), ProtocolError> {
+        self.header(Tag::List, name).await?;
+        self.element_list(tag, length).await
+    }
+
+    pub async fn element<T: NbtPayload + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), ProtocolError> {
+        value.write_payload(self.socket).await
+    }
+
+    pub async fn element_list(&mut self, tag: Tag, length: usize) -> Result<(), ProtocolError> {
+        self.socket.write_u8(tag as u8).await?;
+        self.socket.write_i32(length as i32).await
+    }
+
+    /// Closes the innermost compound.
+    pub async fn end(&mut self) -> Result<(), ProtocolError> {
+        self.socket.write_u8(Tag::End as u8).await
+    }
+}
@@ -0,0 +1,205 @@
+use alloc::{string::String, vec::Vec};
+use embedded_io_async::{Read, Write};
+
+use super::{Bound, PacketInfo, State, WritePacket};
+use crate::{
+    error::ProtocolError,
+    read::ReadExtension,
+    text::TextComponent,
+    types::{Identifier, ReadType, StringList, VarInt, WriteType},
+    write::WriteExtension,
+    McPacket,
+};
+
+#[derive(Debug, Clone, McPacket)]
+#[packet(id = 0x00, state = Configuration, bound = Server)]
+pub struct ClientInformation {
+    #[string(max = 16)]
+    pub locale: String,
+    pub view_distance: i8,
+    /// 0 for full chat, 1 for commands only and 2 for hidden.
+    #[varint]
+    pub chat_mode: i32,
+    pub chat_colors: bool,
+    /// A bit mask of the skin layers to show.
+    pub displayed_skin_parts: u8,
+    /// 0 for left, 1 for right.
+    #[varint]
+    pub main_hand: i32,
+    pub enable_text_filtering: bool,
+    pub allow_server_listings: bool,
+}
+
+#[derive(Debug, McPacket)]
+#[packet(id = 0x01, state = Configuration, bound = Client)]
+pub struct ClientboundPluginMessage {
+    pub channel: Identifier,
+    #[rest]
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, McPacket)]
+#[packet(id = 0x02, state = Configuration, bound = Server)]
+pub struct ServerboundPluginMessage {
+    pub channel: Identifier,
+    #[rest]
+    pub data: Vec<u8>,
+}
+
+/// Kicks the player while they are being configured.
+#[derive(Debug)]
+pub struct ConfigurationDisconnect {
+    pub reason: TextComponent,
+}
+
+impl PacketInfo for ConfigurationDisconnect {
+    const ID: i32 = 0x02;
+    const STATE: State = State::Configuration;
+    const BOUND: Bound = Bound::Client;
+}
+
+// Text components can only be written, so this can't be derived
+impl WritePacket for ConfigurationDisconnect {
+    async fn write_packet<W: Write>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        self.reason.write_type(socket).await
+    }
+}
+
+/// Tells the client we're done, it answers with [`AcknowledgeFinishConfiguration`].
+#[derive(Debug, McPacket)]
+#[packet(id = 0x03, state = Configuration, bound = Client)]
+pub struct FinishConfiguration;
+
+#[derive(Debug, McPacket)]
+#[packet(id = 0x03, state = Configuration, bound = Server)]
+pub struct AcknowledgeFinishConfiguration;
+
+/// The entries of a synchronised registry, like the biomes or damage types.
+///
+/// We never send the entries' data, so the client takes it from its own copy of the vanilla data
+/// pack, which it agreed to in [`ServerboundKnownPacks`]. This keeps the packets small enough to
+/// write straight from flash.
+#[derive(Debug)]
+pub struct RegistryData<'a> {
+    pub registry: &'a str,
+    pub entries: StringList<'a>,
+}
+
+impl PacketInfo for RegistryData<'_> {
+    const ID: i32 = 0x07;
+    const STATE: State = State::Configuration;
+    const BOUND: Bound = Bound::Client;
+}
+
+impl WritePacket for RegistryData<'_> {
+    async fn write_packet<W: Write>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_string(self.registry).await?;
+        socket.write_varint(self.entries.len() as i32).await?;
+        for entry in self.entries {
+            socket.write_string(entry).await?;
+            // No data
+            socket.write_bool(false).await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, McPacket)]
+#[packet(id = 0x0C, state = Configuration, bound = Client)]
+pub struct FeatureFlags {
+    #[prefixed]
+    pub flags: Vec<Identifier>,
+}
+
+#[derive(Debug, McPacket)]
+#[packet(id = 0x0D, state = Configuration, bound = Client)]
+pub struct UpdateTags {
+    #[prefixed]
+    pub registries: Vec<RegistryTags>,
+}
+
+/// The tags of one registry, each a list of the ids of the entries in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryTags {
+    pub registry: Identifier,
+    pub tags: Vec<(Identifier, Vec<VarInt>)>,
+}
+
+impl ReadType for RegistryTags {
+    async fn read_type<R: Read + ?Sized>(socket: &mut R) -> Result<Self, ProtocolError> {
+        let registry = socket.read_identifier().await?;
+        let length = socket.read_prefix_length().await?;
+        let mut tags = Vec::with_capacity(length.min(16));
+        for _ in 0..length {
+            tags.push((
+                socket.read_identifier().await?,
+                socket.read_prefixed().await?,
+            ));
+        }
+        Ok(RegistryTags { registry, tags })
+    }
+}
+
+impl WriteType for RegistryTags {
+    async fn write_type<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_identifier(&self.registry).await?;
+        socket.write_varint(self.tags.len() as i32).await?;
+        for (name, entries) in &self.tags {
+            socket.write_identifier(name).await?;
+            socket.write_prefixed(entries).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists the data packs the server would like to use the client's copy of.
+#[derive(Debug, McPacket)]
+#[packet(id = 0x0E, state = Configuration, bound = Client)]
+pub struct ClientboundKnownPacks {
+    #[prefixed]
+    pub packs: Vec<KnownPack>,
+}
+
+/// The packs from [`ClientboundKnownPacks`] that the client also has.
+#[derive(Debug, McPacket)]
+#[packet(id = 0x07, state = Configuration, bound = Server)]
+pub struct ServerboundKnownPacks {
+    #[prefixed]
+    pub packs: Vec<KnownPack>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KnownPack {
+    pub namespace: String,
+    pub id: String,
+    pub version: String,
+}
+
+impl KnownPack {
+    /// The vanilla data pack of `version`.
+    pub fn core(version: &str) -> KnownPack {
+        KnownPack {
+            namespace: String::from("minecraft"),
+            id: String::from("core"),
+            version: String::from(version),
+        }
+    }
+}
+
+impl ReadType for KnownPack {
+    async fn read_type<R: Read + ?Sized>(socket: &mut R) -> Result<Self, ProtocolError> {
+        Ok(KnownPack {
+            namespace: socket.read_string().await?,
+            id: socket.read_string().await?,
+            version: socket.read_string().await?,
+        })
+    }
+}
+
+impl WriteType for KnownPack {
+    async fn write_type<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_string(&self.namespace).await?;
+        socket.write_string(&self.id).await?;
+        socket.write_string(&self.version).await
+    }
+}
@@ -0,0 +1,113 @@
+//! The server list ping from before 1.7, which isn't framed like the rest of the protocol.
+//!
+//! Old clients and a lot of server list scanners start the connection with `0xFE` rather than a
+//! handshake, which would otherwise be read as the start of a huge frame length. The server
+//! answers with a kick packet whose reason holds the server list data, and closes the connection.
+
+use alloc::{format, string::ToString};
+use embedded_io_async::Write;
+
+use super::status::StatusJson;
+use crate::{error::ProtocolError, write::WriteExtension};
+
+/// The first byte of a legacy ping.
+pub const LEGACY_PING: u8 = 0xFE;
+
+/// The id of the plugin message 1.6 sends after `0xFE 0x01`.
+const PLUGIN_MESSAGE: u8 = 0xFA;
+
+/// The channel of 1.6's plugin message.
+const PING_HOST: &str = "MC|PingHost";
+
+/// The id of the kick packet the response is sent as.
+const KICK: u8 = 0xFF;
+
+/// The protocol version we tell old clients we speak, which they all show as incompatible.
+const LEGACY_PROTOCOL: i32 = 127;
+
+/// The form of a legacy ping, which decides the form of the response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LegacyPing {
+    /// Just `0xFE`, from Beta 1.8 to 1.3.
+    Beta,
+    /// `0xFE 0x01` from 1.4 and 1.5, which 1.6 follows with an `MC|PingHost` plugin message
+    /// starting with `0xFA`.
+    Versioned,
+}
+
+impl LegacyPing {
+    /// Recognises a legacy ping in the first bytes received on a connection.
+    ///
+    /// Clients send the whole ping at once, so the first read is enough to tell the forms apart.
+    /// `0xFE 0x01` is also the length of a 254 byte frame, so anything longer only counts if it
+    /// goes on with the 1.6 plugin message.
+    pub fn parse(bytes: &[u8]) -> Option<LegacyPing> {
+        match bytes {
+            [LEGACY_PING] => Some(LegacyPing::Beta),
+            [LEGACY_PING, 0x01] => Some(LegacyPing::Versioned),
+            [LEGACY_PING, 0x01, PLUGIN_MESSAGE, rest @ ..] if is_ping_host(rest) => {
+                Some(LegacyPing::Versioned)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Whether `bytes` starts with the channel of 1.6's plugin message, as a string that's prefixed
+/// with its length in UTF-16 code units and encoded as UTF-16BE.
+fn is_ping_host(bytes: &[u8]) -> bool {
+    let Some((length, channel)) = bytes.split_first_chunk::<2>() else {
+        return false;
+    };
+    u16::from_be_bytes(*length) as usize == PING_HOST.len()
+        && channel.len() >= 2 * PING_HOST.len()
+        && channel
+            .chunks_exact(2)
+            .zip(PING_HOST.bytes())
+            .all(|(unit, byte)| unit == [0, byte])
+}
+
+/// Answers a legacy ping with the same data as the status response.
+pub async fn write_legacy_status<W: Write>(
+    socket: &mut W,
+    ping: LegacyPing,
+    status: &StatusJson,
+) -> Result<(), ProtocolError> {
+    let (online, max) = status
+        .players
+        .as_ref()
+        .map_or((0, 0), |players| (players.online, players.max));
+
+    let reason = match ping {
+        // The fields are split on `§`, so the MOTD can't have any formatting
+        LegacyPing::Beta => {
+            let mut motd = status
+                .description
+                .as_ref()
+                .map(|description| description.to_string())
+                .unwrap_or_default();
+            motd.retain(|c| c != '§');
+            format!("{}§{}§{}", motd, online, max)
+        }
+        LegacyPing::Versioned => {
+            let motd = status
+                .description
+                .as_ref()
+                .map(|description| description.to_legacy())
+                .unwrap_or_default();
+            format!(
+                "§1\0{}\0{}\0{}\0{}\0{}",
+                LEGACY_PROTOCOL, status.version.name, motd, online, max
+            )
+        }
+    };
+
+    socket.write_u8(KICK).await?;
+    socket
+        .write_u16(reason.encode_utf16().count() as u16)
+        .await?;
+    for unit in reason.encode_utf16() {
+        socket.write_u16(unit).await?;
+    }
+    Ok(())
+}
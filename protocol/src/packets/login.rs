@@ -0,0 +1,129 @@
+use alloc::{string::String, vec::Vec};
+use embedded_io_async::{Read, Write};
+
+use super::{Bound, PacketInfo, State, WritePacket};
+use crate::{
+    error::ProtocolError,
+    read::ReadExtension,
+    text::TextComponent,
+    types::{ReadType, Uuid, WriteType},
+    write::WriteExtension,
+    McPacket,
+};
+
+/// The longest username vanilla allows.
+pub const MAX_USERNAME_LENGTH: usize = 16;
+
+#[derive(Debug, McPacket)]
+#[packet(id = 0x00, state = Login, bound = Server)]
+pub struct LoginStart {
+    #[string(max = 16)]
+    pub name: String,
+    /// The UUID the client thinks it has, which we can't trust in offline mode.
+    pub uuid: Uuid,
+}
+
+/// Asks the client to encrypt the connection, and to authenticate with Mojang if it should.
+#[derive(Debug, McPacket)]
+#[packet(id = 0x01, state = Login, bound = Client)]
+pub struct EncryptionRequest {
+    /// Always empty since 1.7, but still part of the server hash.
+    #[string(max = 20)]
+    pub server_id: String,
+    /// The server's RSA public key, as DER encoded SubjectPublicKeyInfo.
+    #[prefixed]
+    pub public_key: Vec<u8>,
+    /// Random bytes the client has to send back encrypted with the public key.
+    #[prefixed]
+    pub verify_token: Vec<u8>,
+    pub should_authenticate: bool,
+}
+
+/// The shared secret and verify token, both encrypted with the server's public key.
+///
+/// The key is 1024 bits, so anything longer than 128 bytes can't have been encrypted with it.
+#[derive(Debug, McPacket)]
+#[packet(id = 0x01, state = Login, bound = Server)]
+pub struct EncryptionResponse {
+    #[prefixed(max = 128)]
+    pub shared_secret: Vec<u8>,
+    #[prefixed(max = 128)]
+    pub verify_token: Vec<u8>,
+}
+
+#[derive(Debug, McPacket)]
+#[packet(id = 0x02, state = Login, bound = Client)]
+pub struct LoginSuccess {
+    pub uuid: Uuid,
+    #[string(max = 16)]
+    pub username: String,
+    #[prefixed]
+    pub properties: Vec<Property>,
+    /// Whether the client should disconnect when a packet fails to parse, instead of skipping it.
+    pub strict_error_handling: bool,
+}
+
+/// Turns on compression for every packet after this one, in both directions.
+#[derive(Debug, McPacket)]
+#[packet(id = 0x03, state = Login, bound = Client)]
+pub struct SetCompression {
+    /// Packets at least this long are compressed, and a negative threshold turns it off again.
+    #[varint]
+    pub threshold: i32,
+}
+
+#[derive(Debug, McPacket)]
+#[packet(id = 0x03, state = Login, bound = Server)]
+pub struct LoginAcknowledged;
+
+/// Kicks the player before they have logged in.
+#[derive(Debug)]
+pub struct LoginDisconnect {
+    pub reason: TextComponent,
+}
+
+impl PacketInfo for LoginDisconnect {
+    const ID: i32 = 0x00;
+    const STATE: State = State::Login;
+    const BOUND: Bound = Bound::Client;
+}
+
+// The reason is still JSON in this state, so this can't be derived
+impl WritePacket for LoginDisconnect {
+    async fn write_packet<W: Write>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_json(&self.reason).await
+    }
+}
+
+/// A property of a player's profile, like their skin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Property {
+    pub name: String,
+    pub value: String,
+    /// Signed by Mojang, only present in online mode.
+    pub signature: Option<String>,
+}
+
+impl ReadType for Property {
+    async fn read_type<R: Read + ?Sized>(socket: &mut R) -> Result<Self, ProtocolError> {
+        Ok(Property {
+            name: socket.read_string().await?,
+            value: socket.read_string().await?,
+            signature: socket.read_optional().await?,
+        })
+    }
+}
+
+impl WriteType for Property {
+    async fn write_type<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_string(&self.name).await?;
+        socket.write_string(&self.value).await?;
+        socket.write_optional(&self.signature).await
+    }
+}
+
+/// Whether vanilla would accept `name`, 1 to 16 letters, digits or underscores.
+pub fn is_valid_username(name: &str) -> bool {
+    (1..=MAX_USERNAME_LENGTH).contains(&name.len())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
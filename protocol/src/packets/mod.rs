@@ -0,0 +1,158 @@
+use embedded_io_async::{Read, Write};
+
+use crate::{
+    error::ProtocolError,
+    read::{ReadExtension, Slice},
+    types::{ReadType, WriteType},
+    write::WriteExtension,
+};
+
+pub mod configuration;
+pub mod legacy;
+pub mod login;
+pub mod play;
+pub mod status;
+
+/// The newest Minecraft version whose protocol we speak.
+pub const MINECRAFT_VERSION: &str = "1.21.1";
+pub const PROTOCOL_VERSION: i32 = 767;
+
+/// Every version clients can join with, newest first.
+///
+/// Adding one needs its packet ids in `data/packets.txt`, and the server needs registries that
+/// match its vanilla data pack.
+pub const SUPPORTED_VERSIONS: &[Version] = &[
+    Version {
+        name: MINECRAFT_VERSION,
+        protocol: PROTOCOL_VERSION,
+    },
+    // 1.20.5 speaks it too, as 1.21 does 767, but their vanilla packs have different versions, so
+    // they're kicked in configuration
+    Version {
+        name: "1.20.6",
+        protocol: 766,
+    },
+];
+
+/// A Minecraft release and the protocol version it speaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Version {
+    pub name: &'static str,
+    pub protocol: i32,
+}
+
+impl Version {
+    /// The supported version that speaks `protocol`, if there is one.
+    pub fn from_protocol(protocol: i32) -> Option<Version> {
+        SUPPORTED_VERSIONS
+            .iter()
+            .find(|version| version.protocol == protocol)
+            .copied()
+    }
+
+    pub fn newest() -> Version {
+        SUPPORTED_VERSIONS[0]
+    }
+
+    pub fn oldest() -> Version {
+        SUPPORTED_VERSIONS[SUPPORTED_VERSIONS.len() - 1]
+    }
+}
+
+/// The largest frame the protocol allows, the most a 3 byte VarInt can hold.
+pub const MAX_PACKET_LENGTH: i32 = 2097151;
+
+pub trait ReadPacket: Sized {
+    async fn read_packet<R: Read>(socket: &mut R) -> Result<Self, ProtocolError>;
+}
+
+pub trait WritePacket {
+    /// Writes the packet's fields, like [`ReadPacket::read_packet`] reads them.
+    ///
+    /// The length and id are added by [`write_frame`](crate::frame::write_frame), which may call
+    /// this more than once, so it must always write the same bytes.
+    async fn write_packet<W: Write>(&self, socket: &mut W) -> Result<(), ProtocolError>;
+}
+
+/// Where a packet belongs in the protocol, usually from `#[derive(McPacket)]`.
+pub trait PacketInfo {
+    const ID: i32;
+    const STATE: State;
+    const BOUND: Bound;
+}
+
+/// Which side a packet is sent to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// Sent by the server to the client
+    Client,
+    /// Sent by the client to the server
+    Server,
+}
+
+#[derive(Debug)]
+pub struct Packet {
+    pub id: i32,
+    pub data: Slice,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum State {
+    Handshake = 0,
+    Status = 1,
+    Login = 2,
+    Transfer = 3,
+    Custom(i32) = 4,
+    /// Entered after login, it can't be asked for in a handshake.
+    Configuration = 5,
+    /// Entered after configuration, it can't be asked for in a handshake.
+    Play = 6,
+}
+
+/// As the next state in a handshake. Going back to `Handshake` isn't allowed.
+impl ReadType for State {
+    async fn read_type<R: Read + ?Sized>(socket: &mut R) -> Result<Self, ProtocolError> {
+        Ok(match socket.read_varint().await? {
+            1 => State::Status,
+            2 => State::Login,
+            3 => State::Transfer,
+            any if any > 3 => State::Custom(any),
+            any => return Err(ProtocolError::InvalidEnumValue(any)),
+        })
+    }
+}
+
+impl WriteType for State {
+    async fn write_type<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket
+            .write_varint(match self {
+                State::Handshake => 0,
+                State::Status => 1,
+                State::Login => 2,
+                State::Transfer => 3,
+                State::Custom(any) => *any,
+                State::Configuration => return Err(ProtocolError::InvalidEnumValue(5)),
+                State::Play => return Err(ProtocolError::InvalidEnumValue(6)),
+            })
+            .await
+    }
+}
+
+pub mod handshake {
+    use alloc::string::String;
+
+    use super::State;
+    use crate::McPacket;
+
+    #[derive(Debug, McPacket)]
+    #[packet(id = 0x00, state = Handshake, bound = Server)]
+    pub struct HandshakePacket {
+        #[varint]
+        pub protocol_version: i32,
+        #[string(max = 255)]
+        pub server_address: String,
+        pub server_port: u16,
+        pub next_state: State,
+    }
+}
@@ -0,0 +1,272 @@
+use alloc::vec::Vec;
+use core::fmt;
+use embedded_io_async::{Read, Write};
+
+use super::{Bound, PacketInfo, State, WritePacket};
+use crate::{
+    chunk::{write_heightmaps, write_light, write_sections, ChunkColumn, ChunkLayout},
+    error::ProtocolError,
+    read::ReadExtension,
+    text::TextComponent,
+    types::{Identifier, Position, ReadType, WriteType},
+    write::WriteExtension,
+    McPacket,
+};
+
+/// Tells the client the block changes it made up to `sequence` have been dealt with, so it can
+/// stop predicting them.
+#[derive(Debug, McPacket)]
+#[packet(id = 0x05, state = Play, bound = Client)]
+pub struct AcknowledgeBlockChange {
+    #[varint]
+    pub sequence: i32,
+}
+
+/// Changes a block the client has loaded.
+#[derive(Debug, McPacket)]
+#[packet(id = 0x09, state = Play, bound = Client)]
+pub struct BlockUpdate {
+    pub position: Position,
+    /// The block's new state id.
+    #[varint]
+    pub block: i32,
+}
+
+/// Kicks the player while they are playing.
+#[derive(Debug)]
+pub struct PlayDisconnect {
+    pub reason: TextComponent,
+}
+
+impl PacketInfo for PlayDisconnect {
+    const ID: i32 = 0x1D;
+    const STATE: State = State::Play;
+    const BOUND: Bound = Bound::Client;
+}
+
+// Text components can only be written, so this can't be derived
+impl WritePacket for PlayDisconnect {
+    async fn write_packet<W: Write>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        self.reason.write_type(socket).await
+    }
+}
+
+/// Changes something about the game, picked by `event`.
+#[derive(Debug, McPacket)]
+#[packet(id = 0x22, state = Play, bound = Client)]
+pub struct GameEvent {
+    pub event: u8,
+    pub value: f32,
+}
+
+impl GameEvent {
+    /// Tells the client chunks are on their way, so it can leave the loading screen once the one
+    /// it's standing in arrives.
+    pub const START_WAITING_FOR_CHUNKS: u8 = 13;
+}
+
+/// Sent every so often, the client has to answer with the same id or be kicked.
+#[derive(Debug, McPacket)]
+#[packet(id = 0x26, state = Play, bound = Client)]
+pub struct ClientboundKeepAlive {
+    pub id: i64,
+}
+
+/// A chunk column's blocks and light, encoded from `column` as it's written.
+pub struct ChunkDataAndUpdateLight<'a, C: ChunkColumn + ?Sized> {
+    pub x: i32,
+    pub z: i32,
+    pub column: &'a C,
+    layout: ChunkLayout,
+}
+
+impl<'a, C: ChunkColumn + ?Sized> ChunkDataAndUpdateLight<'a, C> {
+    /// Lays out the chunk at `x`, `z` for a client that was sent `biomes` biomes, see
+    /// [`ChunkLayout::new`].
+    pub fn new(x: i32, z: i32, column: &'a C, biomes: usize) -> Self {
+        ChunkDataAndUpdateLight {
+            x,
+            z,
+            column,
+            layout: ChunkLayout::new(column, biomes),
+        }
+    }
+}
+
+impl<C: ChunkColumn + ?Sized> fmt::Debug for ChunkDataAndUpdateLight<'_, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChunkDataAndUpdateLight")
+            .field("x", &self.x)
+            .field("z", &self.z)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C: ChunkColumn + ?Sized> PacketInfo for ChunkDataAndUpdateLight<'_, C> {
+    const ID: i32 = 0x27;
+    const STATE: State = State::Play;
+    const BOUND: Bound = Bound::Client;
+}
+
+impl<C: ChunkColumn + ?Sized> WritePacket for ChunkDataAndUpdateLight<'_, C> {
+    async fn write_packet<W: Write>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_i32(self.x).await?;
+        socket.write_i32(self.z).await?;
+        write_heightmaps(socket, self.column).await?;
+        write_sections(socket, self.column, &self.layout).await?;
+        // No block entities
+        socket.write_varint(0).await?;
+        write_light(socket, self.column, &self.layout).await
+    }
+}
+
+/// Puts the player in a world, the first packet of the Play state.
+#[derive(Debug, McPacket)]
+#[packet(id = 0x2B, state = Play, bound = Client)]
+pub struct LoginPlay {
+    pub entity_id: i32,
+    pub is_hardcore: bool,
+    /// Every dimension on the server.
+    #[prefixed]
+    pub dimension_names: Vec<Identifier>,
+    /// Only used by the client to show the player list.
+    #[varint]
+    pub max_players: i32,
+    #[varint]
+    pub view_distance: i32,
+    #[varint]
+    pub simulation_distance: i32,
+    pub reduced_debug_info: bool,
+    pub enable_respawn_screen: bool,
+    pub do_limited_crafting: bool,
+    /// The id of the dimension's type in the `minecraft:dimension_type` registry.
+    #[varint]
+    pub dimension_type: i32,
+    pub dimension_name: Identifier,
+    /// The first 8 bytes of the SHA-256 of the seed, used for biome noise.
+    pub hashed_seed: i64,
+    /// 0 for survival, 1 for creative, 2 for adventure and 3 for spectator.
+    pub game_mode: u8,
+    /// The same as `game_mode`, or -1 for none.
+    pub previous_game_mode: i8,
+    pub is_debug: bool,
+    /// Superflat worlds have their horizon at y 0 rather than 63.
+    pub is_flat: bool,
+    #[optional]
+    pub death_location: Option<GlobalPosition>,
+    #[varint]
+    pub portal_cooldown: i32,
+    pub enforces_secure_chat: bool,
+}
+
+/// A block position in a particular dimension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlobalPosition {
+    pub dimension: Identifier,
+    pub position: Position,
+}
+
+impl ReadType for GlobalPosition {
+    async fn read_type<R: Read + ?Sized>(socket: &mut R) -> Result<Self, ProtocolError> {
+        Ok(GlobalPosition {
+            dimension: socket.read_identifier().await?,
+            position: socket.read_position().await?,
+        })
+    }
+}
+
+impl WriteType for GlobalPosition {
+    async fn write_type<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_identifier(&self.dimension).await?;
+        socket.write_position(self.position).await
+    }
+}
+
+/// Moves the player, they answer with [`ConfirmTeleportation`].
+#[derive(Debug, McPacket)]
+#[packet(id = 0x40, state = Play, bound = Client)]
+pub struct SynchronizePlayerPosition {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Which of the coordinates are relative to where the player is, a bit each from x to pitch.
+    pub flags: u8,
+    #[varint]
+    pub teleport_id: i32,
+}
+
+/// The chunk the client loads chunks around, which it also stops drawing chunks too far from.
+#[derive(Debug, McPacket)]
+#[packet(id = 0x54, state = Play, bound = Client)]
+pub struct SetCenterChunk {
+    #[varint]
+    pub x: i32,
+    #[varint]
+    pub z: i32,
+}
+
+/// Where compasses point, and where the client spawns before it's told its position.
+#[derive(Debug, McPacket)]
+#[packet(id = 0x56, state = Play, bound = Client)]
+pub struct SetDefaultSpawnPosition {
+    pub position: Position,
+    pub angle: f32,
+}
+
+/// How many ticks old the world is, and what time of day it is, which the client carries on
+/// counting itself in between.
+#[derive(Debug, McPacket)]
+#[packet(id = 0x64, state = Play, bound = Client)]
+pub struct UpdateTime {
+    pub world_age: i64,
+    /// In ticks from sunrise, or negative to stop the sun moving.
+    pub time_of_day: i64,
+}
+
+/// Sends the player back to the Configuration state, they answer with
+/// [`ConfigurationAcknowledged`].
+#[derive(Debug, McPacket)]
+#[packet(id = 0x69, state = Play, bound = Client)]
+pub struct StartConfiguration;
+
+/// The answer to [`SynchronizePlayerPosition`].
+#[derive(Debug, McPacket)]
+#[packet(id = 0x00, state = Play, bound = Server)]
+pub struct ConfirmTeleportation {
+    #[varint]
+    pub teleport_id: i32,
+}
+
+#[derive(Debug, McPacket)]
+#[packet(id = 0x0C, state = Play, bound = Server)]
+pub struct ConfigurationAcknowledged;
+
+/// The answer to [`ClientboundKeepAlive`].
+#[derive(Debug, McPacket)]
+#[packet(id = 0x18, state = Play, bound = Server)]
+pub struct ServerboundKeepAlive {
+    pub id: i64,
+}
+
+/// Digging, and dropping or swapping items, picked by `status`.
+#[derive(Debug, McPacket)]
+#[packet(id = 0x24, state = Play, bound = Server)]
+pub struct PlayerAction {
+    #[varint]
+    pub status: i32,
+    pub position: Position,
+    /// The face of the block being dug: bottom, top, north, south, west or east.
+    pub face: u8,
+    /// Answered with an [`AcknowledgeBlockChange`].
+    #[varint]
+    pub sequence: i32,
+}
+
+impl PlayerAction {
+    /// Creative players break blocks as soon as they start digging.
+    pub const STARTED_DIGGING: i32 = 0;
+    pub const CANCELLED_DIGGING: i32 = 1;
+    pub const FINISHED_DIGGING: i32 = 2;
+}
@@ -0,0 +1,66 @@
+use alloc::{string::String, vec::Vec};
+
+use crate::{error::ProtocolError, text::TextComponent, write::WriteExtension, McPacket};
+use embedded_io_async::Write;
+
+use super::{Bound, PacketInfo, State, WritePacket};
+use serde::Serialize;
+
+// We don't have a StatusResponse packet since its just a wrapper over StatusJson
+
+#[derive(Debug, McPacket)]
+#[packet(id = 0x00, state = Status, bound = Server)]
+pub struct StatusRequest;
+
+impl PacketInfo for StatusJson {
+    const ID: i32 = 0x00;
+    const STATE: State = State::Status;
+    const BOUND: Bound = Bound::Client;
+}
+
+impl WritePacket for StatusJson {
+    async fn write_packet<W: Write>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_json(self).await
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusJson {
+    pub version: VersionData,
+    pub players: Option<PlayerData>,
+    pub description: Option<TextComponent>,
+    pub favicon: Option<String>,
+    pub enforces_secure_chat: bool,
+}
+
+#[derive(Serialize)]
+pub struct VersionData {
+    pub name: String,
+    pub protocol: i32,
+}
+
+#[derive(Serialize)]
+pub struct PlayerData {
+    pub max: u32,
+    pub online: u32,
+    pub sample: Option<Vec<SamplePlayer>>,
+}
+
+#[derive(Serialize)]
+pub struct SamplePlayer {
+    pub name: String,
+    pub id: String,
+}
+
+#[derive(Debug, McPacket)]
+#[packet(id = 0x01, state = Status, bound = Server)]
+pub struct PingRequest {
+    pub payload: i64,
+}
+
+#[derive(Debug, McPacket)]
+#[packet(id = 0x01, state = Status, bound = Client)]
+pub struct PongResponse {
+    pub payload: i64,
+}
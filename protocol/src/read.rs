@@ -0,0 +1,242 @@
+use alloc::{boxed::Box, string::String, vec::Vec};
+use embedded_io_async::{ErrorType, Read};
+
+use crate::{
+    error::ProtocolError,
+    nbt::{self, Nbt, Tag},
+    types::{Angle, BitSet, FixedBitSet, Identifier, Position, ReadType, Uuid},
+};
+
+/// The maximum length of a string, in UTF-16 code units, unless a packet says otherwise.
+pub const MAX_STRING_LENGTH: usize = 32767;
+
+macro_rules! impl_read {
+    ($ty:ty, $read:ident) => {
+        async fn $read(&mut self) -> Result<$ty, ProtocolError> {
+            let mut buf = [0; core::mem::size_of::<$ty>()];
+            self.read_exact(&mut buf).await?;
+            Ok(<$ty>::from_be_bytes(buf))
+        }
+    };
+}
+
+/// List of types is taken from [wiki.vg](https://wiki.vg/Protocol#Data_types)
+///
+/// This is implemented for every [`Read`], so any transport gets these for free.
+pub trait ReadExtension: Read {
+    impl_read!(i8, read_i8);
+    impl_read!(u8, read_u8);
+    impl_read!(i16, read_i16);
+    impl_read!(u16, read_u16);
+    impl_read!(i32, read_i32);
+    impl_read!(i64, read_i64);
+    impl_read!(u64, read_u64);
+    impl_read!(i128, read_i128);
+    impl_read!(u128, read_u128);
+    impl_read!(f32, read_f32);
+    impl_read!(f64, read_f64);
+
+    async fn read_bool(&mut self) -> Result<bool, ProtocolError> {
+        Ok(self.read_u8().await? != 0)
+    }
+
+    async fn read_string(&mut self) -> Result<String, ProtocolError> {
+        self.read_string_max(MAX_STRING_LENGTH).await
+    }
+
+    /// Reads a string that is at most `max` UTF-16 code units long.
+    async fn read_string_max(&mut self, max: usize) -> Result<String, ProtocolError> {
+        let length = self.read_varint().await?;
+        // A code unit can take up to 3 bytes in UTF-8
+        if length < 0 || length as usize > max * 3 {
+            return Err(ProtocolError::StringTooLong { length, max });
+        }
+
+        let mut buf = alloc::vec![0; length as usize];
+        self.read_exact(&mut buf).await?;
+        let string = String::from_utf8(buf).map_err(|_| ProtocolError::InvalidUtf8)?;
+
+        let units = string.encode_utf16().count();
+        if units > max {
+            return Err(ProtocolError::StringTooLong {
+                length: units as i32,
+                max,
+            });
+        }
+        Ok(string)
+    }
+
+    async fn read_identifier(&mut self) -> Result<Identifier, ProtocolError> {
+        Identifier::new(&self.read_string().await?)
+    }
+
+    async fn read_varint(&mut self) -> Result<i32, ProtocolError> {
+        let mut result = 0;
+
+        for shift in (0..35).step_by(7) {
+            let byte = self.read_u8().await?;
+            result |= ((byte & 0b0111_1111) as i32) << shift;
+            if byte & 0b1000_0000 == 0 {
+                return Ok(result);
+            }
+        }
+        Err(ProtocolError::VarIntTooLong)
+    }
+
+    async fn read_varlong(&mut self) -> Result<i64, ProtocolError> {
+        let mut result = 0;
+
+        for shift in (0..70).step_by(7) {
+            let byte = self.read_u8().await?;
+            result |= ((byte & 0b0111_1111) as i64) << shift;
+            if byte & 0b1000_0000 == 0 {
+                return Ok(result);
+            }
+        }
+        Err(ProtocolError::VarIntTooLong)
+    }
+
+    async fn read_uuid(&mut self) -> Result<Uuid, ProtocolError> {
+        Ok(Uuid(self.read_u128().await?))
+    }
+
+    async fn read_position(&mut self) -> Result<Position, ProtocolError> {
+        Ok(Position::from_packed(self.read_i64().await?))
+    }
+
+    async fn read_angle(&mut self) -> Result<Angle, ProtocolError> {
+        Ok(Angle(self.read_u8().await?))
+    }
+
+    /// Reads `length` bytes, where the length is known from context.
+    ///
+    /// The buffer grows as the bytes arrive, so a length that's a lie can't allocate more than
+    /// the data that's really there.
+    async fn read_byte_array(&mut self, length: usize) -> Result<Vec<u8>, ProtocolError> {
+        let mut buf = Vec::with_capacity(length.min(64));
+        let mut chunk = [0; 64];
+        while buf.len() < length {
+            let chunk = &mut chunk[..(length - buf.len()).min(64)];
+            self.read_exact(chunk).await?;
+            buf.extend_from_slice(chunk);
+        }
+        Ok(buf)
+    }
+
+    /// Reads everything up to the end of the data, for byte arrays at the end of a packet.
+    async fn read_to_end(&mut self) -> Result<Vec<u8>, ProtocolError> {
+        let mut buf = Vec::new();
+        let mut chunk = [0; 64];
+        loop {
+            let read = self.read(&mut chunk).await.map_err(ProtocolError::io)?;
+            if read == 0 {
+                return Ok(buf);
+            }
+            buf.extend_from_slice(&chunk[..read]);
+        }
+    }
+
+    /// Reads a byte array prefixed with its length as a VarInt.
+    async fn read_prefixed_byte_array(&mut self) -> Result<Vec<u8>, ProtocolError> {
+        let length = self.read_prefix_length().await?;
+        self.read_byte_array(length).await
+    }
+
+    async fn read_bitset(&mut self) -> Result<BitSet, ProtocolError> {
+        let length = self.read_prefix_length().await?;
+        let mut longs = Vec::with_capacity(length.min(16));
+        for _ in 0..length {
+            longs.push(self.read_u64().await?);
+        }
+        Ok(BitSet(longs))
+    }
+
+    async fn read_fixed_bitset<const N: usize>(&mut self) -> Result<FixedBitSet<N>, ProtocolError> {
+        let mut buf = [0; N];
+        self.read_exact(&mut buf).await?;
+        Ok(FixedBitSet(buf))
+    }
+
+    /// Reads a value that is prefixed with a boolean saying whether it is present.
+    async fn read_optional<T: ReadType>(&mut self) -> Result<Option<T>, ProtocolError> {
+        if self.read_bool().await? {
+            Ok(Some(T::read_type(self).await?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Reads an array that is prefixed with its length as a VarInt.
+    async fn read_prefixed<T: ReadType>(&mut self) -> Result<Vec<T>, ProtocolError> {
+        self.read_prefixed_max(usize::MAX).await
+    }
+
+    /// Reads an array that is prefixed with its length as a VarInt, with at most `max` values.
+    async fn read_prefixed_max<T: ReadType>(
+        &mut self,
+        max: usize,
+    ) -> Result<Vec<T>, ProtocolError> {
+        let length = self.read_prefix_length().await?;
+        if length > max {
+            return Err(ProtocolError::InvalidLength(length as i32));
+        }
+        // Don't trust the length for the allocation, the data might not be there
+        let mut values = Vec::with_capacity(length.min(16));
+        for _ in 0..length {
+            values.push(T::read_type(self).await?);
+        }
+        Ok(values)
+    }
+
+    /// Reads NBT with a nameless root, as sent over the network since 1.20.2.
+    async fn read_nbt(&mut self) -> Result<Nbt, ProtocolError> {
+        let tag = Tag::from_id(self.read_u8().await?)?;
+        nbt::read_payload(self, tag).await
+    }
+
+    /// Reads NBT with a named root, as stored in files.
+    async fn read_named_nbt(&mut self) -> Result<(String, Nbt), ProtocolError> {
+        let tag = Tag::from_id(self.read_u8().await?)?;
+        if tag == Tag::End {
+            return Err(ProtocolError::InvalidNbtTag(Tag::End as u8));
+        }
+        let name = nbt::read_string(self).await?;
+        Ok((name, nbt::read_payload(self, tag).await?))
+    }
+
+    /// Reads a VarInt length prefix, making sure it isn't negative.
+    async fn read_prefix_length(&mut self) -> Result<usize, ProtocolError> {
+        let length = self.read_varint().await?;
+        if length < 0 {
+            return Err(ProtocolError::InvalidLength(length));
+        }
+        Ok(length as usize)
+    }
+}
+
+impl<T: Read + ?Sized> ReadExtension for T {}
+
+#[derive(Debug)]
+pub struct Slice {
+    pub(super) buf: Box<[u8]>,
+    pos: usize,
+}
+
+impl Slice {
+    pub fn new(buf: Box<[u8]>) -> Slice {
+        Slice { buf, pos: 0 }
+    }
+}
+
+impl ErrorType for Slice {
+    type Error = core::convert::Infallible;
+}
+
+impl Read for Slice {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let len = buf.len().min(self.buf.len() - self.pos);
+        buf[..len].copy_from_slice(&self.buf[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
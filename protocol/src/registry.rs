@@ -0,0 +1,96 @@
+//! Maps raw packet ids to typed packets.
+
+use crate::{
+    error::ProtocolError,
+    packets::{
+        configuration::{
+            AcknowledgeFinishConfiguration, ClientInformation, ServerboundKnownPacks,
+            ServerboundPluginMessage,
+        },
+        handshake::HandshakePacket,
+        login::{EncryptionResponse, LoginAcknowledged, LoginStart},
+        play::{
+            ConfigurationAcknowledged, ConfirmTeleportation, PlayerAction, ServerboundKeepAlive,
+        },
+        status::{PingRequest, StatusRequest},
+        Bound, Packet, PacketInfo, ReadPacket, State,
+    },
+};
+
+include!(concat!(env!("OUT_DIR"), "/packet_ids.rs"));
+
+/// The id of packet `P` in `protocol_version`, or `None` if that version doesn't have it.
+///
+/// Versions missing from `data/packets.txt` are assumed to use the ids the packets are declared
+/// with.
+pub fn packet_id<P: PacketInfo>(protocol_version: i32) -> Option<i32> {
+    let Some(column) = PROTOCOL_VERSIONS
+        .iter()
+        .position(|version| *version == protocol_version)
+    else {
+        return Some(P::ID);
+    };
+
+    let ids = PACKET_IDS
+        .iter()
+        .find(|(state, bound, ids)| *state == P::STATE && *bound == P::BOUND && ids[0] == P::ID);
+    match ids {
+        Some((_, _, ids)) if ids[column] < 0 => None,
+        Some((_, _, ids)) => Some(ids[column]),
+        // Not in the data yet, the declared id is all we have
+        None => Some(P::ID),
+    }
+}
+
+macro_rules! serverbound {
+    ($($variant:ident($ty:ty),)*) => {
+        /// Every packet the server knows how to handle, in any state.
+        #[derive(Debug)]
+        pub enum ServerboundPacket {
+            $($variant($ty),)*
+        }
+
+        impl ServerboundPacket {
+            /// Decodes a frame from a client in `state`, using the ids of `protocol_version`.
+            ///
+            /// Any id that has no packet in `state` is a [`ProtocolError::UnknownPacketId`].
+            pub async fn decode(
+                state: State,
+                protocol_version: i32,
+                packet: &mut Packet,
+            ) -> Result<ServerboundPacket, ProtocolError> {
+                $(
+                    if <$ty as PacketInfo>::STATE == state
+                        && packet_id::<$ty>(protocol_version) == Some(packet.id)
+                    {
+                        let decoded = <$ty as ReadPacket>::read_packet(&mut packet.data).await?;
+                        return Ok(ServerboundPacket::$variant(decoded));
+                    }
+                )*
+                Err(ProtocolError::UnknownPacketId(packet.id))
+            }
+        }
+
+        // Catch clientbound packets that were put in here by mistake
+        const _: () = {
+            $(assert!(matches!(<$ty as PacketInfo>::BOUND, Bound::Server));)*
+        };
+    };
+}
+
+serverbound! {
+    Handshake(HandshakePacket),
+    StatusRequest(StatusRequest),
+    PingRequest(PingRequest),
+    LoginStart(LoginStart),
+    EncryptionResponse(EncryptionResponse),
+    LoginAcknowledged(LoginAcknowledged),
+    ClientInformation(ClientInformation),
+    PluginMessage(ServerboundPluginMessage),
+    AcknowledgeFinishConfiguration(AcknowledgeFinishConfiguration),
+    KnownPacks(ServerboundKnownPacks),
+    ConfigurationAcknowledged(ConfigurationAcknowledged),
+    ConfirmTeleportation(ConfirmTeleportation),
+    KeepAlive(ServerboundKeepAlive),
+    PlayerAction(PlayerAction),
+}
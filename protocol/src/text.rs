@@ -0,0 +1,649 @@
+//! [Text components](https://wiki.vg/Text_formatting), used for the MOTD, disconnect messages
+//! and chat.
+//!
+//! Components are sent as JSON in the status response and older protocols, and as NBT since
+//! 1.20.3.
+
+use alloc::{boxed::Box, format, string::String, vec::Vec};
+use core::fmt;
+use embedded_io_async::Write;
+use serde::{ser::SerializeMap, Serialize, Serializer};
+
+use crate::{
+    error::ProtocolError,
+    nbt::{Compound, Nbt},
+    types::{Uuid, WriteType},
+    write::WriteExtension,
+};
+
+/// A piece of formatted text, followed by its `extra` children which inherit its style.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextComponent {
+    pub content: Content,
+    pub style: Style,
+    pub extra: Vec<TextComponent>,
+}
+
+/// What a component displays, before its children.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Content {
+    Text(String),
+    /// A translation key from the client's language file, with the components to put into it.
+    Translate {
+        key: String,
+        with: Vec<TextComponent>,
+    },
+    /// The key bound to a control, like `key.jump`.
+    Keybind(String),
+    /// The score of `name` in `objective`.
+    Score {
+        name: String,
+        objective: String,
+    },
+}
+
+/// Formatting for a component. Anything left as `None` is inherited from the parent.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Style {
+    pub color: Option<Color>,
+    pub bold: Option<bool>,
+    pub italic: Option<bool>,
+    pub underlined: Option<bool>,
+    pub strikethrough: Option<bool>,
+    pub obfuscated: Option<bool>,
+    pub font: Option<String>,
+    /// Text inserted into chat when the component is shift-clicked.
+    pub insertion: Option<String>,
+    pub click_event: Option<ClickEvent>,
+    pub hover_event: Option<HoverEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    Black,
+    DarkBlue,
+    DarkGreen,
+    DarkAqua,
+    DarkRed,
+    DarkPurple,
+    Gold,
+    Gray,
+    DarkGray,
+    Blue,
+    Green,
+    Aqua,
+    Red,
+    LightPurple,
+    Yellow,
+    White,
+    /// Any other color, as `0xRRGGBB`.
+    Rgb(u32),
+}
+
+const NAMED_COLORS: [(Color, char, &str); 16] = [
+    (Color::Black, '0', "black"),
+    (Color::DarkBlue, '1', "dark_blue"),
+    (Color::DarkGreen, '2', "dark_green"),
+    (Color::DarkAqua, '3', "dark_aqua"),
+    (Color::DarkRed, '4', "dark_red"),
+    (Color::DarkPurple, '5', "dark_purple"),
+    (Color::Gold, '6', "gold"),
+    (Color::Gray, '7', "gray"),
+    (Color::DarkGray, '8', "dark_gray"),
+    (Color::Blue, '9', "blue"),
+    (Color::Green, 'a', "green"),
+    (Color::Aqua, 'b', "aqua"),
+    (Color::Red, 'c', "red"),
+    (Color::LightPurple, 'd', "light_purple"),
+    (Color::Yellow, 'e', "yellow"),
+    (Color::White, 'f', "white"),
+];
+
+impl Color {
+    /// The color for a legacy `§` formatting code.
+    pub fn from_code(code: char) -> Option<Color> {
+        let code = code.to_ascii_lowercase();
+        NAMED_COLORS
+            .iter()
+            .find(|(_, c, _)| *c == code)
+            .map(|(color, _, _)| *color)
+    }
+
+    /// The name of the color, or `#rrggbb` for [`Color::Rgb`].
+    pub fn name(&self) -> String {
+        match self {
+            Color::Rgb(rgb) => format!("#{:06x}", rgb & 0xFF_FFFF),
+            color => NAMED_COLORS
+                .iter()
+                .find(|(c, _, _)| c == color)
+                .map(|(_, _, name)| String::from(*name))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClickEvent {
+    pub action: ClickAction,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClickAction {
+    OpenUrl,
+    RunCommand,
+    SuggestCommand,
+    ChangePage,
+    CopyToClipboard,
+}
+
+impl ClickAction {
+    fn name(&self) -> &'static str {
+        match self {
+            ClickAction::OpenUrl => "open_url",
+            ClickAction::RunCommand => "run_command",
+            ClickAction::SuggestCommand => "suggest_command",
+            ClickAction::ChangePage => "change_page",
+            ClickAction::CopyToClipboard => "copy_to_clipboard",
+        }
+    }
+}
+
+impl Serialize for ClickAction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HoverEvent {
+    ShowText(Box<TextComponent>),
+    ShowItem {
+        id: String,
+        count: i32,
+    },
+    ShowEntity {
+        kind: String,
+        id: Uuid,
+        name: Option<Box<TextComponent>>,
+    },
+}
+
+impl HoverEvent {
+    fn action(&self) -> &'static str {
+        match self {
+            HoverEvent::ShowText(_) => "show_text",
+            HoverEvent::ShowItem { .. } => "show_item",
+            HoverEvent::ShowEntity { .. } => "show_entity",
+        }
+    }
+
+    fn contents_nbt(&self) -> Nbt {
+        match self {
+            HoverEvent::ShowText(text) => text.to_nbt(),
+            HoverEvent::ShowItem { id, count } => {
+                let mut contents = Compound::new();
+                contents.insert("id", id.as_str());
+                contents.insert("count", *count);
+                Nbt::Compound(contents)
+            }
+            HoverEvent::ShowEntity { kind, id, name } => {
+                let mut contents = Compound::new();
+                contents.insert("type", kind.as_str());
+                // UUIDs are four ints in NBT, most significant first
+                let ints = (0..4).rev().map(|i| (id.0 >> (i * 32)) as i32).collect();
+                contents.insert("id", Nbt::IntArray(ints));
+                if let Some(name) = name {
+                    contents.insert("name", name.to_nbt());
+                }
+                Nbt::Compound(contents)
+            }
+        }
+    }
+}
+
+impl Serialize for HoverEvent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("action", self.action())?;
+        match self {
+            HoverEvent::ShowText(text) => map.serialize_entry("contents", text)?,
+            HoverEvent::ShowItem { id, count } => {
+                map.serialize_entry("contents", &ItemContents { id, count: *count })?
+            }
+            HoverEvent::ShowEntity { kind, id, name } => map.serialize_entry(
+                "contents",
+                &EntityContents {
+                    kind,
+                    id: format!("{}", id),
+                    name: name.as_deref(),
+                },
+            )?,
+        }
+        map.end()
+    }
+}
+
+#[derive(Serialize)]
+struct ItemContents<'a> {
+    id: &'a str,
+    count: i32,
+}
+
+#[derive(Serialize)]
+struct EntityContents<'a> {
+    #[serde(rename = "type")]
+    kind: &'a str,
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a TextComponent>,
+}
+
+#[derive(Serialize)]
+struct ScoreContents<'a> {
+    name: &'a str,
+    objective: &'a str,
+}
+
+impl TextComponent {
+    pub fn text(text: impl Into<String>) -> TextComponent {
+        TextComponent::new(Content::Text(text.into()))
+    }
+
+    pub fn translate(key: impl Into<String>, with: Vec<TextComponent>) -> TextComponent {
+        TextComponent::new(Content::Translate {
+            key: key.into(),
+            with,
+        })
+    }
+
+    pub fn keybind(key: impl Into<String>) -> TextComponent {
+        TextComponent::new(Content::Keybind(key.into()))
+    }
+
+    pub fn score(name: impl Into<String>, objective: impl Into<String>) -> TextComponent {
+        TextComponent::new(Content::Score {
+            name: name.into(),
+            objective: objective.into(),
+        })
+    }
+
+    fn new(content: Content) -> TextComponent {
+        TextComponent {
+            content,
+            style: Style::default(),
+            extra: Vec::new(),
+        }
+    }
+
+    pub fn color(mut self, color: Color) -> TextComponent {
+        self.style.color = Some(color);
+        self
+    }
+
+    pub fn bold(mut self, bold: bool) -> TextComponent {
+        self.style.bold = Some(bold);
+        self
+    }
+
+    pub fn italic(mut self, italic: bool) -> TextComponent {
+        self.style.italic = Some(italic);
+        self
+    }
+
+    pub fn underlined(mut self, underlined: bool) -> TextComponent {
+        self.style.underlined = Some(underlined);
+        self
+    }
+
+    pub fn strikethrough(mut self, strikethrough: bool) -> TextComponent {
+        self.style.strikethrough = Some(strikethrough);
+        self
+    }
+
+    pub fn obfuscated(mut self, obfuscated: bool) -> TextComponent {
+        self.style.obfuscated = Some(obfuscated);
+        self
+    }
+
+    pub fn click(mut self, action: ClickAction, value: impl Into<String>) -> TextComponent {
+        self.style.click_event = Some(ClickEvent {
+            action,
+            value: value.into(),
+        });
+        self
+    }
+
+    pub fn hover(mut self, event: HoverEvent) -> TextComponent {
+        self.style.hover_event = Some(event);
+        self
+    }
+
+    /// Adds a child after this component's content.
+    pub fn append(mut self, child: impl Into<TextComponent>) -> TextComponent {
+        self.extra.push(child.into());
+        self
+    }
+
+    /// Parses text with legacy formatting codes like `§aHello §lthere`.
+    ///
+    /// Like vanilla, a color code clears any formatting before it and unknown codes are dropped.
+    pub fn from_legacy(text: &str) -> TextComponent {
+        let mut parts = Vec::new();
+        let mut style = Style::default();
+        let mut current = String::new();
+
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c != '§' {
+                current.push(c);
+                continue;
+            }
+            let Some(code) = chars.next() else {
+                current.push(c);
+                break;
+            };
+
+            let mut next = style.clone();
+            match code.to_ascii_lowercase() {
+                'k' => next.obfuscated = Some(true),
+                'l' => next.bold = Some(true),
+                'm' => next.strikethrough = Some(true),
+                'n' => next.underlined = Some(true),
+                'o' => next.italic = Some(true),
+                'r' => next = Style::default(),
+                code => match Color::from_code(code) {
+                    Some(color) => {
+                        next = Style {
+                            color: Some(color),
+                            ..Style::default()
+                        }
+                    }
+                    None => continue,
+                },
+            }
+
+            if !current.is_empty() {
+                parts.push(TextComponent {
+                    content: Content::Text(core::mem::take(&mut current)),
+                    style,
+                    extra: Vec::new(),
+                });
+            }
+            style = next;
+        }
+        if !current.is_empty() {
+            parts.push(TextComponent {
+                content: Content::Text(current),
+                style,
+                extra: Vec::new(),
+            });
+        }
+
+        match parts.len() {
+            0 => TextComponent::text(""),
+            1 => parts.remove(0),
+            // Siblings don't inherit from each other, so each part keeps its own style
+            _ => TextComponent {
+                content: Content::Text(String::new()),
+                style: Style::default(),
+                extra: parts,
+            },
+        }
+    }
+
+    /// Writes the component with legacy formatting codes, for clients that predate components.
+    ///
+    /// RGB colors have no code, so text in them comes out in the default color. Anything that
+    /// isn't formatting, like click events, is left out.
+    pub fn to_legacy(&self) -> String {
+        let mut out = String::new();
+        let mut written = LegacyStyle::default();
+        self.write_legacy(LegacyStyle::default(), &mut written, &mut out);
+        out
+    }
+
+    fn write_legacy(&self, parent: LegacyStyle, written: &mut LegacyStyle, out: &mut String) {
+        let style = LegacyStyle {
+            color: match self.style.color {
+                Some(Color::Rgb(_)) => None,
+                Some(color) => Some(color),
+                None => parent.color,
+            },
+            flags: [
+                self.style.obfuscated.unwrap_or(parent.flags[0]),
+                self.style.bold.unwrap_or(parent.flags[1]),
+                self.style.strikethrough.unwrap_or(parent.flags[2]),
+                self.style.underlined.unwrap_or(parent.flags[3]),
+                self.style.italic.unwrap_or(parent.flags[4]),
+            ],
+        };
+
+        let text = match &self.content {
+            Content::Text(text) => text,
+            Content::Translate { key, .. } => key,
+            Content::Keybind(key) => key,
+            Content::Score { name, .. } => name,
+        };
+        if !text.is_empty() {
+            // Codes can only turn formatting on, and a color code turns it all off again
+            let dropped = (0..5).any(|i| written.flags[i] && !style.flags[i]);
+            if dropped || style.color != written.color {
+                let code = style.color.and_then(|color| {
+                    NAMED_COLORS
+                        .iter()
+                        .find(|(c, _, _)| *c == color)
+                        .map(|(_, code, _)| *code)
+                });
+                out.push('§');
+                out.push(code.unwrap_or('r'));
+                *written = LegacyStyle {
+                    color: style.color,
+                    flags: [false; 5],
+                };
+            }
+            for (i, code) in ['k', 'l', 'm', 'n', 'o'].into_iter().enumerate() {
+                if style.flags[i] && !written.flags[i] {
+                    out.push('§');
+                    out.push(code);
+                    written.flags[i] = true;
+                }
+            }
+            out.push_str(text);
+        }
+
+        for child in &self.extra {
+            child.write_legacy(style, written, out);
+        }
+    }
+
+    /// Turns the component into NBT, as sent since 1.20.3.
+    ///
+    /// Plain text without any formatting is sent as just a string.
+    pub fn to_nbt(&self) -> Nbt {
+        match &self.content {
+            Content::Text(text) if self.style == Style::default() && self.extra.is_empty() => {
+                Nbt::String(text.clone())
+            }
+            _ => Nbt::Compound(self.to_compound()),
+        }
+    }
+
+    fn to_compound(&self) -> Compound {
+        let mut compound = Compound::new();
+        match &self.content {
+            Content::Text(text) => {
+                compound.insert("text", text.as_str());
+            }
+            Content::Translate { key, with } => {
+                compound.insert("translate", key.as_str());
+                if !with.is_empty() {
+                    compound.insert("with", list(with));
+                }
+            }
+            Content::Keybind(key) => {
+                compound.insert("keybind", key.as_str());
+            }
+            Content::Score { name, objective } => {
+                let mut score = Compound::new();
+                score.insert("name", name.as_str());
+                score.insert("objective", objective.as_str());
+                compound.insert("score", score);
+            }
+        }
+
+        let style = &self.style;
+        if let Some(color) = style.color {
+            compound.insert("color", color.name());
+        }
+        for (name, value) in style.flags() {
+            if let Some(value) = value {
+                compound.insert(name, value);
+            }
+        }
+        if let Some(font) = &style.font {
+            compound.insert("font", font.as_str());
+        }
+        if let Some(insertion) = &style.insertion {
+            compound.insert("insertion", insertion.as_str());
+        }
+        if let Some(click) = &style.click_event {
+            let mut event = Compound::new();
+            event.insert("action", click.action.name());
+            event.insert("value", click.value.as_str());
+            compound.insert("clickEvent", event);
+        }
+        if let Some(hover) = &style.hover_event {
+            let mut event = Compound::new();
+            event.insert("action", hover.action());
+            event.insert("contents", hover.contents_nbt());
+            compound.insert("hoverEvent", event);
+        }
+
+        if !self.extra.is_empty() {
+            compound.insert("extra", list(&self.extra));
+        }
+        compound
+    }
+}
+
+impl Style {
+    fn flags(&self) -> [(&'static str, Option<bool>); 5] {
+        [
+            ("bold", self.bold),
+            ("italic", self.italic),
+            ("underlined", self.underlined),
+            ("strikethrough", self.strikethrough),
+            ("obfuscated", self.obfuscated),
+        ]
+    }
+}
+
+/// A list of components, which have to all be compounds since NBT lists can't mix tags.
+fn list(components: &[TextComponent]) -> Vec<Nbt> {
+    components
+        .iter()
+        .map(|component| Nbt::Compound(component.to_compound()))
+        .collect()
+}
+
+impl From<&str> for TextComponent {
+    fn from(text: &str) -> Self {
+        TextComponent::text(text)
+    }
+}
+
+impl From<String> for TextComponent {
+    fn from(text: String) -> Self {
+        TextComponent::text(text)
+    }
+}
+
+impl Serialize for TextComponent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        match &self.content {
+            Content::Text(text) => map.serialize_entry("text", text)?,
+            Content::Translate { key, with } => {
+                map.serialize_entry("translate", key)?;
+                if !with.is_empty() {
+                    map.serialize_entry("with", with)?;
+                }
+            }
+            Content::Keybind(key) => map.serialize_entry("keybind", key)?,
+            Content::Score { name, objective } => {
+                map.serialize_entry("score", &ScoreContents { name, objective })?
+            }
+        }
+
+        let style = &self.style;
+        if let Some(color) = &style.color {
+            map.serialize_entry("color", color)?;
+        }
+        for (name, value) in style.flags() {
+            if let Some(value) = value {
+                map.serialize_entry(name, &value)?;
+            }
+        }
+        if let Some(font) = &style.font {
+            map.serialize_entry("font", font)?;
+        }
+        if let Some(insertion) = &style.insertion {
+            map.serialize_entry("insertion", insertion)?;
+        }
+        if let Some(click) = &style.click_event {
+            map.serialize_entry("clickEvent", click)?;
+        }
+        if let Some(hover) = &style.hover_event {
+            map.serialize_entry("hoverEvent", hover)?;
+        }
+
+        if !self.extra.is_empty() {
+            map.serialize_entry("extra", &self.extra)?;
+        }
+        map.end()
+    }
+}
+
+impl WriteType for TextComponent {
+    /// Writes the component as NBT. Protocols before 1.20.3 want
+    /// [`write_json`](WriteExtension::write_json) instead.
+    async fn write_type<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_nbt(&self.to_nbt()).await
+    }
+}
+
+/// The formatting legacy codes can express, with the flags in code order from `§k` to `§o`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct LegacyStyle {
+    color: Option<Color>,
+    flags: [bool; 5],
+}
+
+impl fmt::Display for TextComponent {
+    /// Writes just the text, without any formatting. Translations show their key.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.content {
+            Content::Text(text) => f.write_str(text)?,
+            Content::Translate { key, .. } => f.write_str(key)?,
+            Content::Keybind(key) => f.write_str(key)?,
+            Content::Score { name, .. } => f.write_str(name)?,
+        }
+        for child in &self.extra {
+            write!(f, "{}", child)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for TextComponent {
+    fn default() -> Self {
+        TextComponent::text(String::new())
+    }
+}
@@ -0,0 +1,396 @@
+//! Data types from [wiki.vg](https://wiki.vg/Protocol#Data_types) that don't map directly onto a
+//! Rust primitive.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+use embedded_io_async::{Read, Write};
+
+use crate::{error::ProtocolError, read::ReadExtension, write::WriteExtension};
+
+/// A type that can be read on its own, so it can be used in prefixed arrays and optional fields.
+pub trait ReadType: Sized {
+    async fn read_type<R: Read + ?Sized>(socket: &mut R) -> Result<Self, ProtocolError>;
+}
+
+/// A type that can be written on its own, so it can be used in prefixed arrays and optional
+/// fields.
+pub trait WriteType {
+    async fn write_type<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError>;
+}
+
+macro_rules! impl_type {
+    ($ty:ty, $read:ident, $write:ident) => {
+        impl ReadType for $ty {
+            async fn read_type<R: Read + ?Sized>(socket: &mut R) -> Result<Self, ProtocolError> {
+                socket.$read().await
+            }
+        }
+
+        impl WriteType for $ty {
+            async fn write_type<W: Write + ?Sized>(
+                &self,
+                socket: &mut W,
+            ) -> Result<(), ProtocolError> {
+                socket.$write(*self).await
+            }
+        }
+    };
+}
+
+impl_type!(i8, read_i8, write_i8);
+impl_type!(u8, read_u8, write_u8);
+impl_type!(i16, read_i16, write_i16);
+impl_type!(u16, read_u16, write_u16);
+impl_type!(i32, read_i32, write_i32);
+impl_type!(i64, read_i64, write_i64);
+impl_type!(u64, read_u64, write_u64);
+impl_type!(i128, read_i128, write_i128);
+impl_type!(u128, read_u128, write_u128);
+impl_type!(f32, read_f32, write_f32);
+impl_type!(f64, read_f64, write_f64);
+impl_type!(bool, read_bool, write_bool);
+impl_type!(Uuid, read_uuid, write_uuid);
+impl_type!(Position, read_position, write_position);
+impl_type!(Angle, read_angle, write_angle);
+
+impl ReadType for String {
+    async fn read_type<R: Read + ?Sized>(socket: &mut R) -> Result<Self, ProtocolError> {
+        socket.read_string().await
+    }
+}
+
+impl WriteType for String {
+    async fn write_type<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_string(self).await
+    }
+}
+
+impl ReadType for Identifier {
+    async fn read_type<R: Read + ?Sized>(socket: &mut R) -> Result<Self, ProtocolError> {
+        socket.read_identifier().await
+    }
+}
+
+impl WriteType for Identifier {
+    async fn write_type<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_identifier(self).await
+    }
+}
+
+impl ReadType for BitSet {
+    async fn read_type<R: Read + ?Sized>(socket: &mut R) -> Result<Self, ProtocolError> {
+        socket.read_bitset().await
+    }
+}
+
+impl WriteType for BitSet {
+    async fn write_type<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_bitset(self).await
+    }
+}
+
+impl<const N: usize> ReadType for FixedBitSet<N> {
+    async fn read_type<R: Read + ?Sized>(socket: &mut R) -> Result<Self, ProtocolError> {
+        socket.read_fixed_bitset().await
+    }
+}
+
+impl<const N: usize> WriteType for FixedBitSet<N> {
+    async fn write_type<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_fixed_bitset(self).await
+    }
+}
+
+impl ReadType for VarInt {
+    async fn read_type<R: Read + ?Sized>(socket: &mut R) -> Result<Self, ProtocolError> {
+        Ok(VarInt(socket.read_varint().await?))
+    }
+}
+
+impl WriteType for VarInt {
+    async fn write_type<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_varint(self.0).await
+    }
+}
+
+impl ReadType for VarLong {
+    async fn read_type<R: Read + ?Sized>(socket: &mut R) -> Result<Self, ProtocolError> {
+        Ok(VarLong(socket.read_varlong().await?))
+    }
+}
+
+impl WriteType for VarLong {
+    async fn write_type<W: Write + ?Sized>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket.write_varlong(self.0).await
+    }
+}
+
+/// An `i32` that is encoded as a VarInt when used in a prefixed array or optional field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VarInt(pub i32);
+
+/// An `i64` that is encoded as a VarLong when used in a prefixed array or optional field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VarLong(pub i64);
+
+/// A 128-bit UUID, sent as two big endian longs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uuid(pub u128);
+
+impl Uuid {
+    /// The UUID vanilla gives a player in offline mode, a version 3 UUID of
+    /// `OfflinePlayer:<name>`.
+    pub fn offline_player(name: &str) -> Uuid {
+        use md5::{Digest, Md5};
+
+        let mut hasher = Md5::new();
+        hasher.update(b"OfflinePlayer:");
+        hasher.update(name.as_bytes());
+        let mut bytes: [u8; 16] = hasher.finalize().into();
+
+        // Set the version to 3 and the variant to RFC 4122
+        bytes[6] = (bytes[6] & 0x0f) | 0x30;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        Uuid(u128::from_be_bytes(bytes))
+    }
+
+    /// Parses 32 hex digits without hyphens, the way Mojang's APIs write UUIDs.
+    pub fn from_simple(hex: &str) -> Option<Uuid> {
+        if hex.len() != 32 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        u128::from_str_radix(hex, 16).ok().map(Uuid)
+    }
+}
+
+impl fmt::Display for Uuid {
+    /// Formats the UUID with hyphens, like `069a79f4-44e9-4726-a5be-fca90e38aaf5`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self.0.to_be_bytes();
+        for (i, byte) in bytes.iter().enumerate() {
+            if matches!(i, 4 | 6 | 8 | 10) {
+                write!(f, "-")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// A block position, packed into a long as 26 bits of x, 26 bits of z and 12 bits of y.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Position {
+    pub fn new(x: i32, y: i32, z: i32) -> Position {
+        Position { x, y, z }
+    }
+
+    pub fn from_packed(value: i64) -> Position {
+        Position {
+            x: (value >> 38) as i32,
+            // Shift left first so the sign is extended
+            y: (value << 52 >> 52) as i32,
+            z: (value << 26 >> 38) as i32,
+        }
+    }
+
+    pub fn to_packed(self) -> i64 {
+        ((self.x as i64 & 0x3FF_FFFF) << 38)
+            | ((self.z as i64 & 0x3FF_FFFF) << 12)
+            | (self.y as i64 & 0xFFF)
+    }
+}
+
+/// A rotation angle in steps of 1/256 of a full turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Angle(pub u8);
+
+impl Angle {
+    pub fn from_degrees(degrees: f32) -> Angle {
+        Angle((degrees * 256.0 / 360.0) as i32 as u8)
+    }
+
+    pub fn to_degrees(self) -> f32 {
+        self.0 as f32 * 360.0 / 256.0
+    }
+}
+
+/// A namespaced location like `minecraft:stone`.
+///
+/// The namespace may only contain `a-z0-9.-_`, and the path may also contain `/`. Without a
+/// namespace, `minecraft` is assumed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Identifier {
+    value: String,
+    separator: usize,
+}
+
+impl Identifier {
+    pub fn new(value: &str) -> Result<Identifier, ProtocolError> {
+        let (namespace, path) = value.split_once(':').unwrap_or(("minecraft", value));
+
+        let valid = |c: char| matches!(c, 'a'..='z' | '0'..='9' | '.' | '-' | '_');
+        if namespace.is_empty()
+            || !namespace.chars().all(valid)
+            || !path.chars().all(|c| valid(c) || c == '/')
+        {
+            return Err(ProtocolError::InvalidIdentifier);
+        }
+
+        let mut string = String::with_capacity(namespace.len() + 1 + path.len());
+        string.push_str(namespace);
+        string.push(':');
+        string.push_str(path);
+
+        Ok(Identifier {
+            value: string,
+            separator: namespace.len(),
+        })
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.value[..self.separator]
+    }
+
+    pub fn path(&self) -> &str {
+        &self.value[self.separator + 1..]
+    }
+
+    /// The full `namespace:path` form.
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.value)
+    }
+}
+
+/// A list of strings packed back to back, each behind a one byte length.
+///
+/// Build scripts generate these for tables that live in flash, since they don't need a pointer
+/// per string like a `&[&str]` does. Strings are at most 127 bytes long, so their length is also
+/// a valid VarInt and they can be written out as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StringList<'a>(&'a [u8]);
+
+impl<'a> StringList<'a> {
+    pub const fn new(packed: &'a [u8]) -> StringList<'a> {
+        StringList(packed)
+    }
+
+    pub fn iter(&self) -> StringListIter<'a> {
+        StringListIter(self.0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&'a str> {
+        self.iter().nth(index)
+    }
+
+    /// The index of `string`, which is what other packets refer to registry entries by.
+    pub fn position(&self, string: &str) -> Option<usize> {
+        self.iter().position(|other| other == string)
+    }
+}
+
+impl<'a> IntoIterator for StringList<'a> {
+    type Item = &'a str;
+    type IntoIter = StringListIter<'a>;
+
+    fn into_iter(self) -> StringListIter<'a> {
+        self.iter()
+    }
+}
+
+/// The strings in a [`StringList`]. This stops early if the list is malformed.
+#[derive(Debug, Clone)]
+pub struct StringListIter<'a>(&'a [u8]);
+
+impl<'a> Iterator for StringListIter<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let (&length, rest) = self.0.split_first()?;
+        let string = rest.get(..length as usize)?;
+        self.0 = &rest[length as usize..];
+        core::str::from_utf8(string).ok()
+    }
+}
+
+/// A bit set of any length, sent as a prefixed array of longs.
+///
+/// Bit `i` is in long `i / 64`, at position `i % 64`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BitSet(pub Vec<u64>);
+
+impl BitSet {
+    pub fn new() -> BitSet {
+        BitSet(Vec::new())
+    }
+
+    pub fn get(&self, bit: usize) -> bool {
+        self.0
+            .get(bit / 64)
+            .is_some_and(|long| long & (1 << (bit % 64)) != 0)
+    }
+
+    pub fn set(&mut self, bit: usize, value: bool) {
+        if bit / 64 >= self.0.len() {
+            if !value {
+                return;
+            }
+            self.0.resize(bit / 64 + 1, 0);
+        }
+
+        if value {
+            self.0[bit / 64] |= 1 << (bit % 64);
+        } else {
+            self.0[bit / 64] &= !(1 << (bit % 64));
+        }
+    }
+}
+
+/// A bit set with a length known from context, sent as `N` bytes.
+///
+/// Bit `i` is in byte `i / 8`, at position `i % 8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FixedBitSet<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> FixedBitSet<N> {
+    pub fn new() -> FixedBitSet<N> {
+        FixedBitSet([0; N])
+    }
+
+    pub fn get(&self, bit: usize) -> bool {
+        self.0[bit / 8] & (1 << (bit % 8)) != 0
+    }
+
+    pub fn set(&mut self, bit: usize, value: bool) {
+        if value {
+            self.0[bit / 8] |= 1 << (bit % 8);
+        } else {
+            self.0[bit / 8] &= !(1 << (bit % 8));
+        }
+    }
+}
+
+impl<const N: usize> Default for FixedBitSet<N> {
+    fn default() -> Self {
+        FixedBitSet::new()
+    }
+}
@@ -0,0 +1,162 @@
+use alloc::vec::Vec;
+use embedded_io_async::Write;
+use serde::Serialize;
+
+use crate::{
+    error::ProtocolError,
+    nbt::{self, Nbt, NbtPayload},
+    read::MAX_STRING_LENGTH,
+    types::{Angle, BitSet, FixedBitSet, Identifier, Position, Uuid, WriteType},
+};
+
+macro_rules! impl_write {
+    ($ty:ty, $write:ident) => {
+        async fn $write(&mut self, value: $ty) -> Result<(), ProtocolError> {
+            self.write_all(&value.to_be_bytes())
+                .await
+                .map_err(ProtocolError::io)
+        }
+    };
+}
+
+/// This is implemented for every [`Write`], so any transport gets these for free.
+pub trait WriteExtension: Write {
+    impl_write!(i8, write_i8);
+    impl_write!(u8, write_u8);
+    impl_write!(i16, write_i16);
+    impl_write!(u16, write_u16);
+    impl_write!(i32, write_i32);
+    impl_write!(i64, write_i64);
+    impl_write!(u64, write_u64);
+    impl_write!(i128, write_i128);
+    impl_write!(u128, write_u128);
+    impl_write!(f32, write_f32);
+    impl_write!(f64, write_f64);
+
+    async fn write_bool(&mut self, value: bool) -> Result<(), ProtocolError> {
+        self.write_u8(if value { 1 } else { 0 }).await
+    }
+
+    async fn write_string(&mut self, value: &str) -> Result<(), ProtocolError> {
+        self.write_prefixed_byte_array(value.as_bytes()).await
+    }
+
+    async fn write_identifier(&mut self, value: &Identifier) -> Result<(), ProtocolError> {
+        self.write_string(value.as_str()).await
+    }
+
+    async fn write_varint(&mut self, value: i32) -> Result<(), ProtocolError> {
+        self.write_varlong(value as u32 as i64).await
+    }
+
+    async fn write_varlong(&mut self, value: i64) -> Result<(), ProtocolError> {
+        let mut value = value as u64;
+        loop {
+            let mut byte = (value & 0b0111_1111) as u8;
+            value >>= 7;
+            if value != 0 {
+                byte |= 0b1000_0000;
+            }
+            self.write_u8(byte).await?;
+            if value == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn write_uuid(&mut self, value: Uuid) -> Result<(), ProtocolError> {
+        self.write_u128(value.0).await
+    }
+
+    async fn write_position(&mut self, value: Position) -> Result<(), ProtocolError> {
+        self.write_i64(value.to_packed()).await
+    }
+
+    async fn write_angle(&mut self, value: Angle) -> Result<(), ProtocolError> {
+        self.write_u8(value.0).await
+    }
+
+    /// Writes bytes without a length, for when the length is known from context.
+    async fn write_byte_array(&mut self, value: &[u8]) -> Result<(), ProtocolError> {
+        self.write_all(value).await.map_err(ProtocolError::io)
+    }
+
+    /// Writes a byte array prefixed with its length as a VarInt.
+    async fn write_prefixed_byte_array(&mut self, value: &[u8]) -> Result<(), ProtocolError> {
+        self.write_varint(value.len() as i32).await?;
+        self.write_byte_array(value).await
+    }
+
+    async fn write_bitset(&mut self, value: &BitSet) -> Result<(), ProtocolError> {
+        self.write_varint(value.0.len() as i32).await?;
+        for long in &value.0 {
+            self.write_u64(*long).await?;
+        }
+        Ok(())
+    }
+
+    async fn write_fixed_bitset<const N: usize>(
+        &mut self,
+        value: &FixedBitSet<N>,
+    ) -> Result<(), ProtocolError> {
+        self.write_byte_array(&value.0).await
+    }
+
+    /// Writes a value prefixed with a boolean saying whether it is present.
+    async fn write_optional<T: WriteType>(
+        &mut self,
+        value: &Option<T>,
+    ) -> Result<(), ProtocolError> {
+        self.write_bool(value.is_some()).await?;
+        if let Some(value) = value {
+            value.write_type(self).await?;
+        }
+        Ok(())
+    }
+
+    /// Writes an array prefixed with its length as a VarInt.
+    async fn write_prefixed<T: WriteType>(&mut self, values: &[T]) -> Result<(), ProtocolError> {
+        self.write_varint(values.len() as i32).await?;
+        for value in values {
+            value.write_type(self).await?;
+        }
+        Ok(())
+    }
+
+    /// Writes a value as a JSON string.
+    async fn write_json<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ProtocolError> {
+        self.write_prefixed_byte_array(&to_json(value)?).await
+    }
+
+    /// Writes NBT with a nameless root, as sent over the network since 1.20.2.
+    async fn write_nbt(&mut self, value: &Nbt) -> Result<(), ProtocolError> {
+        self.write_u8(value.tag() as u8).await?;
+        value.write_payload(self).await
+    }
+
+    /// Writes NBT with a named root, as stored in files.
+    async fn write_named_nbt(&mut self, name: &str, value: &Nbt) -> Result<(), ProtocolError> {
+        self.write_u8(value.tag() as u8).await?;
+        nbt::write_string(self, name).await?;
+        value.write_payload(self).await
+    }
+}
+
+impl<T: Write + ?Sized> WriteExtension for T {}
+
+/// Serializes `value` to JSON, growing the buffer until it fits in a string.
+fn to_json<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, ProtocolError> {
+    let mut buf = alloc::vec![0; 256];
+    loop {
+        match serde_json_core::to_slice(value, &mut buf) {
+            Ok(written) => {
+                buf.truncate(written);
+                return Ok(buf);
+            }
+            Err(serde_json_core::ser::Error::BufferFull) if buf.len() < MAX_STRING_LENGTH * 3 => {
+                buf.resize(buf.len() * 2, 0);
+            }
+            Err(_) => return Err(ProtocolError::InvalidJson),
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use embassy_futures::block_on;
+use picocraft_protocol::{
+    chunk::{
+        write_heightmaps, write_light, write_sections, ChunkColumn, ChunkLayout, ContainerKind,
+        PalettedContainer, LIGHT_ARRAY_LENGTH,
+    },
+    nbt::Nbt,
+    read::ReadExtension,
+    types::BitSet,
+};
+
+/// Stone up to `height`, then air, in four sections, with a different biome in each half.
+struct Hill {
+    height: usize,
+}
+
+impl ChunkColumn for Hill {
+    fn sections(&self) -> usize {
+        4
+    }
+
+    fn block(&self, _x: usize, y: usize, _z: usize) -> u32 {
+        if y < self.height {
+            1
+        } else {
+            0
+        }
+    }
+
+    fn biome(&self, x: usize, _y: usize, _z: usize) -> u32 {
+        if x < 2 {
+            39
+        } else {
+            7
+        }
+    }
+
+    fn height(&self, _x: usize, _z: usize) -> usize {
+        self.height
+    }
+}
+
+fn container(kind: ContainerKind, value: impl Fn(usize) -> u32) -> (PalettedContainer, Vec<u8>) {
+    let container = PalettedContainer::new(kind, &value);
+    let mut out = Vec::new();
+    block_on(container.write(&mut out, &value)).unwrap();
+    assert_eq!(out.len(), container.encoded_len());
+    (container, out)
+}
+
+#[test]
+fn writes_single_value_container() {
+    let (container, out) = container(ContainerKind::BLOCKS, |_| 1);
+
+    assert_eq!(container.bits(), 0);
+    // No bits, stone, no longs
+    assert_eq!(out, [0x00, 0x01, 0x00]);
+}
+
+#[test]
+fn writes_indirect_container() {
+    // Stone on the bottom layer, air above
+    let (container, out) = container(ContainerKind::BLOCKS, |i| (i < 256) as u32);
+
+    // Indirect block palettes have at least 4 bits
+    assert_eq!(container.bits(), 4);
+    assert_eq!(container.palette(), [1, 0]);
+    assert_eq!(&out[..4], [0x04, 0x02, 0x01, 0x00]);
+    // 16 entries to a long, so 256 longs
+    assert_eq!(&out[4..6], [0x80, 0x02]);
+    let longs = &out[6..];
+    assert_eq!(longs.len(), 256 * 8);
+    // The first layer is the first palette entry, the rest the second
+    assert!(longs[..16 * 8].iter().all(|byte| *byte == 0));
+    assert!(longs[16 * 8..].iter().all(|byte| *byte == 0x11));
+}
+
+#[test]
+fn packs_entries_without_straddling_longs() {
+    // Three biomes take two bits, but each long only holds 64 / 2 = 32 of them
+    let (container, out) = container(ContainerKind::biomes(64), |i| (i % 3) as u32);
+
+    assert_eq!(container.bits(), 2);
+    assert_eq!(&out[..5], [0x02, 0x03, 0x00, 0x01, 0x02]);
+    assert_eq!(out[5], 2);
+    let first = u64::from_be_bytes(out[6..14].try_into().unwrap());
+    for i in 0..32 {
+        assert_eq!((first >> (2 * i)) & 0b11, i % 3);
+    }
+}
+
+#[test]
+fn writes_direct_container() {
+    // Every biome is different, more than an indirect palette can hold
+    let (container, out) = container(ContainerKind::biomes(64), |i| i as u32);
+
+    assert_eq!(container.bits(), 6);
+    assert!(container.palette().is_empty());
+    // No palette, then 64 / (64 / 6) longs
+    assert_eq!(&out[..2], [0x06, 0x07]);
+    let first = u64::from_be_bytes(out[2..10].try_into().unwrap());
+    assert_eq!(first & 0b111111, 0);
+    assert_eq!((first >> 6) & 0b111111, 1);
+    assert_eq!(out.len(), 2 + 7 * 8);
+}
+
+#[test]
+fn writes_sections_with_length() {
+    let column = Hill { height: 20 };
+    let mut out = Vec::new();
+    block_on(write_sections(
+        &mut out,
+        &column,
+        &ChunkLayout::new(&column, 64),
+    ))
+    .unwrap();
+
+    let mut data = &out[..];
+    let length = block_on(data.read_varint()).unwrap();
+    assert_eq!(length as usize, data.len());
+
+    // The first section is all stone, with both biomes
+    assert_eq!(block_on(data.read_i16()).unwrap(), 4096);
+    assert_eq!(
+        block_on(data.read_byte_array(3)).unwrap(),
+        [0x00, 0x01, 0x00]
+    );
+    assert_eq!(
+        block_on(data.read_byte_array(4)).unwrap(),
+        [0x01, 0x02, 39, 7]
+    );
+    // One bit per cell, so one long
+    assert_eq!(block_on(data.read_varint()).unwrap(), 1);
+    block_on(data.read_i64()).unwrap();
+
+    // The second has four layers of stone
+    assert_eq!(block_on(data.read_i16()).unwrap(), 4 * 256);
+}
+
+#[test]
+fn writes_heightmaps() {
+    let column = Hill { height: 20 };
+    let mut out = Vec::new();
+    block_on(write_heightmaps(&mut out, &column)).unwrap();
+
+    let Nbt::Compound(heightmaps) = block_on((&out[..]).read_nbt()).unwrap() else {
+        panic!("heightmaps should be a compound");
+    };
+    let Some(Nbt::LongArray(heights)) = heightmaps.get("MOTION_BLOCKING") else {
+        panic!("no MOTION_BLOCKING heightmap");
+    };
+    // Four sections need 7 bits for heights up to 64, so 9 to a long
+    assert_eq!(heights.len(), 29);
+    assert_eq!(heights[0] & 0x7f, 20);
+    assert_eq!((heights[0] >> 56) & 0x7f, 20);
+    assert_eq!(heights[28], 20 | 20 << 7 | 20 << 14 | 20 << 21);
+    assert_eq!(
+        heightmaps.get("WORLD_SURFACE"),
+        heightmaps.get("MOTION_BLOCKING")
+    );
+}
+
+#[test]
+fn leaves_out_sky_light_above_the_ground() {
+    let column = Hill { height: 20 };
+    let mut out = Vec::new();
+    block_on(write_light(
+        &mut out,
+        &column,
+        &ChunkLayout::new(&column, 64),
+    ))
+    .unwrap();
+
+    let mut data = &out[..];
+    let sky = block_on(data.read_bitset()).unwrap();
+    let block = block_on(data.read_bitset()).unwrap();
+    let empty_sky = block_on(data.read_bitset()).unwrap();
+    let empty_block = block_on(data.read_bitset()).unwrap();
+
+    // Below the world and the first section are dark, the second is partly lit and the rest are
+    // fully lit, so they're left out
+    let mut expected = BitSet::new();
+    expected.set(2, true);
+    assert_eq!(sky, expected);
+    let mut expected = BitSet::new();
+    expected.set(0, true);
+    expected.set(1, true);
+    assert_eq!(empty_sky, expected);
+    // No block light anywhere
+    assert_eq!(block, BitSet::new());
+    let mut expected = BitSet::new();
+    for bit in 0..6 {
+        expected.set(bit, true);
+    }
+    assert_eq!(empty_block, expected);
+
+    assert_eq!(block_on(data.read_varint()).unwrap(), 1);
+    let array = block_on(data.read_prefixed_byte_array()).unwrap();
+    assert_eq!(array.len(), LIGHT_ARRAY_LENGTH);
+    // Four layers of stone in the section, then light
+    assert!(array[..4 * 128].iter().all(|byte| *byte == 0));
+    assert!(array[4 * 128..].iter().all(|byte| *byte == 0xff));
+    assert_eq!(block_on(data.read_varint()).unwrap(), 0);
+    assert!(data.is_empty());
+}
@@ -0,0 +1,113 @@
+use embassy_futures::block_on;
+use picocraft_protocol::{
+    error::ProtocolError,
+    frame::{write_frame, FrameDecoder},
+    packets::{
+        handshake::HandshakePacket, Bound, PacketInfo, ReadPacket, State, WritePacket,
+        PROTOCOL_VERSION,
+    },
+    types::{Uuid, VarInt},
+    McPacket,
+};
+
+#[derive(Debug, PartialEq, McPacket)]
+#[packet(id = 0x42, state = Login, bound = Client)]
+struct Everything {
+    #[varint]
+    count: i32,
+    #[varlong]
+    big: i64,
+    id: Uuid,
+    #[string(max = 4)]
+    short: String,
+    #[prefixed]
+    list: Vec<VarInt>,
+    #[optional]
+    maybe: Option<u16>,
+    flag: bool,
+    #[rest]
+    rest: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, McPacket)]
+#[packet(id = 0x00, state = Status, bound = Server)]
+struct Empty;
+
+fn everything() -> Everything {
+    Everything {
+        count: 300,
+        big: -1,
+        id: Uuid(1),
+        short: String::from("abcd"),
+        list: vec![VarInt(1), VarInt(2)],
+        maybe: Some(7),
+        flag: true,
+        rest: vec![9, 9, 9],
+    }
+}
+
+#[test]
+fn generates_packet_info() {
+    assert_eq!(Everything::ID, 0x42);
+    assert_eq!(Everything::STATE, State::Login);
+    assert_eq!(Everything::BOUND, Bound::Client);
+
+    assert_eq!(HandshakePacket::ID, 0x00);
+    assert_eq!(HandshakePacket::STATE, State::Handshake);
+    assert_eq!(HandshakePacket::BOUND, Bound::Server);
+}
+
+#[test]
+fn writes_fields_in_order() {
+    let mut out = Vec::new();
+    block_on(everything().write_packet(&mut out)).unwrap();
+
+    let mut expected = vec![0xac, 0x02];
+    expected.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]);
+    expected.extend_from_slice(&1u128.to_be_bytes());
+    expected.extend_from_slice(b"\x04abcd");
+    expected.extend_from_slice(&[0x02, 0x01, 0x02]);
+    expected.extend_from_slice(&[0x01, 0x00, 0x07]);
+    expected.extend_from_slice(&[0x01]);
+    expected.extend_from_slice(&[9, 9, 9]);
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn round_trips_through_frames() {
+    let mut out = Vec::new();
+    block_on(write_frame(&mut out, PROTOCOL_VERSION, &everything())).unwrap();
+    block_on(write_frame(&mut out, PROTOCOL_VERSION, &Empty)).unwrap();
+
+    let mut decoder = FrameDecoder::new();
+    decoder.push(&out);
+
+    let mut packet = block_on(decoder.next_frame()).unwrap().unwrap();
+    assert_eq!(packet.id, Everything::ID);
+    assert_eq!(
+        block_on(Everything::read_packet(&mut packet.data)).unwrap(),
+        everything()
+    );
+
+    let mut packet = block_on(decoder.next_frame()).unwrap().unwrap();
+    assert_eq!(packet.id, Empty::ID);
+    assert_eq!(
+        block_on(Empty::read_packet(&mut packet.data)).unwrap(),
+        Empty
+    );
+}
+
+#[test]
+fn enforces_string_max() {
+    let mut packet = everything();
+    packet.short = String::from("abcde");
+
+    let mut out = Vec::new();
+    block_on(packet.write_packet(&mut out)).unwrap();
+
+    let mut data = &out[..];
+    assert_eq!(
+        block_on(Everything::read_packet(&mut data)).unwrap_err(),
+        ProtocolError::StringTooLong { length: 5, max: 4 }
+    );
+}
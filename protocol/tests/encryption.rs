@@ -0,0 +1,46 @@
+use embassy_futures::block_on;
+use embedded_io_async::{Read, Write};
+use picocraft_protocol::encryption::CipherStream;
+
+const SECRET: [u8; 16] = *b"sixteen byte key";
+
+fn encrypt(chunks: &[&[u8]]) -> Vec<u8> {
+    let mut stream = CipherStream::new(Vec::new());
+    stream.enable(&SECRET);
+    for chunk in chunks {
+        block_on(stream.write_all(chunk)).unwrap();
+    }
+    stream.into_inner()
+}
+
+#[test]
+fn passes_bytes_through_until_enabled() {
+    let mut stream = CipherStream::new(Vec::new());
+    block_on(stream.write_all(b"hello")).unwrap();
+    assert!(!stream.is_enabled());
+    assert_eq!(stream.into_inner(), b"hello");
+}
+
+#[test]
+fn encryption_carries_on_between_writes() {
+    let data: Vec<u8> = (0..=255).cycle().take(300).collect();
+
+    let whole = encrypt(&[&data]);
+    assert_ne!(whole, data);
+    assert_eq!(whole, encrypt(&[&data[..1], &data[1..100], &data[100..]]));
+}
+
+#[test]
+fn decrypts_what_was_encrypted() {
+    let data: Vec<u8> = (0..=255).cycle().take(300).collect();
+    let encrypted = encrypt(&[&data]);
+
+    let mut stream = CipherStream::new(&encrypted[..]);
+    stream.enable(&SECRET);
+    let mut decrypted = vec![0; data.len()];
+    // Split up differently to how it was written
+    for chunk in decrypted.chunks_mut(7) {
+        block_on(stream.read_exact(chunk)).unwrap();
+    }
+    assert_eq!(decrypted, data);
+}
@@ -0,0 +1,276 @@
+use embassy_futures::block_on;
+use embedded_io_async::{ErrorType, Read};
+use picocraft_protocol::{
+    error::ProtocolError,
+    frame::{FrameDecoder, FrameEncoder},
+    packets::{
+        configuration::{ClientboundPluginMessage, ServerboundPluginMessage},
+        handshake::HandshakePacket,
+        status::PingRequest,
+        ReadPacket, PROTOCOL_VERSION,
+    },
+    types::Identifier,
+};
+
+const HANDSHAKE: &[u8] = &[
+    0x10, 0x00, 0xfb, 0x05, 0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't', 0x63, 0xdd,
+    0x01,
+];
+const STATUS_REQUEST: &[u8] = &[0x01, 0x00];
+const PING_REQUEST: &[u8] = &[0x09, 0x01, 0, 0, 0, 0, 0, 0, 0, 0x2a];
+
+/// A reader that only ever hands out one byte at a time, like a very unlucky TCP connection.
+struct Trickle<'a>(&'a [u8]);
+
+impl ErrorType for Trickle<'_> {
+    type Error = core::convert::Infallible;
+}
+
+impl Read for Trickle<'_> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.0.is_empty() || buf.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = self.0[0];
+        self.0 = &self.0[1..];
+        Ok(1)
+    }
+}
+
+#[test]
+fn waits_for_whole_frame() {
+    let mut decoder = FrameDecoder::new();
+
+    for byte in &HANDSHAKE[..HANDSHAKE.len() - 1] {
+        decoder.push(&[*byte]);
+        assert!(block_on(decoder.next_frame()).unwrap().is_none());
+    }
+    decoder.push(&HANDSHAKE[HANDSHAKE.len() - 1..]);
+
+    let mut packet = block_on(decoder.next_frame()).unwrap().unwrap();
+    assert_eq!(packet.id, 0x00);
+    let handshake = block_on(HandshakePacket::read_packet(&mut packet.data)).unwrap();
+    assert_eq!(handshake.server_address, "localhost");
+    assert_eq!(handshake.server_port, 25565);
+    assert!(block_on(decoder.next_frame()).unwrap().is_none());
+}
+
+#[test]
+fn splits_coalesced_frames() {
+    let mut decoder = FrameDecoder::new();
+    decoder.push(&[HANDSHAKE, STATUS_REQUEST, &PING_REQUEST[..4]].concat());
+
+    assert_eq!(block_on(decoder.next_frame()).unwrap().unwrap().id, 0x00);
+    assert_eq!(block_on(decoder.next_frame()).unwrap().unwrap().id, 0x00);
+    assert!(block_on(decoder.next_frame()).unwrap().is_none());
+
+    decoder.push(&PING_REQUEST[4..]);
+    let mut packet = block_on(decoder.next_frame()).unwrap().unwrap();
+    assert_eq!(packet.id, 0x01);
+    let ping = block_on(PingRequest::read_packet(&mut packet.data)).unwrap();
+    assert_eq!(ping.payload, 42);
+}
+
+#[test]
+fn waits_for_multi_byte_length() {
+    // 200 bytes of body needs a two byte length prefix
+    let mut frame = vec![0xc8, 0x01, 0x05];
+    frame.extend_from_slice(&[0xaa; 199]);
+
+    let mut decoder = FrameDecoder::new();
+    decoder.push(&frame[..1]);
+    assert!(block_on(decoder.next_frame()).unwrap().is_none());
+    decoder.push(&frame[1..]);
+
+    let packet = block_on(decoder.next_frame()).unwrap().unwrap();
+    assert_eq!(packet.id, 0x05);
+}
+
+#[test]
+fn reads_frames_one_byte_at_a_time() {
+    let stream = [HANDSHAKE, STATUS_REQUEST, PING_REQUEST].concat();
+    let mut socket = Trickle(&stream);
+    let mut decoder = FrameDecoder::new();
+
+    let mut handshake = block_on(decoder.read_packet(&mut socket)).unwrap();
+    let handshake = block_on(HandshakePacket::read_packet(&mut handshake.data)).unwrap();
+    assert_eq!(handshake.protocol_version, 763);
+
+    assert_eq!(block_on(decoder.read_packet(&mut socket)).unwrap().id, 0x00);
+
+    let mut ping = block_on(decoder.read_packet(&mut socket)).unwrap();
+    assert_eq!(ping.id, 0x01);
+    let ping = block_on(PingRequest::read_packet(&mut ping.data)).unwrap();
+    assert_eq!(ping.payload, 42);
+
+    assert_eq!(
+        block_on(decoder.read_packet(&mut socket)).unwrap_err(),
+        ProtocolError::ConnectionClosed
+    );
+}
+
+#[test]
+fn eof_inside_frame_is_unexpected() {
+    let mut socket = Trickle(&HANDSHAKE[..5]);
+    let mut decoder = FrameDecoder::new();
+
+    assert_eq!(
+        block_on(decoder.read_packet(&mut socket)).unwrap_err(),
+        ProtocolError::UnexpectedEof
+    );
+}
+
+#[test]
+fn rejects_oversized_length() {
+    let mut decoder = FrameDecoder::new();
+    // 2^21, one more than fits in three VarInt bytes
+    decoder.push(&[0x80, 0x80, 0x80, 0x01]);
+
+    assert_eq!(
+        block_on(decoder.next_frame()).unwrap_err(),
+        ProtocolError::PacketTooLong(2097152)
+    );
+}
+
+#[test]
+fn rejects_negative_length() {
+    let mut decoder = FrameDecoder::new();
+    decoder.push(&[0xff, 0xff, 0xff, 0xff, 0x0f]);
+
+    assert_eq!(
+        block_on(decoder.next_frame()).unwrap_err(),
+        ProtocolError::PacketTooLong(-1)
+    );
+}
+
+#[test]
+fn rejects_overlong_length_varint() {
+    let mut decoder = FrameDecoder::new();
+    decoder.push(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00]);
+
+    assert_eq!(
+        block_on(decoder.next_frame()).unwrap_err(),
+        ProtocolError::VarIntTooLong
+    );
+}
+
+fn plugin_message(data: Vec<u8>) -> ClientboundPluginMessage {
+    ClientboundPluginMessage {
+        channel: Identifier::new("picocraft:test").unwrap(),
+        data,
+    }
+}
+
+/// Writes `data` in a plugin message and reads it back, returning the frame that was sent.
+fn round_trip(threshold: usize, data: Vec<u8>) -> Vec<u8> {
+    let mut encoder = FrameEncoder::new(PROTOCOL_VERSION);
+    encoder.set_compression(Some(threshold));
+    let mut frame = Vec::new();
+    block_on(encoder.write_packet(&mut frame, &plugin_message(data.clone()))).unwrap();
+
+    let mut decoder = FrameDecoder::new();
+    decoder.set_compression(Some(threshold));
+    decoder.push(&frame);
+    let mut packet = block_on(decoder.next_frame()).unwrap().unwrap();
+    assert_eq!(packet.id, 0x01);
+    let message = block_on(ServerboundPluginMessage::read_packet(&mut packet.data)).unwrap();
+    assert_eq!(message.data, data);
+    assert!(block_on(decoder.next_frame()).unwrap().is_none());
+    frame
+}
+
+#[test]
+fn leaves_small_packets_uncompressed() {
+    let frame = round_trip(256, vec![1, 2, 3]);
+    // Length, a data length of zero, then the packet as usual
+    assert_eq!(&frame[..3], &[0x14, 0x00, 0x01]);
+}
+
+#[test]
+fn compresses_large_packets() {
+    let data = b"PicoCraft ".repeat(100);
+    let frame = round_trip(256, data);
+    assert!(frame.len() < 200);
+}
+
+#[test]
+fn compresses_past_the_window() {
+    // Long enough for the compressor to slide its window a few times, and not too repetitive
+    let data: Vec<u8> = (0..20_000u32)
+        .map(|i| (i.wrapping_mul(i) >> 7) as u8)
+        .collect();
+    round_trip(256, data);
+}
+
+#[test]
+fn compresses_incompressible_packets() {
+    let mut state = 0x1234_5678u32;
+    let data: Vec<u8> = (0..5000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    round_trip(64, data);
+}
+
+#[test]
+fn rejects_compressed_packet_under_threshold() {
+    let mut encoder = FrameEncoder::new(PROTOCOL_VERSION);
+    encoder.set_compression(Some(64));
+    let mut frame = Vec::new();
+    block_on(encoder.write_packet(&mut frame, &plugin_message(vec![0; 100]))).unwrap();
+
+    let mut decoder = FrameDecoder::new();
+    decoder.set_compression(Some(256));
+    decoder.push(&frame);
+    assert_eq!(
+        block_on(decoder.next_frame()).unwrap_err(),
+        ProtocolError::InvalidCompression
+    );
+}
+
+#[test]
+fn rejects_bad_compressed_data() {
+    let mut decoder = FrameDecoder::new();
+    decoder.set_compression(Some(16));
+    // Claims 32 bytes of data, but isn't zlib
+    decoder.push(&[0x05, 0x20, 0x01, 0x02, 0x03, 0x04]);
+    assert_eq!(
+        block_on(decoder.next_frame()).unwrap_err(),
+        ProtocolError::InvalidCompression
+    );
+}
+
+#[test]
+fn rejects_frames_over_the_max_length() {
+    let mut decoder = FrameDecoder::new();
+    decoder.set_max_length(100);
+    // Rejected as soon as the length arrives, before buffering the rest
+    decoder.push(&[0x65]);
+
+    assert_eq!(
+        block_on(decoder.next_frame()).unwrap_err(),
+        ProtocolError::PacketTooLong(101)
+    );
+}
+
+#[test]
+fn rejects_packets_decompressing_over_the_max_length() {
+    let mut encoder = FrameEncoder::new(PROTOCOL_VERSION);
+    encoder.set_compression(Some(64));
+    let mut frame = Vec::new();
+    block_on(encoder.write_packet(&mut frame, &plugin_message(vec![0; 1000]))).unwrap();
+    assert!(frame.len() < 100);
+
+    let mut decoder = FrameDecoder::new();
+    decoder.set_compression(Some(64));
+    decoder.set_max_length(100);
+    decoder.push(&frame);
+    assert!(matches!(
+        block_on(decoder.next_frame()),
+        Err(ProtocolError::PacketTooLong(length)) if length > 1000
+    ));
+}
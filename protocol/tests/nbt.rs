@@ -0,0 +1,173 @@
+use embassy_futures::block_on;
+use picocraft_protocol::{
+    error::ProtocolError,
+    nbt::{Compound, Nbt, NbtWriter, Tag, MAX_DEPTH},
+    read::ReadExtension,
+    write::WriteExtension,
+};
+
+// hello_world.nbt from the NBT spec
+const HELLO_WORLD: &[u8] = &[
+    0x0a, 0x00, 0x0b, b'h', b'e', b'l', b'l', b'o', b' ', b'w', b'o', b'r', b'l', b'd', 0x08, 0x00,
+    0x04, b'n', b'a', b'm', b'e', 0x00, 0x09, b'B', b'a', b'n', b'a', b'n', b'r', b'a', b'm', b'a',
+    0x00,
+];
+
+fn every_tag() -> Nbt {
+    let mut inner = Compound::new();
+    inner.insert("byte", 1i8);
+    inner.insert("short", -2i16);
+    inner.insert("int", 3i32);
+    inner.insert("long", -4i64);
+    inner.insert("float", 5.5f32);
+    inner.insert("double", -6.25f64);
+    inner.insert("bytes", vec![1i8, -1]);
+    inner.insert("string", "stone");
+    inner.insert("ints", vec![1i32, 2, 3]);
+    inner.insert("longs", vec![i64::MIN, i64::MAX]);
+    inner.insert("empty", Vec::<Nbt>::new());
+
+    let mut root = Compound::new();
+    root.insert("list", vec![Nbt::from(inner.clone()), Nbt::from(inner)]);
+    Nbt::Compound(root)
+}
+
+#[test]
+fn named_root_round_trip() {
+    let mut data = HELLO_WORLD;
+    let (name, nbt) = block_on(data.read_named_nbt()).unwrap();
+    assert!(data.is_empty());
+    assert_eq!(name, "hello world");
+
+    let Nbt::Compound(compound) = &nbt else {
+        panic!("expected a compound");
+    };
+    assert_eq!(compound.get("name"), Some(&Nbt::from("Bananrama")));
+
+    let mut out = Vec::new();
+    block_on(out.write_named_nbt(&name, &nbt)).unwrap();
+    assert_eq!(out, HELLO_WORLD);
+}
+
+#[test]
+fn network_root_has_no_name() {
+    let mut data = HELLO_WORLD;
+    let (_, nbt) = block_on(data.read_named_nbt()).unwrap();
+
+    let mut out = Vec::new();
+    block_on(out.write_nbt(&nbt)).unwrap();
+    assert_eq!(out[0], 0x0a);
+    assert_eq!(out[1..], HELLO_WORLD[14..]);
+}
+
+#[test]
+fn every_tag_round_trip() {
+    let nbt = every_tag();
+
+    let mut out = Vec::new();
+    block_on(out.write_nbt(&nbt)).unwrap();
+
+    let mut data = out.as_slice();
+    assert_eq!(block_on(data.read_nbt()).unwrap(), nbt);
+    assert!(data.is_empty());
+}
+
+#[test]
+fn streaming_writer_matches_tree() {
+    let mut tree = Compound::new();
+    let mut level = Compound::new();
+    level.insert("seed", 42i64);
+    level.insert("heights", vec![64i64; 4]);
+    tree.insert("level", level);
+    tree.insert("name", "world");
+    tree.insert(
+        "spawn",
+        vec![Nbt::from(vec![1i32, 2]), Nbt::from(vec![3i32])],
+    );
+    tree.insert("players", vec![Nbt::from(Compound::new())]);
+
+    let mut expected = Vec::new();
+    block_on(expected.write_nbt(&Nbt::Compound(tree))).unwrap();
+
+    let mut out = Vec::new();
+    block_on(async {
+        let mut nbt = NbtWriter::network(&mut out).await?;
+        nbt.compound("level").await?;
+        nbt.field("seed", &42i64).await?;
+        nbt.field("heights", &[64i64; 4][..]).await?;
+        nbt.end().await?;
+        nbt.field("name", "world").await?;
+        nbt.list("spawn", Tag::IntArray, 2).await?;
+        nbt.element(&[1i32, 2][..]).await?;
+        nbt.element(&[3i32][..]).await?;
+        nbt.list("players", Tag::Compound, 1).await?;
+        nbt.end().await?;
+        nbt.end().await
+    })
+    .unwrap();
+
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn modified_utf8_strings() {
+    let mut compound = Compound::new();
+    compound.insert("a\0b", "\u{1F600}");
+
+    let mut out = Vec::new();
+    block_on(out.write_nbt(&Nbt::Compound(compound.clone()))).unwrap();
+    assert_eq!(
+        out,
+        [
+            0x0a, 0x08, 0x00, 0x04, b'a', 0xc0, 0x80, b'b', 0x00, 0x06, 0xed, 0xa0, 0xbd, 0xed,
+            0xb8, 0x80, 0x00
+        ]
+    );
+
+    let mut data = out.as_slice();
+    assert_eq!(block_on(data.read_nbt()).unwrap(), Nbt::Compound(compound));
+}
+
+#[test]
+fn rejects_invalid_tags() {
+    let mut data: &[u8] = &[0x0d];
+    assert_eq!(
+        block_on(data.read_nbt()).unwrap_err(),
+        ProtocolError::InvalidNbtTag(13)
+    );
+
+    let mut data: &[u8] = &[0x00];
+    assert_eq!(
+        block_on(data.read_nbt()).unwrap_err(),
+        ProtocolError::InvalidNbtTag(0)
+    );
+
+    let mixed = Nbt::List(vec![Nbt::Int(1), Nbt::Long(2)]);
+    assert_eq!(
+        block_on(Vec::new().write_nbt(&mixed)).unwrap_err(),
+        ProtocolError::InvalidNbtTag(4)
+    );
+}
+
+#[test]
+fn rejects_negative_lengths() {
+    let mut data: &[u8] = &[0x0b, 0xff, 0xff, 0xff, 0xff];
+    assert_eq!(
+        block_on(data.read_nbt()).unwrap_err(),
+        ProtocolError::InvalidLength(-1)
+    );
+}
+
+#[test]
+fn rejects_deep_nesting() {
+    let mut bytes = vec![0x09];
+    for _ in 0..=MAX_DEPTH {
+        bytes.extend_from_slice(&[0x09, 0x00, 0x00, 0x00, 0x01]);
+    }
+
+    let mut data = bytes.as_slice();
+    assert_eq!(
+        block_on(data.read_nbt()).unwrap_err(),
+        ProtocolError::NbtTooDeep
+    );
+}
@@ -0,0 +1,420 @@
+use embassy_futures::block_on;
+use embedded_io_async::Write;
+use picocraft_protocol::{
+    error::ProtocolError,
+    frame::{write_frame, FrameDecoder},
+    packets::{
+        configuration::{
+            ClientInformation, KnownPack, RegistryData, RegistryTags, ServerboundKnownPacks,
+            UpdateTags,
+        },
+        handshake::HandshakePacket,
+        legacy::{write_legacy_status, LegacyPing},
+        login::{is_valid_username, EncryptionResponse, LoginSuccess, Property},
+        status::{PingRequest, PlayerData, PongResponse, StatusJson, VersionData},
+        Bound, PacketInfo, ReadPacket, State, WritePacket, MAX_PACKET_LENGTH, PROTOCOL_VERSION,
+    },
+    read::{ReadExtension, Slice},
+    text::TextComponent,
+    types::{Identifier, StringList, Uuid, VarInt},
+    write::WriteExtension,
+};
+
+fn slice(bytes: &[u8]) -> Slice {
+    Slice::new(bytes.to_vec().into_boxed_slice())
+}
+
+#[test]
+fn reads_handshake() {
+    // Protocol 763, "localhost", port 25565, next state Status
+    let mut data = slice(&[
+        0xfb, 0x05, 0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't', 0x63, 0xdd, 0x01,
+    ]);
+    let packet = block_on(HandshakePacket::read_packet(&mut data)).unwrap();
+
+    assert_eq!(packet.protocol_version, 763);
+    assert_eq!(packet.server_address, "localhost");
+    assert_eq!(packet.server_port, 25565);
+    assert!(matches!(packet.next_state, State::Status));
+}
+
+#[test]
+fn reads_ping_request() {
+    let mut data = slice(&0x0123_4567_89ab_cdefi64.to_be_bytes());
+    let packet = block_on(PingRequest::read_packet(&mut data)).unwrap();
+
+    assert_eq!(packet.payload, 0x0123_4567_89ab_cdef);
+}
+
+#[test]
+fn reads_varints() {
+    let mut data = slice(&[0x00, 0x7f, 0x80, 0x01, 0xff, 0xff, 0xff, 0xff, 0x0f]);
+
+    assert_eq!(block_on(data.read_varint()).unwrap(), 0);
+    assert_eq!(block_on(data.read_varint()).unwrap(), 127);
+    assert_eq!(block_on(data.read_varint()).unwrap(), 128);
+    assert_eq!(block_on(data.read_varint()).unwrap(), -1);
+}
+
+#[test]
+fn parses_packet_from_any_reader() {
+    let mut bytes: &[u8] = &[0x09, 0x01, 0, 0, 0, 0, 0, 0, 0, 0x2a];
+    let mut packet = block_on(FrameDecoder::new().read_packet(&mut bytes)).unwrap();
+
+    assert_eq!(packet.id, 0x01);
+    let ping = block_on(PingRequest::read_packet(&mut packet.data)).unwrap();
+    assert_eq!(ping.payload, 42);
+}
+
+#[test]
+fn writes_pong_to_any_writer() {
+    let mut out = Vec::new();
+    block_on(write_frame(
+        &mut out,
+        PROTOCOL_VERSION,
+        &PongResponse { payload: 42 },
+    ))
+    .unwrap();
+
+    assert_eq!(out, [0x09, 0x01, 0, 0, 0, 0, 0, 0, 0, 0x2a]);
+}
+
+#[test]
+fn frames_status_response() {
+    let status = StatusJson {
+        version: VersionData {
+            name: "1.20.1".to_string(),
+            protocol: 763,
+        },
+        players: None,
+        description: None,
+        favicon: None,
+        enforces_secure_chat: false,
+    };
+    let json = br#"{"version":{"name":"1.20.1","protocol":763},"players":null,"description":null,"favicon":null,"enforcesSecureChat":false}"#;
+
+    let mut out = Vec::new();
+    block_on(write_frame(&mut out, PROTOCOL_VERSION, &status)).unwrap();
+
+    let mut expected = vec![json.len() as u8 + 2, 0x00, json.len() as u8];
+    expected.extend_from_slice(json);
+    assert_eq!(out, expected);
+
+    let mut decoder = FrameDecoder::new();
+    decoder.push(&out);
+    let mut packet = block_on(decoder.next_frame()).unwrap().unwrap();
+    assert_eq!(packet.id, 0x00);
+    assert_eq!(
+        block_on(packet.data.read_string()).unwrap().as_bytes(),
+        json
+    );
+}
+
+struct Huge;
+
+impl PacketInfo for Huge {
+    const ID: i32 = 0x7f;
+    const STATE: State = State::Play;
+    const BOUND: Bound = Bound::Client;
+}
+
+impl WritePacket for Huge {
+    async fn write_packet<W: Write>(&self, socket: &mut W) -> Result<(), ProtocolError> {
+        socket
+            .write_byte_array(&[0; MAX_PACKET_LENGTH as usize])
+            .await
+    }
+}
+
+#[test]
+fn refuses_to_write_oversized_packet() {
+    let mut out = Vec::new();
+
+    assert_eq!(
+        block_on(write_frame(&mut out, PROTOCOL_VERSION, &Huge)).unwrap_err(),
+        ProtocolError::PacketTooLong(MAX_PACKET_LENGTH + 1)
+    );
+    assert!(out.is_empty());
+}
+
+#[test]
+fn writes_varints() {
+    let mut out = Vec::new();
+    for value in [0, 127, 128, -1] {
+        block_on(out.write_varint(value)).unwrap();
+    }
+
+    assert_eq!(out, [0x00, 0x7f, 0x80, 0x01, 0xff, 0xff, 0xff, 0xff, 0x0f]);
+}
+
+#[test]
+fn rejects_overlong_varint() {
+    let mut data = slice(&[0xff, 0xff, 0xff, 0xff, 0xff, 0x01]);
+
+    assert_eq!(
+        block_on(data.read_varint()).unwrap_err(),
+        ProtocolError::VarIntTooLong
+    );
+}
+
+#[test]
+fn rejects_truncated_packet() {
+    let mut data = slice(&[0x00, 0x00, 0x00]);
+
+    assert_eq!(
+        block_on(PingRequest::read_packet(&mut data)).unwrap_err(),
+        ProtocolError::UnexpectedEof
+    );
+}
+
+#[test]
+fn rejects_invalid_utf8() {
+    let mut data = slice(&[0x02, 0xc3, 0x28]);
+
+    assert_eq!(
+        block_on(data.read_string()).unwrap_err(),
+        ProtocolError::InvalidUtf8
+    );
+}
+
+#[test]
+fn rejects_long_encryption_response() {
+    // A shared secret claiming to be 2^31 - 1 bytes long
+    let mut data = slice(&[0xff, 0xff, 0xff, 0xff, 0x07, 0x00]);
+    assert_eq!(
+        block_on(EncryptionResponse::read_packet(&mut data)).unwrap_err(),
+        ProtocolError::InvalidLength(i32::MAX)
+    );
+
+    let mut bytes = vec![0x81, 0x01];
+    bytes.extend_from_slice(&[0; 129]);
+    bytes.push(0x00);
+    assert_eq!(
+        block_on(EncryptionResponse::read_packet(&mut slice(&bytes))).unwrap_err(),
+        ProtocolError::InvalidLength(129)
+    );
+}
+
+#[test]
+fn rejects_long_server_address() {
+    let mut bytes = vec![0xfb, 0x05, 0x80, 0x02];
+    bytes.extend_from_slice(&[b'a'; 256]);
+    bytes.extend_from_slice(&[0x63, 0xdd, 0x01]);
+    let mut data = slice(&bytes);
+
+    assert_eq!(
+        block_on(HandshakePacket::read_packet(&mut data)).unwrap_err(),
+        ProtocolError::StringTooLong {
+            length: 256,
+            max: 255
+        }
+    );
+}
+
+#[test]
+fn rejects_invalid_next_state() {
+    let mut data = slice(&[0xfb, 0x05, 0x00, 0x63, 0xdd, 0x00]);
+
+    assert_eq!(
+        block_on(HandshakePacket::read_packet(&mut data)).unwrap_err(),
+        ProtocolError::InvalidEnumValue(0)
+    );
+}
+
+#[test]
+fn writes_login_success_with_properties() {
+    let success = LoginSuccess {
+        uuid: Uuid(1),
+        username: "Pico".to_string(),
+        properties: vec![Property {
+            name: "textures".to_string(),
+            value: "e30=".to_string(),
+            signature: None,
+        }],
+        strict_error_handling: true,
+    };
+
+    let mut out = Vec::new();
+    block_on(success.write_packet(&mut out)).unwrap();
+
+    let mut expected = 1u128.to_be_bytes().to_vec();
+    expected.extend_from_slice(&[0x04, b'P', b'i', b'c', b'o', 0x01]);
+    expected.extend_from_slice(&[0x08, b't', b'e', b'x', b't', b'u', b'r', b'e', b's']);
+    expected.extend_from_slice(&[0x04, b'e', b'3', b'0', b'=', 0x00, 0x01]);
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn validates_usernames() {
+    for name in ["Notch", "a", "_under_score_", "sixteen_letters_"] {
+        assert!(is_valid_username(name), "{}", name);
+    }
+    for name in ["", "seventeen_letters", "with space", "ünïcödé", "dash-ed"] {
+        assert!(!is_valid_username(name), "{}", name);
+    }
+}
+
+#[test]
+fn reads_client_information() {
+    let mut data = slice(&[
+        0x05, b'e', b'n', b'_', b'g', b'b', 0x0c, 0x01, 0x00, 0x7f, 0x00, 0x01, 0x00,
+    ]);
+    let info = block_on(ClientInformation::read_packet(&mut data)).unwrap();
+
+    assert_eq!(info.locale, "en_gb");
+    assert_eq!(info.view_distance, 12);
+    assert_eq!(info.chat_mode, 1);
+    assert!(!info.chat_colors);
+    assert_eq!(info.displayed_skin_parts, 0x7f);
+    assert_eq!(info.main_hand, 0);
+    assert!(info.enable_text_filtering);
+    assert!(!info.allow_server_listings);
+}
+
+#[test]
+fn reads_known_packs() {
+    let mut data = slice(&[
+        0x01, 0x09, b'm', b'i', b'n', b'e', b'c', b'r', b'a', b'f', b't', 0x04, b'c', b'o', b'r',
+        b'e', 0x06, b'1', b'.', b'2', b'1', b'.', b'1',
+    ]);
+    let known = block_on(ServerboundKnownPacks::read_packet(&mut data)).unwrap();
+
+    assert_eq!(known.packs, vec![KnownPack::core("1.21.1")]);
+}
+
+#[test]
+fn writes_registry_data_without_entry_data() {
+    let data = RegistryData {
+        registry: "minecraft:wolf_variant",
+        entries: StringList::new(b"\x0eminecraft:pale\x0fminecraft:snowy"),
+    };
+
+    let mut out = Vec::new();
+    block_on(data.write_packet(&mut out)).unwrap();
+
+    let mut expected = vec![0x16];
+    expected.extend_from_slice(b"minecraft:wolf_variant");
+    expected.push(0x02);
+    expected.push(0x0e);
+    expected.extend_from_slice(b"minecraft:pale");
+    expected.push(0x00);
+    expected.push(0x0f);
+    expected.extend_from_slice(b"minecraft:snowy");
+    expected.push(0x00);
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn writes_update_tags() {
+    let tags = UpdateTags {
+        registries: vec![RegistryTags {
+            registry: Identifier::new("block").unwrap(),
+            tags: vec![(
+                Identifier::new("dirt").unwrap(),
+                vec![VarInt(9), VarInt(10)],
+            )],
+        }],
+    };
+
+    let mut out = Vec::new();
+    block_on(tags.write_packet(&mut out)).unwrap();
+
+    let mut expected = vec![0x01, 0x0f];
+    expected.extend_from_slice(b"minecraft:block");
+    expected.extend_from_slice(&[0x01, 0x0e]);
+    expected.extend_from_slice(b"minecraft:dirt");
+    expected.extend_from_slice(&[0x02, 0x09, 0x0a]);
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn recognises_legacy_pings() {
+    assert_eq!(LegacyPing::parse(&[0xfe]), Some(LegacyPing::Beta));
+    assert_eq!(
+        LegacyPing::parse(&[0xfe, 0x01]),
+        Some(LegacyPing::Versioned)
+    );
+    let mut ping_host = vec![0xfe, 0x01, 0xfa, 0x00, 0x0b];
+    for byte in b"MC|PingHost" {
+        ping_host.extend_from_slice(&[0x00, *byte]);
+    }
+    ping_host.extend_from_slice(&[0x00, 0x07, 0x4a]);
+    assert_eq!(LegacyPing::parse(&ping_host), Some(LegacyPing::Versioned));
+    // Cut off before the channel, or with another one
+    assert_eq!(LegacyPing::parse(&ping_host[..5]), None);
+    ping_host[6] = b'X';
+    assert_eq!(LegacyPing::parse(&ping_host), None);
+    // A handshake
+    assert_eq!(LegacyPing::parse(&[0x10, 0x00, 0xff, 0x05]), None);
+    assert_eq!(LegacyPing::parse(&[]), None);
+}
+
+#[test]
+fn reads_254_byte_handshake_as_modern() {
+    // Protocol 767, a 246 byte address, port 25565, next state Status
+    let mut frame = vec![0xfe, 0x01, 0x00, 0xff, 0x05, 0xf6, 0x01];
+    frame.extend_from_slice(&[b'a'; 246]);
+    frame.extend_from_slice(&[0x63, 0xdd, 0x01]);
+    assert_eq!(frame.len(), 2 + 254);
+
+    // The first read is at most 64 bytes
+    assert_eq!(LegacyPing::parse(&frame[..64]), None);
+    let mut decoder = FrameDecoder::new();
+    decoder.push(&frame);
+    let mut packet = block_on(decoder.next_frame()).unwrap().unwrap();
+    let handshake = block_on(HandshakePacket::read_packet(&mut packet.data)).unwrap();
+    assert_eq!(handshake.server_address.len(), 246);
+    assert_eq!(handshake.next_state, State::Status);
+}
+
+fn legacy_status() -> StatusJson {
+    StatusJson {
+        version: VersionData {
+            name: "1.21.1".to_string(),
+            protocol: 767,
+        },
+        players: Some(PlayerData {
+            max: 4,
+            online: 1,
+            sample: None,
+        }),
+        description: Some(TextComponent::from_legacy("§aPico")),
+        favicon: None,
+        enforces_secure_chat: false,
+    }
+}
+
+fn kick(reason: &str) -> Vec<u8> {
+    let mut expected = vec![0xff];
+    expected.extend_from_slice(&(reason.encode_utf16().count() as u16).to_be_bytes());
+    for unit in reason.encode_utf16() {
+        expected.extend_from_slice(&unit.to_be_bytes());
+    }
+    expected
+}
+
+#[test]
+fn answers_beta_legacy_ping() {
+    let mut out = Vec::new();
+    block_on(write_legacy_status(
+        &mut out,
+        LegacyPing::Beta,
+        &legacy_status(),
+    ))
+    .unwrap();
+
+    assert_eq!(out, kick("Pico§1§4"));
+}
+
+#[test]
+fn answers_versioned_legacy_ping() {
+    let mut out = Vec::new();
+    block_on(write_legacy_status(
+        &mut out,
+        LegacyPing::Versioned,
+        &legacy_status(),
+    ))
+    .unwrap();
+
+    assert_eq!(out, kick("§1\x00127\x001.21.1\x00§aPico\x001\x004"));
+}
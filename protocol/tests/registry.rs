@@ -0,0 +1,155 @@
+use embassy_futures::block_on;
+use picocraft_protocol::{
+    error::ProtocolError,
+    frame::FrameDecoder,
+    packets::{
+        configuration::{FinishConfiguration, ServerboundKnownPacks},
+        login::LoginStart,
+        play::{LoginPlay, PlayerAction, ServerboundKeepAlive, StartConfiguration},
+        Bound, Packet, PacketInfo, State, Version, SUPPORTED_VERSIONS,
+    },
+    registry::{packet_id, ServerboundPacket, PROTOCOL_VERSIONS},
+    types::Position,
+};
+
+const HANDSHAKE: &[u8] = &[
+    0x10, 0x00, 0xfb, 0x05, 0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't', 0x63, 0xdd,
+    0x01,
+];
+const STATUS_REQUEST: &[u8] = &[0x01, 0x00];
+const LOGIN_ACKNOWLEDGED: &[u8] = &[0x01, 0x03];
+const PING_REQUEST: &[u8] = &[0x09, 0x01, 0, 0, 0, 0, 0, 0, 0, 0x2a];
+
+fn frame(bytes: &[u8]) -> Packet {
+    let mut decoder = FrameDecoder::new();
+    decoder.push(bytes);
+    block_on(decoder.next_frame()).unwrap().unwrap()
+}
+
+fn decode(state: State, bytes: &[u8]) -> Result<ServerboundPacket, ProtocolError> {
+    block_on(ServerboundPacket::decode(state, 763, &mut frame(bytes)))
+}
+
+#[test]
+fn decodes_handshake() {
+    let Ok(ServerboundPacket::Handshake(handshake)) = decode(State::Handshake, HANDSHAKE) else {
+        panic!("expected a handshake");
+    };
+
+    assert_eq!(handshake.protocol_version, 763);
+    assert_eq!(handshake.next_state, State::Status);
+}
+
+#[test]
+fn decodes_status_packets() {
+    assert!(matches!(
+        decode(State::Status, STATUS_REQUEST),
+        Ok(ServerboundPacket::StatusRequest(_))
+    ));
+
+    let Ok(ServerboundPacket::PingRequest(ping)) = decode(State::Status, PING_REQUEST) else {
+        panic!("expected a ping request");
+    };
+    assert_eq!(ping.payload, 42);
+}
+
+#[test]
+fn decodes_login_packets() {
+    assert!(matches!(
+        decode(State::Login, LOGIN_ACKNOWLEDGED),
+        Ok(ServerboundPacket::LoginAcknowledged(_))
+    ));
+    assert_eq!(
+        decode(State::Status, LOGIN_ACKNOWLEDGED).unwrap_err(),
+        ProtocolError::UnknownPacketId(0x03)
+    );
+}
+
+#[test]
+fn decodes_player_action() {
+    // Started digging the top of 1, 64, -2, sequence 5
+    let mut bytes = vec![0x0c, 0x24, 0x00];
+    bytes.extend_from_slice(&Position::new(1, 64, -2).to_packed().to_be_bytes());
+    bytes.extend_from_slice(&[0x01, 0x05]);
+
+    let Ok(ServerboundPacket::PlayerAction(action)) = decode(State::Play, &bytes) else {
+        panic!("expected a player action");
+    };
+    assert_eq!(action.status, PlayerAction::STARTED_DIGGING);
+    assert_eq!(action.position, Position::new(1, 64, -2));
+    assert_eq!(action.face, 1);
+    assert_eq!(action.sequence, 5);
+}
+
+#[test]
+fn rejects_packets_from_other_states() {
+    assert_eq!(
+        decode(State::Handshake, PING_REQUEST).unwrap_err(),
+        ProtocolError::UnknownPacketId(0x01)
+    );
+    assert_eq!(
+        decode(State::Transfer, STATUS_REQUEST).unwrap_err(),
+        ProtocolError::UnknownPacketId(0x00)
+    );
+    assert_eq!(
+        decode(State::Custom(7), STATUS_REQUEST).unwrap_err(),
+        ProtocolError::UnknownPacketId(0x00)
+    );
+}
+
+#[test]
+fn rejects_unknown_ids() {
+    assert_eq!(
+        decode(State::Status, &[0x01, 0x7f]).unwrap_err(),
+        ProtocolError::UnknownPacketId(0x7f)
+    );
+}
+
+#[test]
+fn looks_up_packet_ids() {
+    assert!(PROTOCOL_VERSIONS.contains(&767));
+    assert_eq!(packet_id::<LoginStart>(767), Some(0x00));
+    assert_eq!(packet_id::<FinishConfiguration>(767), Some(0x03));
+    assert_eq!(packet_id::<ServerboundKnownPacks>(767), Some(0x07));
+    assert_eq!(packet_id::<StartConfiguration>(767), Some(0x69));
+    assert_eq!(packet_id::<LoginPlay>(767), Some(0x2b));
+    assert_eq!(packet_id::<ServerboundKeepAlive>(767), Some(0x18));
+    assert_eq!(packet_id::<PlayerAction>(767), Some(0x24));
+}
+
+/// Only added in 1.21, so 1.20.5 doesn't have it.
+struct ServerLinks;
+
+impl PacketInfo for ServerLinks {
+    const ID: i32 = 0x10;
+    const STATE: State = State::Configuration;
+    const BOUND: Bound = Bound::Client;
+}
+
+#[test]
+fn looks_up_packet_ids_by_version() {
+    assert!(PROTOCOL_VERSIONS.contains(&766));
+    assert_eq!(packet_id::<ServerLinks>(767), Some(0x10));
+    assert_eq!(packet_id::<ServerLinks>(766), None);
+    assert_eq!(packet_id::<LoginPlay>(766), Some(0x2b));
+}
+
+#[test]
+fn unlisted_versions_use_declared_ids() {
+    assert_eq!(packet_id::<StartConfiguration>(1), Some(0x69));
+}
+
+#[test]
+fn supported_versions_have_packet_ids() {
+    for version in SUPPORTED_VERSIONS {
+        assert!(
+            PROTOCOL_VERSIONS.contains(&version.protocol),
+            "{:?}",
+            version
+        );
+    }
+    assert_eq!(Version::from_protocol(767).map(|v| v.name), Some("1.21.1"));
+    assert_eq!(Version::from_protocol(766).map(|v| v.name), Some("1.20.6"));
+    assert_eq!(Version::oldest().protocol, 766);
+    assert_eq!(Version::from_protocol(47), None);
+}
@@ -0,0 +1,165 @@
+use embassy_futures::block_on;
+use picocraft_protocol::{
+    nbt::{Compound, Nbt},
+    read::ReadExtension,
+    text::{ClickAction, Color, Content, HoverEvent, TextComponent},
+    types::{Uuid, WriteType},
+    write::WriteExtension,
+};
+
+fn json(component: &TextComponent) -> String {
+    let mut out = Vec::new();
+    block_on(out.write_json(component)).unwrap();
+
+    let mut data = out.as_slice();
+    block_on(data.read_string()).unwrap()
+}
+
+#[test]
+fn plain_text_json() {
+    assert_eq!(json(&"hello".into()), r#"{"text":"hello"}"#);
+    assert_eq!(
+        json(&TextComponent::text("quote \" slash \\")),
+        r#"{"text":"quote \" slash \\"}"#
+    );
+}
+
+#[test]
+fn styled_json() {
+    let component = TextComponent::text("Pico")
+        .color(Color::Gold)
+        .bold(true)
+        .italic(false)
+        .click(ClickAction::OpenUrl, "https://example.com")
+        .hover(HoverEvent::ShowText(Box::new("hi".into())))
+        .append(TextComponent::text("Craft").color(Color::Rgb(0x12ab34)));
+
+    assert_eq!(
+        json(&component),
+        concat!(
+            r#"{"text":"Pico","color":"gold","bold":true,"italic":false,"#,
+            r#""clickEvent":{"action":"open_url","value":"https://example.com"},"#,
+            r#""hoverEvent":{"action":"show_text","contents":{"text":"hi"}},"#,
+            r##""extra":[{"text":"Craft","color":"#12ab34"}]}"##
+        )
+    );
+}
+
+#[test]
+fn other_content_json() {
+    let translate = TextComponent::translate(
+        "multiplayer.disconnect.outdated_client",
+        vec!["1.20.1".into()],
+    );
+    assert_eq!(
+        json(&translate),
+        r#"{"translate":"multiplayer.disconnect.outdated_client","with":[{"text":"1.20.1"}]}"#
+    );
+
+    assert_eq!(
+        json(&TextComponent::keybind("key.jump")),
+        r#"{"keybind":"key.jump"}"#
+    );
+    assert_eq!(
+        json(&TextComponent::score("Notch", "kills")),
+        r#"{"score":{"name":"Notch","objective":"kills"}}"#
+    );
+
+    let entity = TextComponent::text("?").hover(HoverEvent::ShowEntity {
+        kind: "minecraft:pig".into(),
+        id: Uuid(1),
+        name: None,
+    });
+    assert_eq!(
+        json(&entity),
+        concat!(
+            r#"{"text":"?","hoverEvent":{"action":"show_entity","contents":"#,
+            r#"{"type":"minecraft:pig","id":"00000000-0000-0000-0000-000000000001"}}}"#
+        )
+    );
+}
+
+#[test]
+fn plain_text_nbt_is_a_string() {
+    assert_eq!(TextComponent::text("hi").to_nbt(), Nbt::from("hi"));
+
+    let mut out = Vec::new();
+    block_on(TextComponent::text("hi").write_type(&mut out)).unwrap();
+    assert_eq!(out, [0x08, 0x00, 0x02, b'h', b'i']);
+}
+
+#[test]
+fn styled_nbt() {
+    let component = TextComponent::text("a")
+        .color(Color::Red)
+        .underlined(true)
+        .append("b");
+
+    let mut child = Compound::new();
+    child.insert("text", "b");
+    let mut expected = Compound::new();
+    expected.insert("text", "a");
+    expected.insert("color", "red");
+    expected.insert("underlined", true);
+    expected.insert("extra", vec![Nbt::Compound(child)]);
+
+    assert_eq!(component.to_nbt(), Nbt::Compound(expected));
+}
+
+#[test]
+fn parses_legacy_codes() {
+    assert_eq!(
+        TextComponent::from_legacy("no codes"),
+        TextComponent::text("no codes")
+    );
+    assert_eq!(
+        TextComponent::from_legacy("§cred"),
+        TextComponent::text("red").color(Color::Red)
+    );
+
+    let parsed = TextComponent::from_legacy("A §a§lPico§r§xCraft §9§oserver§");
+    assert_eq!(parsed.content, Content::Text(String::new()));
+    assert_eq!(
+        parsed.extra,
+        [
+            TextComponent::text("A "),
+            TextComponent::text("Pico").color(Color::Green).bold(true),
+            TextComponent::text("Craft "),
+            TextComponent::text("server§")
+                .color(Color::Blue)
+                .italic(true),
+        ]
+    );
+    assert_eq!(parsed.to_string(), "A PicoCraft server§");
+}
+
+#[test]
+fn color_code_resets_formatting() {
+    let parsed = TextComponent::from_legacy("§lbold§eyellow");
+
+    assert_eq!(
+        parsed.extra,
+        [
+            TextComponent::text("bold").bold(true),
+            TextComponent::text("yellow").color(Color::Yellow),
+        ]
+    );
+}
+
+#[test]
+fn writes_legacy_codes() {
+    for text in ["A §aPicoCraft§r server.", "§lbold §6§lgold bold", "plain"] {
+        assert_eq!(TextComponent::from_legacy(text).to_legacy(), text);
+    }
+}
+
+#[test]
+fn legacy_codes_follow_inheritance() {
+    let component = TextComponent::text("a")
+        .bold(true)
+        .append(TextComponent::text("b").bold(false))
+        .append(TextComponent::text("c").color(Color::Rgb(0x123456)))
+        .append(TextComponent::text("d").color(Color::Red).italic(true));
+
+    assert_eq!(component.to_legacy(), "§la§rb§lc§c§l§od");
+}
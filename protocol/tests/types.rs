@@ -0,0 +1,322 @@
+use embassy_futures::block_on;
+use picocraft_protocol::{
+    error::ProtocolError,
+    read::ReadExtension,
+    types::{Angle, BitSet, FixedBitSet, Identifier, Position, StringList, Uuid, VarInt},
+    write::WriteExtension,
+};
+
+// Examples are taken from wiki.vg
+const VARINTS: &[(i32, &[u8])] = &[
+    (0, &[0x00]),
+    (1, &[0x01]),
+    (2, &[0x02]),
+    (127, &[0x7f]),
+    (128, &[0x80, 0x01]),
+    (255, &[0xff, 0x01]),
+    (25565, &[0xdd, 0xc7, 0x01]),
+    (2097151, &[0xff, 0xff, 0x7f]),
+    (2147483647, &[0xff, 0xff, 0xff, 0xff, 0x07]),
+    (-1, &[0xff, 0xff, 0xff, 0xff, 0x0f]),
+    (-2147483648, &[0x80, 0x80, 0x80, 0x80, 0x08]),
+];
+
+const VARLONGS: &[(i64, &[u8])] = &[
+    (0, &[0x00]),
+    (1, &[0x01]),
+    (2, &[0x02]),
+    (127, &[0x7f]),
+    (128, &[0x80, 0x01]),
+    (255, &[0xff, 0x01]),
+    (2147483647, &[0xff, 0xff, 0xff, 0xff, 0x07]),
+    (
+        9223372036854775807,
+        &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+    ),
+    (
+        -1,
+        &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+    ),
+    (
+        -2147483648,
+        &[0x80, 0x80, 0x80, 0x80, 0xf8, 0xff, 0xff, 0xff, 0xff, 0x01],
+    ),
+    (
+        -9223372036854775808,
+        &[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01],
+    ),
+];
+
+#[test]
+fn varint_round_trip() {
+    for (value, bytes) in VARINTS {
+        let mut out = Vec::new();
+        block_on(out.write_varint(*value)).unwrap();
+        assert_eq!(&out, bytes, "writing {}", value);
+
+        let mut data = *bytes;
+        assert_eq!(block_on(data.read_varint()).unwrap(), *value);
+        assert!(data.is_empty());
+    }
+}
+
+#[test]
+fn varlong_round_trip() {
+    for (value, bytes) in VARLONGS {
+        let mut out = Vec::new();
+        block_on(out.write_varlong(*value)).unwrap();
+        assert_eq!(&out, bytes, "writing {}", value);
+
+        let mut data = *bytes;
+        assert_eq!(block_on(data.read_varlong()).unwrap(), *value);
+        assert!(data.is_empty());
+    }
+}
+
+#[test]
+fn rejects_overlong_varlong() {
+    let mut data: &[u8] = &[0xff; 11];
+
+    assert_eq!(
+        block_on(data.read_varlong()).unwrap_err(),
+        ProtocolError::VarIntTooLong
+    );
+}
+
+#[test]
+fn position_round_trip() {
+    let position = Position::new(18357644, 831, -20882616);
+    let bytes = 0x4607_632c_15b4_833fu64.to_be_bytes();
+
+    let mut out = Vec::new();
+    block_on(out.write_position(position)).unwrap();
+    assert_eq!(out, bytes);
+
+    let mut data: &[u8] = &bytes;
+    assert_eq!(block_on(data.read_position()).unwrap(), position);
+}
+
+#[test]
+fn negative_position_round_trip() {
+    let position = Position::new(-1, -64, -33554432);
+
+    assert_eq!(Position::from_packed(position.to_packed()), position);
+}
+
+#[test]
+fn uuid_round_trip() {
+    let uuid = Uuid(0x069a79f4_44e9_4726_a5be_fca90e38aaf5);
+    let bytes = [
+        0x06, 0x9a, 0x79, 0xf4, 0x44, 0xe9, 0x47, 0x26, 0xa5, 0xbe, 0xfc, 0xa9, 0x0e, 0x38, 0xaa,
+        0xf5,
+    ];
+
+    let mut out = Vec::new();
+    block_on(out.write_uuid(uuid)).unwrap();
+    assert_eq!(out, bytes);
+
+    let mut data: &[u8] = &bytes;
+    assert_eq!(block_on(data.read_uuid()).unwrap(), uuid);
+    assert_eq!(uuid.to_string(), "069a79f4-44e9-4726-a5be-fca90e38aaf5");
+}
+
+#[test]
+fn angle_conversion() {
+    assert_eq!(Angle::from_degrees(90.0), Angle(64));
+    assert_eq!(Angle::from_degrees(-90.0), Angle(192));
+    assert_eq!(Angle(128).to_degrees(), 180.0);
+
+    let mut out = Vec::new();
+    block_on(out.write_angle(Angle(64))).unwrap();
+    assert_eq!(out, [64]);
+}
+
+#[test]
+fn identifier_validation() {
+    let stone = Identifier::new("stone").unwrap();
+    assert_eq!(stone.as_str(), "minecraft:stone");
+    assert_eq!(stone.namespace(), "minecraft");
+    assert_eq!(stone.path(), "stone");
+
+    let custom = Identifier::new("picocraft:worldgen/void").unwrap();
+    assert_eq!(custom.namespace(), "picocraft");
+    assert_eq!(custom.path(), "worldgen/void");
+
+    for invalid in [
+        "Stone",
+        "minecraft:a b",
+        "mine/craft:stone",
+        ":stone",
+        "a:b:c",
+    ] {
+        assert_eq!(
+            Identifier::new(invalid).unwrap_err(),
+            ProtocolError::InvalidIdentifier,
+            "{}",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn identifier_round_trip() {
+    let bytes = b"\x0fminecraft:stone";
+
+    let mut data: &[u8] = bytes;
+    let identifier = block_on(data.read_identifier()).unwrap();
+    assert_eq!(identifier.path(), "stone");
+
+    let mut out = Vec::new();
+    block_on(out.write_identifier(&identifier)).unwrap();
+    assert_eq!(out, bytes);
+
+    let mut data: &[u8] = b"\x05Stone";
+    assert_eq!(
+        block_on(data.read_identifier()).unwrap_err(),
+        ProtocolError::InvalidIdentifier
+    );
+}
+
+#[test]
+fn byte_array_round_trip() {
+    let mut out = Vec::new();
+    block_on(out.write_prefixed_byte_array(&[1, 2, 3])).unwrap();
+    block_on(out.write_byte_array(&[4, 5])).unwrap();
+    assert_eq!(out, [3, 1, 2, 3, 4, 5]);
+
+    let mut data: &[u8] = &out;
+    assert_eq!(
+        block_on(data.read_prefixed_byte_array()).unwrap(),
+        [1, 2, 3]
+    );
+    assert_eq!(block_on(data.read_byte_array(2)).unwrap(), [4, 5]);
+}
+
+#[test]
+fn bitset_round_trip() {
+    let mut bitset = BitSet::new();
+    bitset.set(0, true);
+    bitset.set(65, true);
+    assert!(bitset.get(65));
+    assert!(!bitset.get(64));
+    assert!(!bitset.get(1000));
+
+    let mut out = Vec::new();
+    block_on(out.write_bitset(&bitset)).unwrap();
+    assert_eq!(
+        out,
+        [0x02, 0, 0, 0, 0, 0, 0, 0, 0x01, 0, 0, 0, 0, 0, 0, 0, 0x02]
+    );
+
+    let mut data: &[u8] = &out;
+    assert_eq!(block_on(data.read_bitset()).unwrap(), bitset);
+}
+
+#[test]
+fn rejects_arrays_longer_than_the_data() {
+    // Claims 2^31 - 1 entries, which mustn't be allocated up front
+    let huge = [0xff, 0xff, 0xff, 0xff, 0x07, 1, 2, 3];
+    let mut data: &[u8] = &huge;
+    assert!(matches!(
+        block_on(data.read_bitset()),
+        Err(ProtocolError::UnexpectedEof)
+    ));
+    let mut data: &[u8] = &huge;
+    assert!(matches!(
+        block_on(data.read_prefixed_byte_array()),
+        Err(ProtocolError::UnexpectedEof)
+    ));
+    let mut data: &[u8] = &huge[5..];
+    assert!(matches!(
+        block_on(data.read_byte_array(usize::MAX)),
+        Err(ProtocolError::UnexpectedEof)
+    ));
+}
+
+#[test]
+fn fixed_bitset_round_trip() {
+    let mut bitset = FixedBitSet::<2>::new();
+    bitset.set(1, true);
+    bitset.set(8, true);
+    assert!(bitset.get(8));
+
+    let mut out = Vec::new();
+    block_on(out.write_fixed_bitset(&bitset)).unwrap();
+    assert_eq!(out, [0x02, 0x01]);
+
+    let mut data: &[u8] = &out;
+    assert_eq!(block_on(data.read_fixed_bitset::<2>()).unwrap(), bitset);
+}
+
+#[test]
+fn prefixed_and_optional_round_trip() {
+    let mut out = Vec::new();
+    block_on(out.write_prefixed(&[VarInt(1), VarInt(300)])).unwrap();
+    block_on(out.write_optional(&Some(String::from("hi")))).unwrap();
+    block_on(out.write_optional::<i64>(&None)).unwrap();
+    assert_eq!(out, [0x02, 0x01, 0xac, 0x02, 0x01, 0x02, b'h', b'i', 0x00]);
+
+    let mut data: &[u8] = &out;
+    assert_eq!(
+        block_on(data.read_prefixed::<VarInt>()).unwrap(),
+        [VarInt(1), VarInt(300)]
+    );
+    assert_eq!(
+        block_on(data.read_optional::<String>()).unwrap().as_deref(),
+        Some("hi")
+    );
+    assert_eq!(block_on(data.read_optional::<i64>()).unwrap(), None);
+}
+
+#[test]
+fn rejects_negative_array_length() {
+    let mut data: &[u8] = &[0xff, 0xff, 0xff, 0xff, 0x0f];
+
+    assert_eq!(
+        block_on(data.read_prefixed::<u8>()).unwrap_err(),
+        ProtocolError::InvalidLength(-1)
+    );
+}
+
+#[test]
+fn wide_integers_round_trip() {
+    let mut out = Vec::new();
+    block_on(out.write_u64(u64::MAX - 1)).unwrap();
+    block_on(out.write_i128(-2)).unwrap();
+
+    let mut data: &[u8] = &out;
+    assert_eq!(block_on(data.read_u64()).unwrap(), u64::MAX - 1);
+    assert_eq!(block_on(data.read_i128()).unwrap(), -2);
+}
+
+#[test]
+fn offline_player_uuid() {
+    let uuid = Uuid::offline_player("Notch");
+
+    assert_eq!(uuid.to_string(), "b50ad385-829d-3141-a216-7e7d7539ba7f");
+    // Version 3, RFC 4122 variant
+    assert_eq!((uuid.0 >> 76) & 0xf, 3);
+    assert_eq!((uuid.0 >> 62) & 0b11, 0b10);
+}
+
+#[test]
+fn string_list_lookups() {
+    let list = StringList::new(b"\x05stone\x04dirt\x00\x05grass");
+
+    assert_eq!(list.len(), 4);
+    assert_eq!(
+        list.iter().collect::<Vec<_>>(),
+        ["stone", "dirt", "", "grass"]
+    );
+    assert_eq!(list.get(1), Some("dirt"));
+    assert_eq!(list.get(4), None);
+    assert_eq!(list.position("grass"), Some(3));
+    assert_eq!(list.position("sand"), None);
+}
+
+#[test]
+fn truncated_string_list_stops_early() {
+    let list = StringList::new(b"\x05stone\x09dirt");
+
+    assert_eq!(list.iter().collect::<Vec<_>>(), ["stone"]);
+}
@@ -0,0 +1,195 @@
+//! Generates the registry and block tables from the files in `data`.
+//!
+//! Registry entries are packed into blobs that are included with `include_bytes!`, so they sit in
+//! flash as a `StringList` without a pointer per entry.
+//!
+//! Only the entries' names are kept, not their NBT, which would be far too big for the Pico.
+//! Registry Data is sent without it, so the client fills each entry in from its own
+//! `minecraft:core` pack. That only works if the client has the same pack, so clients that don't
+//! list it in Known Packs are disconnected before any registries are sent.
+
+use std::{env, fmt::Write, fs, path::Path};
+
+fn main() {
+    println!("cargo:rerun-if-changed=data/registries.txt");
+    println!("cargo:rerun-if-changed=data/blocks.txt");
+
+    let out = env::var_os("OUT_DIR").unwrap();
+    let out = Path::new(&out);
+
+    let registries = fs::read_to_string("data/registries.txt").unwrap();
+    fs::write(out.join("registries.rs"), registries_rs(&registries, out)).unwrap();
+
+    let blocks = fs::read_to_string("data/blocks.txt").unwrap();
+    fs::write(out.join("blocks.rs"), blocks_rs(&blocks)).unwrap();
+}
+
+/// The non-empty lines that aren't comments, with their line numbers.
+fn lines(data: &str) -> impl Iterator<Item = (usize, &str)> {
+    data.lines()
+        .enumerate()
+        .map(|(number, line)| (number + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// `minecraft:worldgen/biome` becomes `BIOME`.
+fn const_name(id: &str) -> String {
+    let path = id.rsplit([':', '/']).next().unwrap();
+    path.to_uppercase()
+}
+
+/// A line's name, and the protocol version it was added in, or 0 if it's in every version.
+fn since(line: &str, number: usize) -> (&str, i32) {
+    match line.split_once(char::is_whitespace) {
+        Some((name, version)) => {
+            let version = version
+                .trim()
+                .parse()
+                .unwrap_or_else(|_| panic!("registries.txt:{}: invalid protocol version", number));
+            (name, version)
+        }
+        None => (line, 0),
+    }
+}
+
+/// Entries packed the way `StringList` reads them.
+fn pack<'a>(entries: impl Iterator<Item = &'a str>) -> Vec<u8> {
+    let mut packed = Vec::new();
+    for entry in entries {
+        packed.push(entry.len() as u8);
+        packed.extend_from_slice(entry.as_bytes());
+    }
+    packed
+}
+
+/// A registry and its entries, each with the protocol version it was added in.
+type Registry<'a> = (&'a str, i32, Vec<(&'a str, i32)>);
+
+fn registries_rs(data: &str, out: &Path) -> String {
+    let mut registries: Vec<Registry> = Vec::new();
+    for (number, line) in lines(data) {
+        if let Some(registry) = line.strip_prefix('[') {
+            let (registry, since) = since(registry, number);
+            let registry = registry.strip_suffix(']').expect("unclosed registry name");
+            registries.push((registry, since, Vec::new()));
+            continue;
+        }
+
+        let (_, _, entries) = registries
+            .last_mut()
+            .unwrap_or_else(|| panic!("registries.txt:{}: entry outside a registry", number));
+        let entry = since(line, number);
+        assert!(
+            entry.0.len() < 128,
+            "registries.txt:{}: entry is too long",
+            number
+        );
+        entries.push(entry);
+    }
+
+    let mut code = String::new();
+    for (registry, _, entries) in &registries {
+        let file = out.join(format!("{}.bin", registry.replace([':', '/'], "_")));
+        fs::write(&file, pack(entries.iter().map(|(entry, _)| *entry))).unwrap();
+        writeln!(code, "/// The entries of `{}`.", registry).unwrap();
+        writeln!(
+            code,
+            "pub const {}: StringList<'static> = StringList::new(include_bytes!({:?}));",
+            const_name(registry),
+            file.display()
+        )
+        .unwrap();
+    }
+
+    writeln!(code).unwrap();
+    writeln!(
+        code,
+        "/// Every registry the client needs before it can join, with its entries."
+    )
+    .unwrap();
+    writeln!(
+        code,
+        "pub const REGISTRIES: &[(&str, StringList<'static>)] = &["
+    )
+    .unwrap();
+    for (registry, _, _) in &registries {
+        writeln!(code, "    ({:?}, {}),", registry, const_name(registry)).unwrap();
+    }
+    writeln!(code, "];").unwrap();
+
+    // Older versions get the registries without whatever was added after them
+    let mut versions: Vec<i32> = registries
+        .iter()
+        .flat_map(|(_, since, entries)| {
+            core::iter::once(*since).chain(entries.iter().map(|(_, since)| *since))
+        })
+        .collect();
+    versions.sort_unstable();
+    versions.dedup();
+    versions.reverse();
+
+    writeln!(code).unwrap();
+    writeln!(
+        code,
+        "/// The registries for each protocol version that added to them, newest first."
+    )
+    .unwrap();
+    writeln!(
+        code,
+        "const VERSIONED_REGISTRIES: &[(i32, &[(&str, StringList<'static>)])] = &["
+    )
+    .unwrap();
+    writeln!(code, "    ({}, REGISTRIES),", versions[0]).unwrap();
+    for &version in &versions[1..] {
+        writeln!(code, "    ({}, &[", version).unwrap();
+        for (registry, since, entries) in &registries {
+            if *since > version {
+                continue;
+            }
+            if entries.iter().all(|(_, since)| *since <= version) {
+                writeln!(code, "        ({:?}, {}),", registry, const_name(registry)).unwrap();
+                continue;
+            }
+            let file = out.join(format!(
+                "{}_{}.bin",
+                registry.replace([':', '/'], "_"),
+                version
+            ));
+            let older = entries.iter().filter(|(_, since)| *since <= version);
+            fs::write(&file, pack(older.map(|(entry, _)| *entry))).unwrap();
+            writeln!(
+                code,
+                "        ({:?}, StringList::new(include_bytes!({:?}))),",
+                registry,
+                file.display()
+            )
+            .unwrap();
+        }
+        writeln!(code, "    ]),").unwrap();
+    }
+    writeln!(code, "];").unwrap();
+    code
+}
+
+fn blocks_rs(data: &str) -> String {
+    let mut code = String::new();
+    for (number, line) in lines(data) {
+        let Some((name, state)) = line.split_once(char::is_whitespace) else {
+            panic!("blocks.txt:{}: expected a name and a state id", number);
+        };
+        let state: u16 = state
+            .trim()
+            .parse()
+            .unwrap_or_else(|_| panic!("blocks.txt:{}: invalid state id", number));
+
+        writeln!(code, "/// The default state of `{}`.", name).unwrap();
+        writeln!(
+            code,
+            "pub const {}: BlockState = {};",
+            const_name(name),
+            state
+        )
+        .unwrap();
+    }
+    code
+}
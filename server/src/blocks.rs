@@ -0,0 +1,6 @@
+//! Block state ids, generated from `data/blocks.txt`.
+
+/// An id in the global block state palette.
+pub type BlockState = u16;
+
+include!(concat!(env!("OUT_DIR"), "/blocks.rs"));
@@ -0,0 +1,85 @@
+//! The Configuration state, between Login and Play.
+
+use alloc::{format, vec, vec::Vec};
+use embedded_io_async::Write;
+use picocraft_protocol::{
+    error::ProtocolError,
+    frame::FrameEncoder,
+    packets::{
+        configuration::{
+            ClientboundKnownPacks, ClientboundPluginMessage, ConfigurationDisconnect, FeatureFlags,
+            FinishConfiguration, KnownPack, RegistryData, UpdateTags,
+        },
+        Version,
+    },
+    text::TextComponent,
+    types::Identifier,
+};
+
+use crate::registries;
+
+/// What we call ourselves in the F3 screen.
+pub const BRAND: &str = "PicoCraft";
+
+/// Starts configuring the client, either straight after login or when it comes back from Play.
+///
+/// The client answers with the packs it knows, which should then be passed to [`finish`].
+pub async fn start<W: Write>(
+    socket: &mut W,
+    encoder: &FrameEncoder,
+    version: Version,
+) -> Result<(), ProtocolError> {
+    // The brand is a string, and short enough for its length to fit in one byte
+    let mut data = vec![BRAND.len() as u8];
+    data.extend_from_slice(BRAND.as_bytes());
+    let brand = ClientboundPluginMessage {
+        channel: Identifier::new("brand")?,
+        data,
+    };
+    encoder.write_packet(socket, &brand).await?;
+
+    let flags = FeatureFlags {
+        flags: vec![Identifier::new("vanilla")?],
+    };
+    encoder.write_packet(socket, &flags).await?;
+
+    let packs = ClientboundKnownPacks {
+        packs: vec![KnownPack::core(version.name)],
+    };
+    encoder.write_packet(socket, &packs).await
+}
+
+/// Sends the registries and tells the client we're done.
+///
+/// We don't have the registries' data, so if the client doesn't have the same vanilla pack it is
+/// disconnected instead and this returns `false`. That includes other releases that speak the
+/// same protocol, like 1.20.5, as the client only answers with packs of the exact version we ask
+/// for.
+pub async fn finish<W: Write>(
+    socket: &mut W,
+    encoder: &FrameEncoder,
+    version: Version,
+    known: &[KnownPack],
+) -> Result<bool, ProtocolError> {
+    if !known.contains(&KnownPack::core(version.name)) {
+        let reason = TextComponent::text(format!(
+            "This server needs a vanilla {} client, not another version with the same protocol",
+            version.name
+        ));
+        encoder
+            .write_packet(socket, &ConfigurationDisconnect { reason })
+            .await?;
+        return Ok(false);
+    }
+
+    for &(registry, entries) in registries::for_version(version.protocol) {
+        let data = RegistryData { registry, entries };
+        encoder.write_packet(socket, &data).await?;
+    }
+    let tags = UpdateTags {
+        registries: Vec::new(),
+    };
+    encoder.write_packet(socket, &tags).await?;
+    encoder.write_packet(socket, &FinishConfiguration).await?;
+    Ok(true)
+}
@@ -0,0 +1,120 @@
+//! The server's side of the Encryption Request and Response.
+//!
+//! The client makes up a shared secret, encrypts it with our RSA public key and sends it back
+//! along with the verify token we gave it. From then on both sides encrypt the connection with
+//! the secret, see [`CipherStream`](picocraft_protocol::encryption::CipherStream).
+
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write;
+
+use picocraft_protocol::{
+    encryption::SECRET_LENGTH, error::ProtocolError, packets::login::EncryptionResponse,
+};
+use rand_core::CryptoRngCore;
+use rsa::{pkcs8::EncodePublicKey, Pkcs1v15Encrypt, RsaPrivateKey};
+use sha1::{Digest, Sha1};
+
+/// How big vanilla's keys are, and the only size clients expect.
+pub const KEY_BITS: usize = 1024;
+
+/// The length of the verify token, the same as vanilla's.
+pub const VERIFY_TOKEN_LENGTH: usize = 4;
+
+/// The key pair the server encrypts logins with.
+///
+/// Generating one takes a while on the Pico, so it's done once on boot and shared by every
+/// connection.
+#[derive(Debug, Clone)]
+pub struct ServerKey {
+    private: RsaPrivateKey,
+    /// Sent to every client, so it's only encoded once.
+    public_der: Vec<u8>,
+}
+
+impl ServerKey {
+    pub fn generate<R: CryptoRngCore>(rng: &mut R) -> Result<ServerKey, rsa::Error> {
+        let private = RsaPrivateKey::new(rng, KEY_BITS)?;
+        let public_der = private
+            .to_public_key()
+            .to_public_key_der()
+            .map_err(|_| rsa::Error::Internal)?
+            .into_vec();
+        Ok(ServerKey {
+            private,
+            public_der,
+        })
+    }
+
+    /// The public key as DER encoded SubjectPublicKeyInfo, which is what clients expect.
+    pub fn public_der(&self) -> &[u8] {
+        &self.public_der
+    }
+
+    /// Decrypts the shared secret from `response`, once its verify token matches the one the
+    /// client was sent.
+    pub fn shared_secret<R: CryptoRngCore>(
+        &self,
+        rng: &mut R,
+        response: &EncryptionResponse,
+        verify_token: &[u8; VERIFY_TOKEN_LENGTH],
+    ) -> Result<[u8; SECRET_LENGTH], ProtocolError> {
+        let token = self.decrypt(rng, &response.verify_token)?;
+        if token != verify_token {
+            return Err(ProtocolError::InvalidEncryption);
+        }
+
+        let secret = self.decrypt(rng, &response.shared_secret)?;
+        secret
+            .try_into()
+            .map_err(|_| ProtocolError::InvalidEncryption)
+    }
+
+    fn decrypt<R: CryptoRngCore>(
+        &self,
+        rng: &mut R,
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, ProtocolError> {
+        // Blinding stops the time this takes from giving the key away
+        self.private
+            .decrypt_blinded(rng, Pkcs1v15Encrypt, ciphertext)
+            .map_err(|_| ProtocolError::InvalidEncryption)
+    }
+}
+
+/// The hash the client and server both send to the session server, to show they're talking to
+/// each other.
+///
+/// It's a SHA-1 digest printed like Java's `BigInteger::toString(16)`, so it's signed, and has no
+/// leading zeros.
+pub fn server_hash(server_id: &str, shared_secret: &[u8], public_key: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(server_id.as_bytes());
+    hasher.update(shared_secret);
+    hasher.update(public_key);
+    let mut digest: [u8; 20] = hasher.finalize().into();
+
+    let negative = digest[0] & 0x80 != 0;
+    if negative {
+        // Two's complement, to get the magnitude
+        let mut carry = true;
+        for byte in digest.iter_mut().rev() {
+            (*byte, carry) = (!*byte).overflowing_add(carry as u8);
+        }
+    }
+
+    let mut hex = String::with_capacity(40);
+    for byte in digest {
+        write!(hex, "{:02x}", byte).unwrap();
+    }
+    let digits = match hex.trim_start_matches('0') {
+        "" => "0",
+        digits => digits,
+    };
+
+    let mut hash = String::with_capacity(41);
+    if negative {
+        hash.push('-');
+    }
+    hash.push_str(digits);
+    hash
+}
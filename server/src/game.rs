@@ -0,0 +1,277 @@
+//! The game loop, which moves the world on 20 times a second and tells every player about it.
+//!
+//! Connections don't change the world themselves, they queue changes for the next tick, which
+//! keeps them and sends them to every player that's in the world.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    cell::{Cell, RefCell},
+    ops::{Deref, DerefMut},
+};
+use embassy_sync::{
+    blocking_mutex::raw::NoopRawMutex,
+    pubsub::{PubSubChannel, Subscriber},
+};
+use embassy_time::{Duration, Instant, Timer};
+use log::{debug, info, warn};
+
+use crate::{
+    play::KEEP_ALIVE_INTERVAL,
+    store::{BlockEdit, BlockStore},
+    MAX_PLAYERS,
+};
+
+/// How many ticks there are in a second, the same as vanilla.
+pub const TICKS_PER_SECOND: u64 = 20;
+
+/// How long each tick has.
+pub const TICK: Duration = Duration::from_millis(1000 / TICKS_PER_SECOND);
+
+/// How many ticks there are from one sunrise to the next.
+pub const DAY_LENGTH: u64 = 24000;
+
+/// How far behind the loop can fall before it stops trying to catch up, like vanilla.
+const MAX_LAG: Duration = Duration::from_secs(2);
+
+/// How many updates a connection can fall behind by before it misses some.
+const UPDATE_CAPACITY: usize = 16;
+
+/// How often the TPS is logged, in ticks.
+const LOG_INTERVAL: u64 = 60 * TICKS_PER_SECOND;
+
+/// Something the game loop tells every player in the world.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Update {
+    /// Every player has to answer with this id before the next one, or be kicked.
+    KeepAlive(i64),
+    /// The world is this many ticks old, sent every second to keep clients' clocks right.
+    Time(u64),
+    /// A block has changed.
+    Block(BlockEdit),
+    /// Every player goes back to configuration, to be sent the registries and packs again.
+    Reconfigure,
+}
+
+/// A connection's view of the updates, which it has until it leaves the world.
+///
+/// The player is counted as playing for as long as this is kept.
+pub struct Updates<'a> {
+    subscriber: Subscriber<'a, NoopRawMutex, Update, UPDATE_CAPACITY, { MAX_PLAYERS as usize }, 0>,
+    players: &'a Cell<u32>,
+}
+
+impl<'a> Deref for Updates<'a> {
+    type Target =
+        Subscriber<'a, NoopRawMutex, Update, UPDATE_CAPACITY, { MAX_PLAYERS as usize }, 0>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.subscriber
+    }
+}
+
+impl DerefMut for Updates<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.subscriber
+    }
+}
+
+impl Drop for Updates<'_> {
+    fn drop(&mut self) {
+        self.players.set(self.players.get() - 1);
+    }
+}
+
+/// Something in the world that moves on every tick.
+pub trait Entity {
+    /// Moves the entity on by a tick, passing anything players should know to `broadcast`.
+    ///
+    /// Returns false once the entity is gone from the world.
+    fn tick(&mut self, broadcast: &mut dyn FnMut(Update)) -> bool;
+}
+
+/// Runs once, on the tick it was scheduled for.
+type Task<B> = Box<dyn FnOnce(&Game<B>)>;
+
+/// How well the game loop is keeping up.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TickStats {
+    /// How long the last tick took to run.
+    pub tick_time: Duration,
+    /// The longest a tick took over the last second.
+    pub max_tick_time: Duration,
+    /// How many ticks ran over the last second, which is 20 unless the loop is falling behind.
+    pub tps: f32,
+}
+
+/// Everything about the world that changes as it's played, shared by the game loop and every
+/// connection.
+pub struct Game<B> {
+    /// Keeps the blocks players change.
+    pub store: RefCell<B>,
+    /// How many ticks old the world is.
+    ticks: Cell<u64>,
+    stats: Cell<TickStats>,
+    /// Block changes waiting for the next tick.
+    changes: RefCell<Vec<BlockEdit>>,
+    /// Tasks and the tick they're due on.
+    tasks: RefCell<Vec<(u64, Task<B>)>>,
+    entities: RefCell<Vec<Box<dyn Entity>>>,
+    /// How many players are in the world.
+    players: Cell<u32>,
+    updates: PubSubChannel<NoopRawMutex, Update, UPDATE_CAPACITY, { MAX_PLAYERS as usize }, 0>,
+}
+
+impl<B: BlockStore> Game<B> {
+    /// Starts a new game at sunrise, keeping block changes in `store`.
+    pub fn new(store: B) -> Game<B> {
+        Game {
+            store: RefCell::new(store),
+            ticks: Cell::new(0),
+            stats: Cell::new(TickStats::default()),
+            changes: RefCell::new(Vec::new()),
+            tasks: RefCell::new(Vec::new()),
+            entities: RefCell::new(Vec::new()),
+            players: Cell::new(0),
+            updates: PubSubChannel::new(),
+        }
+    }
+
+    /// How many ticks old the world is.
+    pub fn ticks(&self) -> u64 {
+        self.ticks.get()
+    }
+
+    /// How well the game loop is keeping up, for diagnostics.
+    pub fn stats(&self) -> TickStats {
+        self.stats.get()
+    }
+
+    /// Starts sending updates to a player joining the world, or returns `None` if it's full.
+    pub fn join(&self) -> Option<Updates<'_>> {
+        let subscriber = self.updates.subscriber().ok()?;
+        self.players.set(self.players.get() + 1);
+        Some(Updates {
+            subscriber,
+            players: &self.players,
+        })
+    }
+
+    /// How many players are in the world, from joining until their [`Updates`] are dropped.
+    pub fn players(&self) -> u32 {
+        self.players.get()
+    }
+
+    /// Sends every player in the world back to configuration, which they rejoin from.
+    pub fn reconfigure(&self) {
+        info!("Reconfiguring every player");
+        self.updates
+            .immediate_publisher()
+            .publish_immediate(Update::Reconfigure);
+    }
+
+    /// Changes a block on the next tick.
+    pub fn change_block(&self, edit: BlockEdit) {
+        self.changes.borrow_mut().push(edit);
+    }
+
+    /// Runs `task` in `delay` ticks' time, or on the next tick if that's 0.
+    pub fn schedule(&self, delay: u64, task: impl FnOnce(&Game<B>) + 'static) {
+        let due = self.ticks() + delay.max(1);
+        self.tasks.borrow_mut().push((due, Box::new(task)));
+    }
+
+    /// Adds `entity` to the world, to be ticked from the next tick.
+    pub fn spawn(&self, entity: impl Entity + 'static) {
+        self.entities.borrow_mut().push(Box::new(entity));
+    }
+
+    /// Runs the game loop forever, ticking 20 times a second.
+    pub async fn run(&self) -> ! {
+        let mut next = Instant::now();
+        let mut second = (next, self.ticks());
+        let mut max_tick_time = Duration::from_ticks(0);
+        loop {
+            Timer::at(next).await;
+            let start = Instant::now();
+            self.tick();
+            let end = Instant::now();
+
+            let tick_time = end - start;
+            max_tick_time = max_tick_time.max(tick_time);
+            let mut stats = TickStats {
+                tick_time,
+                ..self.stats()
+            };
+            if self.ticks().is_multiple_of(TICKS_PER_SECOND) {
+                let elapsed = (end - second.0).as_micros().max(1);
+                stats.tps = ((self.ticks() - second.1) * 1_000_000) as f32 / elapsed as f32;
+                stats.max_tick_time = max_tick_time;
+                second = (end, self.ticks());
+                max_tick_time = Duration::from_ticks(0);
+            }
+            if self.ticks().is_multiple_of(LOG_INTERVAL) {
+                info!(
+                    "{:.1} TPS, the slowest tick took {} us",
+                    stats.tps,
+                    stats.max_tick_time.as_micros()
+                );
+            }
+            self.stats.set(stats);
+
+            next += TICK;
+            if end > next + MAX_LAG {
+                let behind = (end - next).as_millis();
+                warn!("Can't keep up, skipping {} ms of ticks", behind);
+                next = end;
+            }
+        }
+    }
+
+    /// Moves the world on by one tick.
+    ///
+    /// [`Game::run`] calls this on time, it's only public so it can be stepped through.
+    pub fn tick(&self) {
+        let ticks = self.ticks() + 1;
+        self.ticks.set(ticks);
+
+        // Tasks can schedule more tasks, which wait for a later tick
+        let due = {
+            let mut tasks = self.tasks.borrow_mut();
+            let (due, waiting): (Vec<_>, Vec<_>) =
+                tasks.drain(..).partition(|(tick, _)| *tick <= ticks);
+            *tasks = waiting;
+            due
+        };
+        for (_, task) in due {
+            task(self);
+        }
+
+        let publisher = self.updates.immediate_publisher();
+        let mut broadcast = |update| publisher.publish_immediate(update);
+        self.entities
+            .borrow_mut()
+            .retain_mut(|entity| entity.tick(&mut broadcast));
+
+        let changes = core::mem::take(&mut *self.changes.borrow_mut());
+        for edit in changes {
+            if let Err(err) = self.store.borrow_mut().record(&edit) {
+                // The block is still changed for everyone until the chunk is sent again
+                warn!(
+                    "Couldn't keep the block changed at {:?}: {}",
+                    edit.position(),
+                    err
+                );
+            }
+            broadcast(Update::Block(edit));
+        }
+
+        if ticks.is_multiple_of(TICKS_PER_SECOND) {
+            broadcast(Update::Time(ticks));
+        }
+        let keep_alive_interval = KEEP_ALIVE_INTERVAL.as_millis() / TICK.as_millis();
+        if ticks.is_multiple_of(keep_alive_interval) {
+            debug!("Sending keep alive {}", ticks);
+            broadcast(Update::KeepAlive(ticks as i64));
+        }
+    }
+}
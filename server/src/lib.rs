@@ -0,0 +1,642 @@
+//! The parts of PicoCraft that don't depend on the Pico, so they can also run on a computer.
+
+#![no_std]
+#![allow(async_fn_in_trait)]
+
+extern crate alloc;
+
+pub mod blocks;
+pub mod configuration;
+pub mod encryption;
+pub mod game;
+pub mod play;
+pub mod registries;
+pub mod session;
+pub mod store;
+pub mod world;
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use embassy_futures::select::{select, Either};
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, channel::Channel, pubsub::WaitResult};
+use embedded_io_async::{Read, Write};
+use encryption::{server_hash, ServerKey, VERIFY_TOKEN_LENGTH};
+use game::{Game, Update, Updates};
+use log::{debug, info, warn};
+use picocraft_protocol::{
+    encryption::CipherStream,
+    error::ProtocolError,
+    frame::{FrameDecoder, FrameEncoder},
+    packets::{
+        configuration::{
+            AcknowledgeFinishConfiguration, ClientInformation, KnownPack, ServerboundPluginMessage,
+        },
+        legacy::{write_legacy_status, LegacyPing},
+        login::{
+            is_valid_username, EncryptionRequest, EncryptionResponse, LoginAcknowledged,
+            LoginDisconnect, LoginStart, LoginSuccess, Property, SetCompression,
+        },
+        play::{
+            ClientboundKeepAlive, ConfigurationAcknowledged, PlayDisconnect, PlayerAction,
+            StartConfiguration,
+        },
+        status::{PlayerData, PongResponse, StatusJson, VersionData},
+        PacketInfo, State, Version,
+    },
+    read::ReadExtension,
+    registry::ServerboundPacket,
+    text::TextComponent,
+    types::Uuid,
+};
+use rand_core::CryptoRngCore;
+use session::{has_joined, Connect, Profile, MOJANG_SESSION_SERVER};
+use store::BlockStore;
+use world::World;
+
+/// The most players the server list says can join.
+pub const MAX_PLAYERS: u32 = 4;
+
+/// Settings for the whole server, shared by every connection.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Packets at least this many bytes long are compressed, or `None` to never compress.
+    ///
+    /// Compressing takes time, but sends less over the Pico's slow Wi-Fi.
+    pub compression_threshold: Option<usize>,
+    /// The longest packet a client can send, before or after decompressing. Longer ones close
+    /// the connection.
+    ///
+    /// Each connection buffers a whole packet, so this has to fit in RAM for every player.
+    pub max_packet_length: usize,
+    /// Encrypts logins with this key, or `None` to leave connections unencrypted.
+    pub key: Option<ServerKey>,
+    /// Checks that players are who they say they are with `session_server`, which needs `key`.
+    ///
+    /// This isn't quite vanilla's online mode: there's no room for a certificate store, and
+    /// `embedded-tls` can't check certificates any other way on the Pico, so the session server's
+    /// certificate is never checked. Anyone who can get between the server and the session server
+    /// can pretend to be it, and let in whoever they like under any name.
+    pub check_sessions: bool,
+    /// The base URL of the session server, which can be pointed somewhere other than Mojang's
+    /// for testing.
+    pub session_server: String,
+    /// The world players join, which is generated as it's sent.
+    pub world: World,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            // Like vanilla
+            compression_threshold: Some(256),
+            // More than anything the server reads, and small enough for every player to have one
+            // on the Pico's heap, even while it's being decompressed
+            max_packet_length: 4 * 1024,
+            // Has to be generated, which needs a random number generator
+            key: None,
+            check_sessions: false,
+            session_server: MOJANG_SESSION_SERVER.to_string(),
+            world: World::default(),
+        }
+    }
+}
+
+/// Someone who has logged in.
+#[derive(Debug, Clone)]
+pub struct Player {
+    pub name: String,
+    pub uuid: Uuid,
+    /// Skin and cape, which only the session server has.
+    pub properties: Vec<Property>,
+    /// Sent by the client during configuration.
+    pub information: Option<ClientInformation>,
+}
+
+/// Talks to one client until it disconnects or sends something we can't handle.
+///
+/// This works over anything that implements [`Read`] and [`Write`], so the firmware passes its
+/// `TcpSocket` and the simulator passes a host socket. `rng` makes the verify tokens for
+/// encryption, so it has to be unpredictable, and `connector` reaches the session server when
+/// [`Config::check_sessions`] is set. `game` is the world players join, which every connection shares and
+/// [`Game::run`] moves on.
+pub async fn serve<S: Read + Write, B: BlockStore, R: CryptoRngCore, C: Connect>(
+    socket: &mut S,
+    config: &Config,
+    game: &Game<B>,
+    rng: &mut R,
+    connector: &mut C,
+) -> Result<(), ProtocolError> {
+    // Does nothing until the client has sent its shared secret
+    let socket = &mut CipherStream::new(socket);
+
+    let mut state = State::Handshake;
+    // Until the handshake tells us otherwise
+    let mut protocol_version = 0;
+    // The version we talk to the client with, only checked to be supported once it logs in
+    let mut version = Version::newest();
+    let mut player: Option<Player> = None;
+    // Who is logging in and the verify token they were sent, between the encryption packets
+    let mut encrypting: Option<(String, [u8; VERIFY_TOKEN_LENGTH])> = None;
+    // The teleport the client hasn't confirmed yet, and the id of the last one sent
+    let mut teleport: Option<i32> = None;
+    let mut teleport_id = 0;
+    // The keep alive the client hasn't answered yet
+    let mut keep_alive: Option<i64> = None;
+    // Whether Finish Configuration was sent and the client hasn't acknowledged it yet
+    let mut awaiting_finish = false;
+    // Whether Start Configuration was sent and the client hasn't acknowledged it yet
+    let mut awaiting_configuration = false;
+    // What the game loop tells us while the player is in the world
+    let mut updates: Option<Updates> = None;
+    let mut decoder = FrameDecoder::new();
+    decoder.set_max_length(config.max_packet_length);
+    let mut encoder = FrameEncoder::new(protocol_version);
+    let channel: Channel<NoopRawMutex, PacketEvent, 4> = Channel::new();
+
+    // Legacy pings can only be told apart from a handshake by the first bytes of the connection
+    let mut first = [0; 64];
+    let read = socket.read(&mut first).await.map_err(ProtocolError::io)?;
+    if let Some(ping) = LegacyPing::parse(&first[..read]) {
+        info!("Answering legacy ping {:?}", ping);
+        write_legacy_status(socket, ping, &status(Version::newest(), game)).await?;
+        socket.flush().await.map_err(ProtocolError::io)?;
+        return Ok(());
+    }
+    decoder.push(&first[..read]);
+
+    loop {
+        let read = read_packets(socket, &mut decoder, &channel, &state, protocol_version);
+        let result = match &mut updates {
+            // Reading only ever waits on the socket, so nothing is lost when an update wins
+            Some(updates) => match select(read, updates.next_message()).await {
+                Either::First(result) => result,
+                Either::Second(WaitResult::Message(Update::KeepAlive(id))) => {
+                    if keep_alive.is_some() {
+                        info!("Kicking a client that stopped answering keep alives");
+                        kick_from_play(socket, &encoder, "disconnect.timeout").await?;
+                        return Ok(());
+                    }
+                    encoder
+                        .write_packet(socket, &ClientboundKeepAlive { id })
+                        .await?;
+                    socket.flush().await.map_err(ProtocolError::io)?;
+                    keep_alive = Some(id);
+                    continue;
+                }
+                Either::Second(WaitResult::Message(Update::Reconfigure)) => {
+                    encoder.write_packet(socket, &StartConfiguration).await?;
+                    socket.flush().await.map_err(ProtocolError::io)?;
+                    awaiting_configuration = true;
+                    continue;
+                }
+                Either::Second(WaitResult::Message(update)) => {
+                    play::send_update(socket, &encoder, &update).await?;
+                    socket.flush().await.map_err(ProtocolError::io)?;
+                    continue;
+                }
+                Either::Second(WaitResult::Lagged(missed)) => {
+                    // Only happens if the client is too slow to keep up anyway
+                    warn!("A client missed {} updates", missed);
+                    continue;
+                }
+            },
+            None => read.await,
+        };
+        match result {
+            // The client is done with us, which is how status pings end
+            Err(ProtocolError::ConnectionClosed) => return Ok(()),
+            result => result?,
+        }
+
+        while let Ok(msg) = channel.try_receive() {
+            match msg {
+                PacketEvent::ProtocolVersion(client_version) => {
+                    protocol_version = client_version;
+                    encoder = FrameEncoder::new(client_version);
+                    version = Version::from_protocol(client_version).unwrap_or(Version::newest());
+                }
+                PacketEvent::ChangeState(new_state) => {
+                    info!("Changing state to {:?}", new_state);
+                    state = new_state;
+
+                    let joining = matches!(state, State::Login | State::Transfer);
+                    if joining && Version::from_protocol(protocol_version).is_none() {
+                        info!("Kicking client with protocol version {}", protocol_version);
+                        let reason = outdated_reason(protocol_version);
+                        encoder
+                            .write_packet(socket, &LoginDisconnect { reason })
+                            .await?;
+                        socket.flush().await.map_err(ProtocolError::io)?;
+                        return Ok(());
+                    }
+                }
+                PacketEvent::StatusRequest => {
+                    // Echoing the client's version if we support it shows the server as
+                    // compatible, otherwise the client shows our newest one in red
+                    encoder.write_packet(socket, &status(version, game)).await?;
+                }
+                PacketEvent::PingRequest(payload) => {
+                    info!("Sending pong with payload {}", payload);
+                    encoder
+                        .write_packet(socket, &PongResponse { payload })
+                        .await?;
+                }
+                PacketEvent::LoginStart(name) => {
+                    // Only once, before logging in or encrypting
+                    if player.is_some() || encrypting.is_some() {
+                        return Err(ProtocolError::UnknownPacketId(LoginStart::ID));
+                    }
+                    if !is_valid_username(&name) {
+                        info!("Kicking {:?} for an invalid username", name);
+                        let reason = TextComponent::text("Invalid username");
+                        encoder
+                            .write_packet(socket, &LoginDisconnect { reason })
+                            .await?;
+                        socket.flush().await.map_err(ProtocolError::io)?;
+                        return Ok(());
+                    }
+
+                    if config.check_sessions && config.key.is_none() {
+                        warn!(
+                            "Checking sessions needs a key, so {:?} can't be checked",
+                            name
+                        );
+                        let reason = TextComponent::translate(
+                            "multiplayer.disconnect.authservers_down",
+                            Vec::new(),
+                        );
+                        encoder
+                            .write_packet(socket, &LoginDisconnect { reason })
+                            .await?;
+                        socket.flush().await.map_err(ProtocolError::io)?;
+                        return Ok(());
+                    }
+
+                    if let Some(key) = &config.key {
+                        let mut verify_token = [0; VERIFY_TOKEN_LENGTH];
+                        rng.fill_bytes(&mut verify_token);
+                        let request = EncryptionRequest {
+                            server_id: String::new(),
+                            public_key: key.public_der().to_vec(),
+                            verify_token: verify_token.to_vec(),
+                            should_authenticate: config.check_sessions,
+                        };
+                        encoder.write_packet(socket, &request).await?;
+                        encrypting = Some((name, verify_token));
+                    } else {
+                        let profile = Profile::offline(name);
+                        let login =
+                            log_in(socket, &mut encoder, &mut decoder, config, profile).await?;
+                        player = Some(login);
+                    }
+                }
+                PacketEvent::EncryptionResponse(response) => {
+                    // Only valid as an answer to Encryption Request
+                    let (Some(key), Some((name, verify_token))) = (&config.key, encrypting.take())
+                    else {
+                        return Err(ProtocolError::UnknownPacketId(EncryptionResponse::ID));
+                    };
+                    let secret = key.shared_secret(rng, &response, &verify_token)?;
+                    socket.enable(&secret);
+                    info!("Encrypted the connection with {}", name);
+
+                    let profile = if config.check_sessions {
+                        let hash = server_hash("", &secret, key.public_der());
+                        let joined =
+                            has_joined(connector, rng, &config.session_server, &name, &hash).await;
+                        match joined {
+                            Ok(Some(profile)) => profile,
+                            result => {
+                                let key = if let Err(err) = result {
+                                    warn!(
+                                        "Couldn't check {} with the session server: {}",
+                                        name, err
+                                    );
+                                    "multiplayer.disconnect.authservers_down"
+                                } else {
+                                    info!("Kicking {} for failing authentication", name);
+                                    "multiplayer.disconnect.unverified_username"
+                                };
+                                let reason = TextComponent::translate(key, Vec::new());
+                                encoder
+                                    .write_packet(socket, &LoginDisconnect { reason })
+                                    .await?;
+                                socket.flush().await.map_err(ProtocolError::io)?;
+                                return Ok(());
+                            }
+                        }
+                    } else {
+                        Profile::offline(name)
+                    };
+
+                    let login = log_in(socket, &mut encoder, &mut decoder, config, profile).await?;
+                    player = Some(login);
+                }
+                PacketEvent::LoginAcknowledged => {
+                    // Only valid as an answer to Login Success
+                    let Some(player) = &player else {
+                        return Err(ProtocolError::UnknownPacketId(LoginAcknowledged::ID));
+                    };
+                    info!("{} logged in", player.name);
+                    state = State::Configuration;
+                    configuration::start(socket, &encoder, version).await?;
+                }
+                PacketEvent::ClientInformation(information) => {
+                    if let Some(player) = &mut player {
+                        player.information = Some(information);
+                    }
+                }
+                PacketEvent::PluginMessage(message) => {
+                    if message.channel.as_str() == "minecraft:brand" {
+                        let brand = (&message.data[..]).read_string().await?;
+                        info!("Client brand is {}", brand);
+                    }
+                }
+                PacketEvent::KnownPacks(packs) => {
+                    if !configuration::finish(socket, &encoder, version, &packs).await? {
+                        socket.flush().await.map_err(ProtocolError::io)?;
+                        return Ok(());
+                    }
+                    awaiting_finish = true;
+                }
+                PacketEvent::FinishConfiguration => {
+                    // Only valid as an answer to Finish Configuration, after logging in
+                    let (Some(player), true) = (&player, awaiting_finish) else {
+                        return Err(ProtocolError::UnknownPacketId(
+                            AcknowledgeFinishConfiguration::ID,
+                        ));
+                    };
+                    awaiting_finish = false;
+                    info!("Finished configuration");
+                    state = State::Play;
+
+                    updates = game.join();
+                    if updates.is_none() {
+                        info!("Kicking {} as the world is full", player.name);
+                        let reason = "multiplayer.disconnect.server_full";
+                        kick_from_play(socket, &encoder, reason).await?;
+                        return Ok(());
+                    }
+                    teleport_id += 1;
+                    play::join(socket, &encoder, player, &config.world, game, teleport_id).await?;
+                    teleport = Some(teleport_id);
+                    keep_alive = None;
+                }
+                PacketEvent::ConfirmTeleportation(id) => {
+                    if teleport == Some(id) {
+                        info!("Teleport {} confirmed", id);
+                        teleport = None;
+                    } else {
+                        // Vanilla ignores confirmations it isn't waiting for too
+                        debug!("Ignoring confirmation of teleport {}", id);
+                    }
+                }
+                PacketEvent::KeepAlive(id) => {
+                    if keep_alive != Some(id) {
+                        info!("Kicking a client for answering keep alive {}", id);
+                        kick_from_play(socket, &encoder, "disconnect.timeout").await?;
+                        return Ok(());
+                    }
+                    keep_alive = None;
+                }
+                PacketEvent::PlayerAction(action) => {
+                    if action.status == PlayerAction::STARTED_DIGGING {
+                        let (position, sequence) = (action.position, action.sequence);
+                        play::break_block(socket, &encoder, game, position, sequence).await?;
+                    }
+                }
+                PacketEvent::ConfigurationAcknowledged => {
+                    // Only valid as an answer to Start Configuration
+                    if !awaiting_configuration {
+                        return Err(ProtocolError::UnknownPacketId(
+                            ConfigurationAcknowledged::ID,
+                        ));
+                    }
+                    awaiting_configuration = false;
+                    state = State::Configuration;
+                    updates = None;
+                    configuration::start(socket, &encoder, version).await?;
+                }
+            }
+        }
+
+        socket.flush().await.map_err(ProtocolError::io)?;
+    }
+}
+
+/// Turns on compression and sends Login Success for `profile`, once the connection is encrypted
+/// if it's going to be.
+async fn log_in<W: Write>(
+    socket: &mut W,
+    encoder: &mut FrameEncoder,
+    decoder: &mut FrameDecoder,
+    config: &Config,
+    profile: Profile,
+) -> Result<Player, ProtocolError> {
+    let Profile {
+        uuid,
+        name,
+        properties,
+    } = profile;
+    info!("{} is logging in with UUID {}", name, uuid);
+
+    // Everything after Set Compression is in the compressed format
+    if let Some(threshold) = config.compression_threshold {
+        let compression = SetCompression {
+            threshold: threshold as i32,
+        };
+        encoder.write_packet(socket, &compression).await?;
+        encoder.set_compression(Some(threshold));
+        decoder.set_compression(Some(threshold));
+    }
+
+    let success = LoginSuccess {
+        uuid,
+        username: name.clone(),
+        properties: properties.clone(),
+        strict_error_handling: false,
+    };
+    encoder.write_packet(socket, &success).await?;
+    Ok(Player {
+        name,
+        uuid,
+        properties,
+        information: None,
+    })
+}
+
+/// Disconnects a player in Play, for the reason translated from `key`.
+async fn kick_from_play<W: Write>(
+    socket: &mut W,
+    encoder: &FrameEncoder,
+    key: &str,
+) -> Result<(), ProtocolError> {
+    let reason = TextComponent::translate(key, Vec::new());
+    encoder
+        .write_packet(socket, &PlayDisconnect { reason })
+        .await?;
+    socket.flush().await.map_err(ProtocolError::io)
+}
+
+/// What the server list shows, claiming to speak `version`, with the players in `game`.
+fn status<B: BlockStore>(version: Version, game: &Game<B>) -> StatusJson {
+    StatusJson {
+        version: VersionData {
+            name: version.name.to_string(),
+            protocol: version.protocol,
+        },
+        players: Some(PlayerData {
+            max: MAX_PLAYERS,
+            online: game.players(),
+            sample: None,
+        }),
+        description: Some(TextComponent::from_legacy("A §aPicoCraft§r server.")),
+        favicon: None,
+        enforces_secure_chat: false,
+    }
+}
+
+/// Why a client with `protocol_version` can't join, in the client's own language.
+fn outdated_reason(protocol_version: i32) -> TextComponent {
+    let (oldest, newest) = (Version::oldest(), Version::newest());
+    let supported = if oldest == newest {
+        newest.name.to_string()
+    } else {
+        format!("{}-{}", oldest.name, newest.name)
+    };
+
+    let key = if protocol_version < oldest.protocol {
+        "multiplayer.disconnect.outdated_client"
+    } else {
+        "multiplayer.disconnect.outdated_server"
+    };
+    TextComponent::translate(key, vec![TextComponent::text(supported)])
+}
+
+async fn read_packets<R: Read>(
+    socket: &mut R,
+    decoder: &mut FrameDecoder,
+    channel: &Channel<NoopRawMutex, PacketEvent, 4>,
+    state: &State,
+    protocol_version: i32,
+) -> Result<(), ProtocolError> {
+    let mut packet = decoder.read_packet(socket).await?;
+    info!("Received packet with id {}", packet.id);
+
+    let decoded = match ServerboundPacket::decode(*state, protocol_version, &mut packet).await {
+        // Players send lots we don't do anything with yet, like movement, which is no reason to
+        // kick them
+        Err(ProtocolError::UnknownPacketId(id)) if *state == State::Play => {
+            debug!("Ignoring play packet with id {:#04x}", id);
+            return Ok(());
+        }
+        result => result?,
+    };
+
+    match decoded {
+        ServerboundPacket::Handshake(packet) => {
+            info!(
+                "Received handshake packet {} {} {} {:?}",
+                packet.protocol_version,
+                packet.server_address,
+                packet.server_port,
+                packet.next_state
+            );
+
+            channel
+                .send(PacketEvent::ProtocolVersion(packet.protocol_version))
+                .await;
+            channel
+                .send(PacketEvent::ChangeState(packet.next_state))
+                .await;
+        }
+        ServerboundPacket::StatusRequest(_) => {
+            info!("Received status request");
+            channel.send(PacketEvent::StatusRequest).await;
+        }
+        ServerboundPacket::PingRequest(ping) => {
+            info!("Received ping request");
+            channel.send(PacketEvent::PingRequest(ping.payload)).await;
+        }
+        ServerboundPacket::LoginStart(login) => {
+            info!("Received login start from {}", login.name);
+            channel.send(PacketEvent::LoginStart(login.name)).await;
+        }
+        ServerboundPacket::EncryptionResponse(response) => {
+            info!("Received encryption response");
+            channel
+                .send(PacketEvent::EncryptionResponse(response))
+                .await;
+        }
+        ServerboundPacket::LoginAcknowledged(_) => {
+            info!("Received login acknowledged");
+            channel.send(PacketEvent::LoginAcknowledged).await;
+        }
+        ServerboundPacket::ClientInformation(information) => {
+            info!(
+                "Received client information, locale {} and view distance {}",
+                information.locale, information.view_distance
+            );
+            channel
+                .send(PacketEvent::ClientInformation(information))
+                .await;
+        }
+        ServerboundPacket::PluginMessage(message) => {
+            info!("Received plugin message on {}", message.channel);
+            channel.send(PacketEvent::PluginMessage(message)).await;
+        }
+        ServerboundPacket::KnownPacks(packs) => {
+            info!("Received {} known packs", packs.packs.len());
+            channel.send(PacketEvent::KnownPacks(packs.packs)).await;
+        }
+        ServerboundPacket::AcknowledgeFinishConfiguration(_) => {
+            info!("Received acknowledge finish configuration");
+            channel.send(PacketEvent::FinishConfiguration).await;
+        }
+        ServerboundPacket::ConfigurationAcknowledged(_) => {
+            info!("Received configuration acknowledged");
+            channel.send(PacketEvent::ConfigurationAcknowledged).await;
+        }
+        ServerboundPacket::ConfirmTeleportation(confirm) => {
+            info!("Received confirm teleportation {}", confirm.teleport_id);
+            channel
+                .send(PacketEvent::ConfirmTeleportation(confirm.teleport_id))
+                .await;
+        }
+        ServerboundPacket::KeepAlive(keep_alive) => {
+            info!("Received keep alive {}", keep_alive.id);
+            channel.send(PacketEvent::KeepAlive(keep_alive.id)).await;
+        }
+        ServerboundPacket::PlayerAction(action) => {
+            info!(
+                "Received player action {} at {:?}",
+                action.status, action.position
+            );
+            channel.send(PacketEvent::PlayerAction(action)).await;
+        }
+    }
+
+    Ok(())
+}
+
+pub enum PacketEvent {
+    ProtocolVersion(i32),
+    ChangeState(State),
+    StatusRequest,
+    PingRequest(i64),
+    LoginStart(String),
+    EncryptionResponse(EncryptionResponse),
+    LoginAcknowledged,
+    ClientInformation(ClientInformation),
+    PluginMessage(ServerboundPluginMessage),
+    KnownPacks(Vec<KnownPack>),
+    FinishConfiguration,
+    ConfigurationAcknowledged,
+    ConfirmTeleportation(i32),
+    KeepAlive(i64),
+    PlayerAction(PlayerAction),
+}
@@ -0,0 +1,189 @@
+//! The Play state, once the player is in the world.
+
+use alloc::vec;
+use embassy_time::Duration;
+use embedded_io_async::Write;
+use log::{info, warn};
+use picocraft_protocol::{
+    error::ProtocolError,
+    frame::FrameEncoder,
+    packets::play::{
+        AcknowledgeBlockChange, BlockUpdate, ChunkDataAndUpdateLight, GameEvent, LoginPlay,
+        SetCenterChunk, SetDefaultSpawnPosition, SynchronizePlayerPosition, UpdateTime,
+    },
+    types::{Identifier, Position},
+};
+
+use crate::{
+    blocks::AIR,
+    game::{Game, Update, DAY_LENGTH},
+    registries::{self, BIOME, DIMENSION_TYPE},
+    store::{BlockEdit, BlockStore},
+    world::{Generator, World},
+    Player, MAX_PLAYERS,
+};
+
+/// The furthest we send chunks, in chunks. Clients asking for less get less.
+pub const VIEW_DISTANCE: i32 = 4;
+
+/// How often the client has to answer a keep alive, the same as vanilla.
+pub const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// The overworld's height and bottom, which every chunk has to match.
+pub const WORLD_HEIGHT: i32 = 384;
+pub const MIN_Y: i32 = -64;
+
+/// Every player is the same entity until there are other entities to tell them apart from.
+const ENTITY_ID: i32 = 1;
+
+/// Puts the player in `world` and sends the chunks around them, generating each one as it goes
+/// with the edits kept by `game` laid over it.
+///
+/// The client answers the position with a teleport confirmation for `teleport_id`.
+pub async fn join<W: Write, B: BlockStore>(
+    socket: &mut W,
+    encoder: &FrameEncoder,
+    player: &Player,
+    world: &World,
+    game: &Game<B>,
+    teleport_id: i32,
+) -> Result<(), ProtocolError> {
+    let view_distance = player
+        .information
+        .as_ref()
+        .map_or(VIEW_DISTANCE, |information| {
+            (information.view_distance as i32).clamp(2, VIEW_DISTANCE)
+        });
+    let overworld = Identifier::new("overworld")?;
+    // The client knows the dimension types from its own data pack
+    let dimension_type = registries::id_of(DIMENSION_TYPE, overworld.as_str())
+        .expect("registries.txt has no overworld");
+
+    let login = LoginPlay {
+        entity_id: ENTITY_ID,
+        is_hardcore: false,
+        dimension_names: vec![overworld.clone()],
+        max_players: MAX_PLAYERS as i32,
+        view_distance,
+        simulation_distance: view_distance,
+        reduced_debug_info: false,
+        enable_respawn_screen: true,
+        do_limited_crafting: false,
+        dimension_type,
+        dimension_name: overworld,
+        hashed_seed: 0,
+        // Creative, so falling into the void doesn't matter
+        game_mode: 1,
+        previous_game_mode: -1,
+        is_debug: false,
+        is_flat: matches!(world.generator, Generator::Superflat(_)),
+        death_location: None,
+        portal_cooldown: 0,
+        enforces_secure_chat: false,
+    };
+    encoder.write_packet(socket, &login).await?;
+    encoder
+        .write_packet(socket, &update_time(game.ticks()))
+        .await?;
+
+    let spawn = world.spawn();
+    let default_spawn = SetDefaultSpawnPosition {
+        position: spawn,
+        angle: 0.0,
+    };
+    encoder.write_packet(socket, &default_spawn).await?;
+
+    let waiting = GameEvent {
+        event: GameEvent::START_WAITING_FOR_CHUNKS,
+        value: 0.0,
+    };
+    encoder.write_packet(socket, &waiting).await?;
+
+    let (center_x, center_z) = (spawn.x >> 4, spawn.z >> 4);
+    let center = SetCenterChunk {
+        x: center_x,
+        z: center_z,
+    };
+    encoder.write_packet(socket, &center).await?;
+
+    info!(
+        "Sending {} chunks to {}",
+        (2 * view_distance + 1) * (2 * view_distance + 1),
+        player.name
+    );
+    let plains =
+        registries::id_of(BIOME, "minecraft:plains").expect("registries.txt has no plains");
+    for x in center_x - view_distance..=center_x + view_distance {
+        for z in center_z - view_distance..=center_z + view_distance {
+            let mut column = world.chunk(x, z, plains as u32);
+            let edits = game.store.borrow_mut().edits_in(x, z, |edit| {
+                let (x, y, z) = (edit.x as usize, edit.y as usize, edit.z as usize);
+                column.set_block(x, y, z, edit.block);
+            });
+            if let Err(err) = edits {
+                warn!("Couldn't read the edits to chunk {}, {}: {}", x, z, err);
+            }
+            let chunk = ChunkDataAndUpdateLight::new(x, z, &column, BIOME.len());
+            encoder.write_packet(socket, &chunk).await?;
+            // Each chunk takes a while, so let everyone else have a turn
+            embassy_futures::yield_now().await;
+        }
+    }
+
+    let position = SynchronizePlayerPosition {
+        x: spawn.x as f64 + 0.5,
+        y: spawn.y as f64,
+        z: spawn.z as f64 + 0.5,
+        yaw: 0.0,
+        pitch: 0.0,
+        // Nothing is relative
+        flags: 0,
+        teleport_id,
+    };
+    encoder.write_packet(socket, &position).await
+}
+
+/// Breaks the block at `position` for everyone on the next tick, then tells the client it's
+/// been dealt with.
+pub async fn break_block<W: Write, B: BlockStore>(
+    socket: &mut W,
+    encoder: &FrameEncoder,
+    game: &Game<B>,
+    position: Position,
+    sequence: i32,
+) -> Result<(), ProtocolError> {
+    if (MIN_Y..MIN_Y + WORLD_HEIGHT).contains(&position.y) {
+        game.change_block(BlockEdit::new(position, AIR));
+    }
+    encoder
+        .write_packet(socket, &AcknowledgeBlockChange { sequence })
+        .await
+}
+
+/// Sends the player something the game loop told everyone, other than keep alives, which
+/// [`serve`](crate::serve) has to keep track of.
+pub async fn send_update<W: Write>(
+    socket: &mut W,
+    encoder: &FrameEncoder,
+    update: &Update,
+) -> Result<(), ProtocolError> {
+    match update {
+        // These need the connection's state, so they're handled by it
+        Update::KeepAlive(_) | Update::Reconfigure => Ok(()),
+        Update::Time(ticks) => encoder.write_packet(socket, &update_time(*ticks)).await,
+        Update::Block(edit) => {
+            let block = BlockUpdate {
+                position: edit.position(),
+                block: edit.block as i32,
+            };
+            encoder.write_packet(socket, &block).await
+        }
+    }
+}
+
+fn update_time(ticks: u64) -> UpdateTime {
+    UpdateTime {
+        world_age: ticks as i64,
+        time_of_day: (ticks % DAY_LENGTH) as i64,
+    }
+}
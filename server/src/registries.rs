@@ -0,0 +1,29 @@
+//! The entries of every synchronised registry, generated from `data/registries.txt`.
+//!
+//! Entries are numbered in the order they are sent, so these orders are what other packets' ids
+//! refer to.
+//!
+//! There's no data for the entries, only their names. The client takes the data from its own
+//! `minecraft:core` pack, so [`configuration::finish`](crate::configuration::finish) only sends
+//! these to clients that say they have the pack for their version.
+
+use picocraft_protocol::types::StringList;
+
+include!(concat!(env!("OUT_DIR"), "/registries.rs"));
+
+/// Every registry a client speaking `protocol_version` needs, with the entries its vanilla pack
+/// has.
+///
+/// [`REGISTRIES`] and the constants for each registry are the newest version's, so ids taken from
+/// them are only right for older versions if the registry hasn't changed since.
+pub fn for_version(protocol_version: i32) -> &'static [(&'static str, StringList<'static>)] {
+    VERSIONED_REGISTRIES
+        .iter()
+        .find(|(since, _)| *since <= protocol_version)
+        .map_or(REGISTRIES, |(_, registries)| registries)
+}
+
+/// The id of `entry` in `entries`, as other packets refer to it.
+pub fn id_of(entries: StringList, entry: &str) -> Option<i32> {
+    entries.position(entry).map(|i| i as i32)
+}
@@ -0,0 +1,282 @@
+//! Checks players with a session server, like vanilla's `hasJoined` request in online mode.
+//!
+//! The client tells the session server it's joining a server with a hash only the two of them
+//! can work out, then we ask the session server whether anyone with that name joined with the same
+//! hash. If they did, the session server answers with their real UUID and skin.
+//!
+//! Requests are plain HTTP/1.0, which the session server can't answer with chunked bodies, over a
+//! connection from whatever implements [`Connect`]. `https://` URLs go over TLS 1.3, but the
+//! session server's certificate isn't checked, so this only keeps out players who can't get
+//! between us and the session server.
+
+use alloc::{format, string::String, vec, vec::Vec};
+use core::fmt;
+
+use embedded_io_async::{Error, ErrorKind, Read, Write};
+use embedded_tls::{Aes128GcmSha256, NoVerify, TlsConfig, TlsConnection, TlsContext};
+use log::{info, warn};
+use picocraft_protocol::{packets::login::Property, types::Uuid};
+use rand_core::CryptoRngCore;
+use serde::Deserialize;
+
+/// Mojang's session server, which vanilla servers use.
+pub const MOJANG_SESSION_SERVER: &str = "https://sessionserver.mojang.com";
+
+/// The most we read of a response. Profiles are under 2 KiB, even with a skin.
+const MAX_RESPONSE_LENGTH: usize = 8192;
+
+/// TLS records can be this long, so the read buffer needs to be as well.
+const TLS_READ_BUFFER: usize = 16640;
+const TLS_WRITE_BUFFER: usize = 4096;
+
+/// Opens TCP connections, so this module doesn't need to know about the network stack.
+pub trait Connect {
+    type Connection<'a>: Read + Write
+    where
+        Self: 'a;
+
+    /// Connects to `host`, which may need looking up in DNS first.
+    async fn connect(&mut self, host: &str, port: u16) -> Result<Self::Connection<'_>, ErrorKind>;
+}
+
+/// A player's profile, with their real UUID if it came from the session server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub uuid: Uuid,
+    pub name: String,
+    /// Skin and cape, signed by the session server.
+    pub properties: Vec<Property>,
+}
+
+impl Profile {
+    /// The profile vanilla makes up for `name` in offline mode, which has no skin.
+    pub fn offline(name: String) -> Profile {
+        Profile {
+            uuid: Uuid::offline_player(&name),
+            name,
+            properties: Vec::new(),
+        }
+    }
+}
+
+/// Why a player couldn't be checked with the session server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionError {
+    /// The session server's URL isn't an `http://` or `https://` one.
+    InvalidUrl,
+    /// Connecting, or talking to the session server, failed.
+    Io(ErrorKind),
+    /// The TLS handshake failed.
+    Tls,
+    /// The session server answered with a status other than 200 or 204.
+    Status(u16),
+    /// The response wasn't HTTP, or didn't have a profile in it.
+    InvalidResponse,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::InvalidUrl => write!(f, "invalid session server URL"),
+            SessionError::Io(kind) => write!(f, "I/O error: {:?}", kind),
+            SessionError::Tls => write!(f, "TLS handshake failed"),
+            SessionError::Status(status) => write!(f, "session server returned {}", status),
+            SessionError::InvalidResponse => write!(f, "invalid session server response"),
+        }
+    }
+}
+
+/// Asks `session_server` whether `username` joined with `server_hash`, returning their profile if
+/// they did.
+///
+/// `session_server` is the base URL, like [`MOJANG_SESSION_SERVER`].
+pub async fn has_joined<C: Connect, R: CryptoRngCore>(
+    connector: &mut C,
+    rng: &mut R,
+    session_server: &str,
+    username: &str,
+    server_hash: &str,
+) -> Result<Option<Profile>, SessionError> {
+    let url = Url::parse(session_server).ok_or(SessionError::InvalidUrl)?;
+    // Usernames and hashes never need escaping in a query
+    let request = format!(
+        "GET {}/session/minecraft/hasJoined?username={}&serverId={} HTTP/1.0\r\n\
+         Host: {}\r\n\
+         User-Agent: PicoCraft\r\n\
+         \r\n",
+        url.path, username, server_hash, url.host
+    );
+
+    info!("Checking {} with {}", username, url.host);
+    let mut socket = connector
+        .connect(url.host, url.port)
+        .await
+        .map_err(SessionError::Io)?;
+
+    let response = if url.tls {
+        let mut read_buffer = vec![0; TLS_READ_BUFFER];
+        let mut write_buffer = vec![0; TLS_WRITE_BUFFER];
+        let mut tls: TlsConnection<_, Aes128GcmSha256> =
+            TlsConnection::new(&mut socket, &mut read_buffer, &mut write_buffer);
+
+        // There's no room for a certificate store on the Pico, and embedded-tls doesn't let a
+        // verifier see the certificate to pin it, so the certificate isn't checked
+        let config = TlsConfig::new()
+            .with_server_name(url.host)
+            .enable_rsa_signatures();
+        tls.open::<_, NoVerify>(TlsContext::new(&config, rng))
+            .await
+            .map_err(|err| {
+                warn!("TLS handshake with {} failed: {:?}", url.host, err);
+                SessionError::Tls
+            })?;
+        exchange(&mut tls, &request).await?
+    } else {
+        exchange(&mut socket, &request).await?
+    };
+
+    parse_response(&response)
+}
+
+/// The parts of a session server URL we need.
+struct Url<'a> {
+    tls: bool,
+    host: &'a str,
+    port: u16,
+    /// Anything after the host, without a trailing slash.
+    path: &'a str,
+}
+
+impl Url<'_> {
+    fn parse(url: &str) -> Option<Url<'_>> {
+        let (tls, rest) = if let Some(rest) = url.strip_prefix("https://") {
+            (true, rest)
+        } else {
+            (false, url.strip_prefix("http://")?)
+        };
+
+        let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().ok()?),
+            None => (authority, if tls { 443 } else { 80 }),
+        };
+        if host.is_empty() {
+            return None;
+        }
+
+        Some(Url {
+            tls,
+            host,
+            port,
+            path: path.trim_end_matches('/'),
+        })
+    }
+}
+
+/// Sends `request` and reads the whole response.
+async fn exchange<S: Read + Write>(socket: &mut S, request: &str) -> Result<Vec<u8>, SessionError> {
+    let io = |err: S::Error| SessionError::Io(err.kind());
+    socket.write_all(request.as_bytes()).await.map_err(io)?;
+    socket.flush().await.map_err(io)?;
+
+    let mut response = Vec::new();
+    let mut chunk = [0; 256];
+    loop {
+        let read = match socket.read(&mut chunk).await {
+            Ok(read) => read,
+            // TLS reports the server closing the connection as an error, which is fine once the
+            // headers are in, as the body is checked when it's parsed
+            Err(_) if body_start(&response).is_some() => 0,
+            Err(err) => return Err(io(err)),
+        };
+        if read == 0 {
+            return Ok(response);
+        }
+        if response.len() + read > MAX_RESPONSE_LENGTH {
+            return Err(SessionError::InvalidResponse);
+        }
+        response.extend_from_slice(&chunk[..read]);
+
+        // Don't wait for the server to hang up if it said how long the body is
+        if let (Some(start), Some(length)) = (body_start(&response), content_length(&response)) {
+            if response.len() >= start + length {
+                response.truncate(start + length);
+                return Ok(response);
+            }
+        }
+    }
+}
+
+/// Where the body starts, once all of the headers have been read.
+fn body_start(response: &[u8]) -> Option<usize> {
+    response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .map(|end| end + 4)
+}
+
+fn content_length(response: &[u8]) -> Option<usize> {
+    let head = core::str::from_utf8(&response[..body_start(response)?]).ok()?;
+    head.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        if !name.eq_ignore_ascii_case("content-length") {
+            return None;
+        }
+        value.trim().parse().ok()
+    })
+}
+
+fn parse_response(response: &[u8]) -> Result<Option<Profile>, SessionError> {
+    let start = body_start(response).ok_or(SessionError::InvalidResponse)?;
+    let head =
+        core::str::from_utf8(&response[..start]).map_err(|_| SessionError::InvalidResponse)?;
+    let status = head
+        .strip_prefix("HTTP/1.")
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or(SessionError::InvalidResponse)?;
+
+    match status {
+        200 => parse_profile(&response[start..]).map(Some),
+        // The player didn't join with that hash, so they aren't who they say they are
+        204 => Ok(None),
+        status => Err(SessionError::Status(status)),
+    }
+}
+
+#[derive(Deserialize)]
+struct ProfileJson {
+    id: String,
+    name: String,
+    #[serde(default)]
+    properties: Vec<PropertyJson>,
+}
+
+#[derive(Deserialize)]
+struct PropertyJson {
+    name: String,
+    value: String,
+    #[serde(default)]
+    signature: Option<String>,
+}
+
+fn parse_profile(body: &[u8]) -> Result<Profile, SessionError> {
+    // Gson escapes `=` as `\u003d`, so base64 values may need unescaping somewhere
+    let mut unescaped = vec![0; body.len()];
+    let (profile, _): (ProfileJson, _) = serde_json_core::from_slice_escaped(body, &mut unescaped)
+        .map_err(|_| SessionError::InvalidResponse)?;
+
+    Ok(Profile {
+        uuid: Uuid::from_simple(&profile.id).ok_or(SessionError::InvalidResponse)?,
+        name: profile.name,
+        properties: profile
+            .properties
+            .into_iter()
+            .map(|property| Property {
+                name: property.name,
+                value: property.value,
+                signature: property.signature,
+            })
+            .collect(),
+    })
+}
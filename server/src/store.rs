@@ -0,0 +1,463 @@
+//! Blocks players have changed, kept so they outlive the connection and, in flash, a reboot.
+//!
+//! The generated world is the same every time, so the edits are all that needs keeping. They're
+//! laid over each chunk as it's generated.
+
+use alloc::vec::Vec;
+use core::fmt;
+use embedded_storage::nor_flash::{NorFlash, NorFlashError, NorFlashErrorKind};
+use picocraft_protocol::{chunk::SECTION_SIZE, types::Position};
+
+use crate::{blocks::BlockState, play::MIN_Y};
+
+/// A block someone changed, and what it is now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockEdit {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    /// Where the block is in the chunk, with `x` and `z` from 0 to 15 and `y` counting up from
+    /// the bottom of the world.
+    pub x: u8,
+    pub y: u16,
+    pub z: u8,
+    pub block: BlockState,
+}
+
+impl BlockEdit {
+    /// Changes the block at `position` in world coordinates to `block`.
+    pub fn new(position: Position, block: BlockState) -> BlockEdit {
+        let size = SECTION_SIZE as i32;
+        BlockEdit {
+            chunk_x: position.x.div_euclid(size),
+            chunk_z: position.z.div_euclid(size),
+            x: position.x.rem_euclid(size) as u8,
+            y: (position.y - MIN_Y) as u16,
+            z: position.z.rem_euclid(size) as u8,
+            block,
+        }
+    }
+
+    /// Where the block is in world coordinates.
+    pub fn position(&self) -> Position {
+        let size = SECTION_SIZE as i32;
+        Position {
+            x: self.chunk_x * size + self.x as i32,
+            y: self.y as i32 + MIN_Y,
+            z: self.chunk_z * size + self.z as i32,
+        }
+    }
+
+    /// Whether `other` changes the same block, so one of them overrules the other.
+    pub fn same_block(&self, other: &BlockEdit) -> bool {
+        (self.chunk_x, self.chunk_z, self.x, self.y, self.z)
+            == (other.chunk_x, other.chunk_z, other.x, other.y, other.z)
+    }
+}
+
+/// Why an edit couldn't be kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreError {
+    /// Reading, writing or erasing the flash failed.
+    Flash(NorFlashErrorKind),
+    /// Every edit in the store is still needed, so there's no room for another.
+    Full,
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Flash(kind) => write!(f, "flash error: {:?}", kind),
+            StoreError::Full => write!(f, "block store is full"),
+        }
+    }
+}
+
+fn flash_error<E: NorFlashError>(err: E) -> StoreError {
+    StoreError::Flash(err.kind())
+}
+
+/// Somewhere to keep block edits.
+///
+/// This is blocking, as writing to the Pico's flash stops everything else anyway.
+pub trait BlockStore {
+    /// Keeps `edit`, which overrules any earlier edit to the same block.
+    fn record(&mut self, edit: &BlockEdit) -> Result<(), StoreError>;
+
+    /// Calls `f` with the edits in the chunk at `chunk_x`, `chunk_z`, oldest first.
+    fn edits_in(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        f: impl FnMut(&BlockEdit),
+    ) -> Result<(), StoreError>;
+}
+
+/// Keeps edits in RAM, so they're forgotten when the server stops.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    edits: Vec<BlockEdit>,
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+}
+
+impl BlockStore for MemoryStore {
+    fn record(&mut self, edit: &BlockEdit) -> Result<(), StoreError> {
+        self.edits.retain(|earlier| !earlier.same_block(edit));
+        self.edits.push(*edit);
+        Ok(())
+    }
+
+    fn edits_in(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        mut f: impl FnMut(&BlockEdit),
+    ) -> Result<(), StoreError> {
+        self.edits
+            .iter()
+            .filter(|edit| (edit.chunk_x, edit.chunk_z) == (chunk_x, chunk_z))
+            .for_each(&mut f);
+        Ok(())
+    }
+}
+
+/// How many bytes each edit takes in flash.
+pub const RECORD_LENGTH: usize = 16;
+
+/// How many bytes the header at the start of each bank takes: one record with its generation,
+/// then one with the seed its edits were made under.
+pub const HEADER_LENGTH: usize = 2 * RECORD_LENGTH;
+
+/// Marks a bank's header, "PCBS".
+const MAGIC: u32 = 0x5043_4253;
+
+/// Keeps edits in a log in flash.
+///
+/// The store is split into two banks, and edits are appended to the one in use until it's full.
+/// It's then compacted into the other bank, leaving out edits that were overruled, and that
+/// bank's header is written last of all, with a higher generation than the old one. Losing
+/// power part way through leaves the old bank as the newest one with a valid header, and a
+/// record that was only partly written fails its checksum and is skipped, so the store never
+/// loses an edit that was finished.
+///
+/// The edits only make sense over the world they were made in, so the seed it was generated from
+/// is kept in the header too.
+#[derive(Debug)]
+pub struct FlashStore<F> {
+    flash: F,
+    /// Where the store starts in `flash`.
+    start: u32,
+    /// How big each of the two banks is.
+    bank_size: u32,
+    /// The bank in use, 0 or 1.
+    bank: u32,
+    generation: u32,
+    seed: u64,
+    /// Where the next record goes in the bank in use.
+    end: u32,
+}
+
+impl<F: NorFlash> FlashStore<F> {
+    /// Opens the store in the `size` bytes of `flash` from `start`, with whatever edits are
+    /// already there, or starts a new one for worlds generated from `seed` if there's no store
+    /// there yet.
+    ///
+    /// `start` and `size` have to be lined up with the flash's erase sectors, with a whole
+    /// number of sectors in each half of `size`.
+    pub fn open(flash: F, start: u32, size: u32, seed: u64) -> Result<FlashStore<F>, StoreError> {
+        let bank_size = size / 2;
+        assert!(
+            start.is_multiple_of(F::ERASE_SIZE as u32)
+                && bank_size.is_multiple_of(F::ERASE_SIZE as u32),
+            "the block store has to be made of whole erase sectors"
+        );
+        assert!(RECORD_LENGTH.is_multiple_of(F::WRITE_SIZE) && bank_size as usize > HEADER_LENGTH);
+
+        let mut store = FlashStore {
+            flash,
+            start,
+            bank_size,
+            bank: 0,
+            generation: 0,
+            seed,
+            end: HEADER_LENGTH as u32,
+        };
+        let mut newest: Option<(u32, u32, u64)> = None;
+        for bank in [0, 1] {
+            if let Some((generation, seed)) = store.read_header(bank)? {
+                if newest.is_none_or(|(_, newest, _)| generation > newest) {
+                    newest = Some((bank, generation, seed));
+                }
+            }
+        }
+
+        match newest {
+            Some((bank, generation, seed)) => {
+                store.bank = bank;
+                store.generation = generation;
+                store.seed = seed;
+                store.end = store.find_end()?;
+            }
+            None => {
+                store.erase(0)?;
+                store.write_header(0, 1, seed)?;
+                store.generation = 1;
+            }
+        }
+        Ok(store)
+    }
+
+    /// The seed the world these edits were made in was generated from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Forgets every edit and starts again for worlds generated from `seed`.
+    ///
+    /// Like compacting, this switches to the other bank, so losing power part way through
+    /// leaves the old edits as they were.
+    pub fn reset(&mut self, seed: u64) -> Result<(), StoreError> {
+        let other = 1 - self.bank;
+        self.erase(other)?;
+        self.write_header(other, self.generation.wrapping_add(1), seed)?;
+        self.bank = other;
+        self.generation = self.generation.wrapping_add(1);
+        self.seed = seed;
+        self.end = HEADER_LENGTH as u32;
+        Ok(())
+    }
+
+    /// Gives the flash back.
+    pub fn into_inner(self) -> F {
+        self.flash
+    }
+
+    /// How many more edits fit before the store has to be compacted.
+    pub fn space(&self) -> usize {
+        (self.bank_size - self.end) as usize / RECORD_LENGTH
+    }
+
+    fn bank_start(&self, bank: u32) -> u32 {
+        self.start + bank * self.bank_size
+    }
+
+    fn read(&mut self, bank: u32, offset: u32, bytes: &mut [u8]) -> Result<(), StoreError> {
+        let start = self.bank_start(bank);
+        self.flash.read(start + offset, bytes).map_err(flash_error)
+    }
+
+    fn erase(&mut self, bank: u32) -> Result<(), StoreError> {
+        let start = self.bank_start(bank);
+        self.flash
+            .erase(start, start + self.bank_size)
+            .map_err(flash_error)
+    }
+
+    /// The bank's generation and seed, if it has a valid header.
+    fn read_header(&mut self, bank: u32) -> Result<Option<(u32, u64)>, StoreError> {
+        let mut header = [0; HEADER_LENGTH];
+        self.read(bank, 0, &mut header)?;
+        let (first, second) = header.split_at(RECORD_LENGTH);
+        let (Some(first), Some(second)) = (
+            checked(first.try_into().unwrap()),
+            checked(second.try_into().unwrap()),
+        ) else {
+            return Ok(None);
+        };
+        if u32::from_le_bytes(first[0..4].try_into().unwrap()) != MAGIC {
+            return Ok(None);
+        }
+        Ok(Some((
+            u32::from_le_bytes(first[4..8].try_into().unwrap()),
+            u64::from_le_bytes(second[0..8].try_into().unwrap()),
+        )))
+    }
+
+    /// Writes the bank's header, with the record that makes it valid last.
+    fn write_header(&mut self, bank: u32, generation: u32, seed: u64) -> Result<(), StoreError> {
+        let start = self.bank_start(bank);
+        let mut data = [0; RECORD_LENGTH - 2];
+        data[0..8].copy_from_slice(&seed.to_le_bytes());
+        self.flash
+            .write(start + RECORD_LENGTH as u32, &with_checksum(&data))
+            .map_err(flash_error)?;
+
+        let mut data = [0; RECORD_LENGTH - 2];
+        data[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        data[4..8].copy_from_slice(&generation.to_le_bytes());
+        self.flash
+            .write(start, &with_checksum(&data))
+            .map_err(flash_error)
+    }
+
+    /// Calls `f` with each record in the bank in use from `from` up to `to` and where it is,
+    /// until `f` returns false.
+    fn scan(
+        &mut self,
+        from: u32,
+        to: u32,
+        mut f: impl FnMut(u32, &[u8; RECORD_LENGTH]) -> bool,
+    ) -> Result<(), StoreError> {
+        let mut buffer = [0; 16 * RECORD_LENGTH];
+        let mut offset = from;
+        while offset < to {
+            let length = ((to - offset) as usize).min(buffer.len());
+            self.read(self.bank, offset, &mut buffer[..length])?;
+            for (i, record) in buffer[..length].chunks_exact(RECORD_LENGTH).enumerate() {
+                if !f(
+                    offset + (i * RECORD_LENGTH) as u32,
+                    record.try_into().unwrap(),
+                ) {
+                    return Ok(());
+                }
+            }
+            offset += length as u32;
+        }
+        Ok(())
+    }
+
+    /// Where the first erased record is in the bank in use.
+    ///
+    /// Half written records aren't erased, so they're skipped rather than written over.
+    fn find_end(&mut self) -> Result<u32, StoreError> {
+        let mut end = self.bank_size;
+        self.scan(HEADER_LENGTH as u32, self.bank_size, |offset, record| {
+            if record.iter().all(|byte| *byte == 0xff) {
+                end = offset;
+                return false;
+            }
+            true
+        })?;
+        Ok(end)
+    }
+
+    /// Copies the edits that are still needed into the other bank and switches to it.
+    ///
+    /// Each edit is checked against every one after it, which is slow, but it only happens once
+    /// every few thousand edits and doesn't need any memory.
+    fn compact(&mut self) -> Result<(), StoreError> {
+        let other = 1 - self.bank;
+        self.erase(other)?;
+
+        let mut written = HEADER_LENGTH as u32;
+        let mut offset = HEADER_LENGTH as u32;
+        while offset < self.end {
+            let mut record = [0; RECORD_LENGTH];
+            self.read(self.bank, offset, &mut record)?;
+            offset += RECORD_LENGTH as u32;
+            let Some(edit) = decode(&record) else {
+                continue;
+            };
+
+            let mut overruled = false;
+            self.scan(offset, self.end, |_, later| {
+                overruled = decode(later).is_some_and(|later| later.same_block(&edit));
+                !overruled
+            })?;
+            if !overruled {
+                let start = self.bank_start(other);
+                self.flash
+                    .write(start + written, &record)
+                    .map_err(flash_error)?;
+                written += RECORD_LENGTH as u32;
+            }
+        }
+
+        // Only now does the other bank count
+        self.write_header(other, self.generation.wrapping_add(1), self.seed)?;
+        self.bank = other;
+        self.generation = self.generation.wrapping_add(1);
+        self.end = written;
+        Ok(())
+    }
+}
+
+impl<F: NorFlash> BlockStore for FlashStore<F> {
+    fn record(&mut self, edit: &BlockEdit) -> Result<(), StoreError> {
+        if self.space() == 0 {
+            self.compact()?;
+            if self.space() == 0 {
+                return Err(StoreError::Full);
+            }
+        }
+
+        // Whether or not the write works, the record might be half written now
+        let offset = self.bank_start(self.bank) + self.end;
+        self.end += RECORD_LENGTH as u32;
+        self.flash.write(offset, &encode(edit)).map_err(flash_error)
+    }
+
+    fn edits_in(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        mut f: impl FnMut(&BlockEdit),
+    ) -> Result<(), StoreError> {
+        self.scan(HEADER_LENGTH as u32, self.end, |_, record| {
+            if let Some(edit) = decode(record) {
+                if (edit.chunk_x, edit.chunk_z) == (chunk_x, chunk_z) {
+                    f(&edit);
+                }
+            }
+            true
+        })
+    }
+}
+
+/// Packs `edit` into a record: the chunk's x and z, y, x and z packed into a byte, a byte of
+/// padding, the block and then a checksum, all little endian.
+fn encode(edit: &BlockEdit) -> [u8; RECORD_LENGTH] {
+    let mut data = [0; RECORD_LENGTH - 2];
+    data[0..4].copy_from_slice(&edit.chunk_x.to_le_bytes());
+    data[4..8].copy_from_slice(&edit.chunk_z.to_le_bytes());
+    data[8..10].copy_from_slice(&edit.y.to_le_bytes());
+    data[10] = edit.x | (edit.z << 4);
+    data[12..14].copy_from_slice(&edit.block.to_le_bytes());
+    with_checksum(&data)
+}
+
+/// The edit in `record`, or `None` if it's erased or was only partly written.
+fn decode(record: &[u8; RECORD_LENGTH]) -> Option<BlockEdit> {
+    let data = checked(record)?;
+    Some(BlockEdit {
+        chunk_x: i32::from_le_bytes(data[0..4].try_into().unwrap()),
+        chunk_z: i32::from_le_bytes(data[4..8].try_into().unwrap()),
+        x: data[10] & 0x0f,
+        y: u16::from_le_bytes(data[8..10].try_into().unwrap()),
+        z: data[10] >> 4,
+        block: u16::from_le_bytes(data[12..14].try_into().unwrap()),
+    })
+}
+
+fn with_checksum(data: &[u8; RECORD_LENGTH - 2]) -> [u8; RECORD_LENGTH] {
+    let mut record = [0; RECORD_LENGTH];
+    record[..RECORD_LENGTH - 2].copy_from_slice(data);
+    record[RECORD_LENGTH - 2..].copy_from_slice(&crc16(data).to_le_bytes());
+    record
+}
+
+/// The data in `record`, if its checksum matches.
+fn checked(record: &[u8; RECORD_LENGTH]) -> Option<&[u8]> {
+    let (data, checksum) = record.split_at(RECORD_LENGTH - 2);
+    (crc16(data).to_le_bytes() == checksum).then_some(data)
+}
+
+/// CRC-16/CCITT-FALSE, which is enough to catch a record that lost power part way through.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xffff_u16;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
@@ -0,0 +1,453 @@
+//! Worlds made up as their chunks are sent, since the Pico has no room to keep one.
+//!
+//! Every block comes from the world's seed and where the block is, using nothing but integer
+//! maths, so a chunk is the same every time it's generated, on the Pico or anywhere else.
+
+use alloc::vec::Vec;
+use picocraft_protocol::{
+    chunk::{ChunkColumn, SECTION_SIZE},
+    types::Position,
+};
+
+use crate::{
+    blocks::{
+        BlockState, AIR, BEDROCK, DIRT, GRASS_BLOCK, OAK_LEAVES, OAK_LOG, SAND, STONE, WATER,
+    },
+    play::{MIN_Y, WORLD_HEIGHT},
+};
+
+/// Water fills generated terrain up to just below this height, like vanilla's sea level.
+pub const SEA_LEVEL: i32 = 63;
+
+/// Where players appear in a void world, where there's nothing to stand on.
+const VOID_SPAWN_HEIGHT: i32 = 64;
+
+/// How far the noise of each octave stretches, as a power of two, and how many blocks high it
+/// goes, from the broadest to the finest.
+const OCTAVES: [(u32, i32); 3] = [(7, 26), (5, 10), (3, 3)];
+
+/// The lowest the ground can be, where every octave is at its lowest.
+const BASE_HEIGHT: i32 = 45;
+
+/// Trees are spread out with at most one in each 8×8 cell, as a power of two.
+const TREE_CELL_SHIFT: u32 = 3;
+
+/// One in this many cells has a tree in it.
+const TREE_CHANCE: u32 = 3;
+
+/// How far leaves reach from a trunk, so how far past a chunk's edges to look for trees.
+const LEAF_RADIUS: i32 = 2;
+
+/// The tallest a tree gets above the ground, with the leaves on top.
+const TREE_HEIGHT: i32 = 7;
+
+/// Mixed into the seed so trees don't line up with the noise.
+const TREE_SALT: u64 = 0x7472_6565;
+
+/// Some number of the same block, stacked on the layers below.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layer {
+    pub block: BlockState,
+    pub thickness: u16,
+}
+
+const fn layer(block: BlockState, thickness: u16) -> Layer {
+    Layer { block, thickness }
+}
+
+/// Vanilla's default superflat preset: bedrock, two layers of dirt and grass.
+pub const CLASSIC_FLAT: &[Layer] = &[layer(BEDROCK, 1), layer(DIRT, 2), layer(GRASS_BLOCK, 1)];
+
+/// Vanilla's preset with 230 layers of stone to dig through.
+pub const TUNNELERS_DREAM: &[Layer] = &[
+    layer(BEDROCK, 1),
+    layer(STONE, 230),
+    layer(DIRT, 5),
+    layer(GRASS_BLOCK, 1),
+];
+
+/// Vanilla's preset with 90 layers of water over a sandy floor.
+pub const WATER_WORLD: &[Layer] = &[
+    layer(BEDROCK, 1),
+    layer(STONE, 5),
+    layer(DIRT, 5),
+    layer(SAND, 5),
+    layer(WATER, 90),
+];
+
+/// How the blocks of a world are picked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generator {
+    /// Nothing at all.
+    Void,
+    /// The same layers everywhere, from the bottom of the world up.
+    Superflat(&'static [Layer]),
+    /// Hills, beaches and sea made from value noise, with oak trees on the grass.
+    Terrain,
+}
+
+impl Generator {
+    /// The generator called `name`, which is `void`, `terrain` or one of the superflat presets:
+    /// `flat`, `tunnelers_dream` or `water_world`.
+    pub fn from_name(name: &str) -> Option<Generator> {
+        Some(match name {
+            "void" => Generator::Void,
+            "flat" => Generator::Superflat(CLASSIC_FLAT),
+            "tunnelers_dream" => Generator::Superflat(TUNNELERS_DREAM),
+            "water_world" => Generator::Superflat(WATER_WORLD),
+            "terrain" => Generator::Terrain,
+            _ => return None,
+        })
+    }
+}
+
+/// A generator and the seed it's given, which is all there is to a world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct World {
+    pub generator: Generator,
+    pub seed: u64,
+}
+
+impl Default for World {
+    fn default() -> World {
+        World {
+            generator: Generator::Terrain,
+            // Has to be picked at random, which needs a random number generator
+            seed: 0,
+        }
+    }
+}
+
+impl World {
+    /// Generates the chunk column at chunk coordinates `x`, `z`, all of it in `biome`.
+    pub fn chunk(&self, x: i32, z: i32, biome: u32) -> Chunk {
+        let mut chunk = Chunk {
+            generator: self.generator,
+            biome,
+            ground: [MIN_Y as i16; 256],
+            heights: [0; 256],
+            trees: Vec::new(),
+            edits: Vec::new(),
+        };
+
+        match self.generator {
+            Generator::Void => {}
+            Generator::Superflat(layers) => {
+                let height: u16 = layers.iter().map(|layer| layer.thickness).sum();
+                chunk.heights = [height; 256];
+            }
+            Generator::Terrain => {
+                let (min_x, min_z) = (x * SECTION_SIZE as i32, z * SECTION_SIZE as i32);
+                for (i, ground) in chunk.ground.iter_mut().enumerate() {
+                    *ground =
+                        ground_height(self.seed, min_x + column_x(i), min_z + column_z(i)) as i16;
+                }
+                chunk.trees = trees(self.seed, min_x, min_z);
+                // Leaves can hang over lower ground than the tree's
+                let tree_top = chunk
+                    .trees
+                    .iter()
+                    .map(|tree| tree.ground + TREE_HEIGHT)
+                    .max();
+
+                for i in 0..256 {
+                    let ground = chunk.ground[i] as i32;
+                    let bottom = (ground + 1).max(SEA_LEVEL);
+                    let top = (bottom..=tree_top.unwrap_or(MIN_Y))
+                        .rev()
+                        .find(|y| chunk.tree_block(column_x(i), *y, column_z(i)).is_some())
+                        .map_or(bottom, |y| y + 1);
+                    chunk.heights[i] = (top - MIN_Y) as u16;
+                }
+            }
+        }
+        chunk
+    }
+
+    /// Where players appear, on top of whatever is at 0, 0.
+    pub fn spawn(&self) -> Position {
+        let y = match self.generator {
+            Generator::Void => VOID_SPAWN_HEIGHT,
+            _ => self.chunk(0, 0, 0).height(0, 0) as i32 + MIN_Y,
+        };
+        Position { x: 0, y, z: 0 }
+    }
+}
+
+/// One chunk column of a [`World`], worked out when it's made so that each block is quick to
+/// look up while it's sent.
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    generator: Generator,
+    biome: u32,
+    /// The top block of the ground in each column, indexed in `z`, `x` order.
+    ground: [i16; 256],
+    /// What [`ChunkColumn::height`] returns for each column.
+    heights: [u16; 256],
+    /// Every tree close enough to have leaves in the chunk.
+    trees: Vec<Tree>,
+    /// Blocks players changed, by their index in `y`, `z`, `x` order, kept sorted.
+    edits: Vec<(u32, BlockState)>,
+}
+
+fn column_x(index: usize) -> i32 {
+    (index % SECTION_SIZE) as i32
+}
+
+fn column_z(index: usize) -> i32 {
+    (index / SECTION_SIZE) as i32
+}
+
+impl Chunk {
+    /// Changes the block at `x`, `y`, `z`, with `y` counting up from the bottom of the world.
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, block: BlockState) {
+        let i = z * SECTION_SIZE + x;
+        let index = (y * 256 + i) as u32;
+        match self.edits.binary_search_by_key(&index, |(index, _)| *index) {
+            Ok(found) => self.edits[found].1 = block,
+            Err(at) => self.edits.insert(at, (index, block)),
+        }
+
+        // Let the new block be seen, then drop back down to the highest block that isn't air
+        let mut height = (self.heights[i] as usize).max(y + 1);
+        self.heights[i] = height as u16;
+        while height > 0 && self.block(x, height - 1, z) == AIR as u32 {
+            height -= 1;
+        }
+        self.heights[i] = height as u16;
+    }
+
+    /// The log or leaves of a tree at `x`, `y`, `z`, with `y` in world coordinates and `x` and
+    /// `z` relative to the chunk.
+    fn tree_block(&self, x: i32, y: i32, z: i32) -> Option<BlockState> {
+        let mut found = None;
+        for tree in &self.trees {
+            match tree.block(x, y, z) {
+                // Trunks win over another tree's leaves
+                Some(OAK_LOG) => return Some(OAK_LOG),
+                Some(block) => found = Some(block),
+                None => {}
+            }
+        }
+        found
+    }
+}
+
+impl ChunkColumn for Chunk {
+    fn sections(&self) -> usize {
+        WORLD_HEIGHT as usize / SECTION_SIZE
+    }
+
+    fn block(&self, x: usize, y: usize, z: usize) -> u32 {
+        let i = z * SECTION_SIZE + x;
+        // Most of the world is air above everything, so that's checked first
+        if y >= self.heights[i] as usize {
+            return AIR as u32;
+        }
+        if !self.edits.is_empty() {
+            let index = (y * 256 + i) as u32;
+            if let Ok(found) = self.edits.binary_search_by_key(&index, |(index, _)| *index) {
+                return self.edits[found].1 as u32;
+            }
+        }
+
+        let block = match self.generator {
+            Generator::Void => AIR,
+            Generator::Superflat(layers) => {
+                let mut bottom = 0;
+                let mut block = AIR;
+                for layer in layers {
+                    bottom += layer.thickness as usize;
+                    if y < bottom {
+                        block = layer.block;
+                        break;
+                    }
+                }
+                block
+            }
+            Generator::Terrain => {
+                let y = y as i32 + MIN_Y;
+                let ground = self.ground[i] as i32;
+                // Beaches and the sea floor are sand
+                let sandy = ground <= SEA_LEVEL;
+                if y > ground {
+                    if y < SEA_LEVEL {
+                        WATER
+                    } else {
+                        self.tree_block(x as i32, y, z as i32).unwrap_or(AIR)
+                    }
+                } else if y == MIN_Y {
+                    BEDROCK
+                } else if y == ground {
+                    if sandy {
+                        SAND
+                    } else {
+                        GRASS_BLOCK
+                    }
+                } else if y > ground - 4 {
+                    if sandy {
+                        SAND
+                    } else {
+                        DIRT
+                    }
+                } else {
+                    STONE
+                }
+            }
+        };
+        block as u32
+    }
+
+    fn biome(&self, _x: usize, _y: usize, _z: usize) -> u32 {
+        self.biome
+    }
+
+    fn height(&self, x: usize, z: usize) -> usize {
+        self.heights[z * SECTION_SIZE + x] as usize
+    }
+
+    fn sky_light(&self, x: usize, y: i32, z: usize) -> u8 {
+        if self.generator != Generator::Terrain {
+            return if y >= self.height(x, z) as i32 { 15 } else { 0 };
+        }
+
+        // Leaves let the light through, but it fades a level for each block of water
+        let y = y + MIN_Y;
+        if y <= self.ground[z * SECTION_SIZE + x] as i32 {
+            0
+        } else {
+            15 - (SEA_LEVEL - y).clamp(0, 15) as u8
+        }
+    }
+}
+
+/// An oak tree, with its trunk at `x`, `z` relative to the chunk it's in.
+#[derive(Debug, Clone, Copy)]
+struct Tree {
+    x: i32,
+    z: i32,
+    /// The block the tree grows out of.
+    ground: i32,
+    /// How many logs tall the trunk is.
+    trunk: i32,
+    /// Which corners of the leaves are left out, four bits for each of the lower three layers.
+    corners: u16,
+}
+
+impl Tree {
+    /// Like vanilla's oak trees, two layers of leaves around the top of the trunk, then two
+    /// narrower layers, with the corners left out of the top one and some of the others.
+    fn block(&self, x: i32, y: i32, z: i32) -> Option<BlockState> {
+        let (dx, dy, dz) = (x - self.x, y - self.ground, z - self.z);
+        if dx == 0 && dz == 0 && (1..=self.trunk).contains(&dy) {
+            return Some(OAK_LOG);
+        }
+
+        let layer = dy - self.trunk + 2;
+        let radius = match layer {
+            0 | 1 => 2,
+            2 | 3 => 1,
+            _ => return None,
+        };
+        if dx.abs() > radius || dz.abs() > radius {
+            return None;
+        }
+        if dx.abs() == radius && dz.abs() == radius {
+            let corner = (dx > 0) as i32 * 2 + (dz > 0) as i32;
+            if layer == 3 || self.corners & (1 << (layer * 4 + corner)) != 0 {
+                return None;
+            }
+        }
+        Some(OAK_LEAVES)
+    }
+}
+
+/// The trees with leaves in the chunk starting at `min_x`, `min_z`.
+fn trees(seed: u64, min_x: i32, min_z: i32) -> Vec<Tree> {
+    let size = SECTION_SIZE as i32;
+    let cells = |min: i32| {
+        (min - LEAF_RADIUS) >> TREE_CELL_SHIFT..=(min + size - 1 + LEAF_RADIUS) >> TREE_CELL_SHIFT
+    };
+
+    let mut trees = Vec::new();
+    for cell_x in cells(min_x) {
+        for cell_z in cells(min_z) {
+            let hash = hash(seed ^ TREE_SALT, cell_x, cell_z);
+            if !hash.is_multiple_of(TREE_CHANCE) {
+                continue;
+            }
+            let mask = (1 << TREE_CELL_SHIFT) - 1;
+            let x = (cell_x << TREE_CELL_SHIFT) + ((hash >> 8) as i32 & mask);
+            let z = (cell_z << TREE_CELL_SHIFT) + ((hash >> 11) as i32 & mask);
+            if !(min_x - LEAF_RADIUS..min_x + size + LEAF_RADIUS).contains(&x)
+                || !(min_z - LEAF_RADIUS..min_z + size + LEAF_RADIUS).contains(&z)
+            {
+                continue;
+            }
+
+            // Only on grass, so not on beaches or under the sea
+            let ground = ground_height(seed, x, z);
+            if ground <= SEA_LEVEL {
+                continue;
+            }
+            trees.push(Tree {
+                x: x - min_x,
+                z: z - min_z,
+                ground,
+                trunk: 4 + (hash >> 14) as i32 % 3,
+                corners: (hash >> 16) as u16 & 0xfff,
+            });
+        }
+    }
+    trees
+}
+
+/// The top block of the ground at `x`, `z`, from a few octaves of value noise.
+fn ground_height(seed: u64, x: i32, z: i32) -> i32 {
+    let hills: i32 = OCTAVES
+        .iter()
+        .enumerate()
+        .map(|(octave, &(shift, amplitude))| {
+            (value_noise(seed.wrapping_add(octave as u64), x, z, shift) * amplitude) >> 16
+        })
+        .sum();
+    BASE_HEIGHT + hills
+}
+
+/// Smoothly interpolates between random values at the corners of a grid `1 << shift` blocks
+/// across, giving a value from 0 to 65535.
+fn value_noise(seed: u64, x: i32, z: i32, shift: u32) -> i32 {
+    let (cell_x, cell_z) = (x >> shift, z >> shift);
+    let mask = (1 << shift) - 1;
+    // How far across the cell, as 16 bit fractions
+    let (across_x, across_z) = (
+        smoothstep((x & mask) << (16 - shift)),
+        smoothstep((z & mask) << (16 - shift)),
+    );
+    let corner = |dx: i32, dz: i32| (hash(seed, cell_x + dx, cell_z + dz) >> 16) as i32;
+
+    let top = lerp(corner(0, 0), corner(1, 0), across_x);
+    let bottom = lerp(corner(0, 1), corner(1, 1), across_x);
+    lerp(top, bottom, across_z)
+}
+
+/// Eases `t` in and out, `3t² - 2t³` with 16 bit fractions.
+fn smoothstep(t: i32) -> i32 {
+    let t = t as i64;
+    ((t * t * (3 * 65536 - 2 * t)) >> 32) as i32
+}
+
+fn lerp(a: i32, b: i32, t: i32) -> i32 {
+    a + (((b - a) as i64 * t as i64) >> 16) as i32
+}
+
+/// Mixes `seed` and a position into 32 random looking bits, with SplitMix64's finaliser.
+fn hash(seed: u64, x: i32, z: i32) -> u32 {
+    let mix = |mut h: u64| {
+        h = (h ^ h >> 30).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        h = (h ^ h >> 27).wrapping_mul(0x94d0_49bb_1331_11eb);
+        h ^ h >> 31
+    };
+    let h = mix(seed ^ (x as u32 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    (mix(h ^ (z as u32 as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)) >> 32) as u32
+}
@@ -0,0 +1,232 @@
+mod common;
+
+use common::{run, run_with_game, test_config, MockSession};
+use picocraft_protocol::error::ProtocolError;
+use picocraft_server::{game::Game, store::MemoryStore};
+
+// Protocol 767, "localhost", port 25565, next state Status
+const HANDSHAKE: &[u8] = &[
+    0x10, 0x00, 0xff, 0x05, 0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't', 0x63, 0xdd,
+    0x01,
+];
+const STATUS_REQUEST: &[u8] = &[0x01, 0x00];
+const PING_REQUEST: &[u8] = &[0x09, 0x01, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+const PONG_RESPONSE: &[u8] = &[0x09, 0x01, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+
+const STATUS_JSON: &str = concat!(
+    r#"{"version":{"name":"1.21.1","protocol":767},"#,
+    r#""players":{"max":4,"online":0,"sample":null},"#,
+    r#""description":{"text":"","extra":[{"text":"A "},{"text":"PicoCraft","color":"green"},{"text":" server."}]},"#,
+    r#""favicon":null,"enforcesSecureChat":false}"#
+);
+
+fn status_response() -> Vec<u8> {
+    status_response_with(STATUS_JSON)
+}
+
+fn status_response_with(json: &str) -> Vec<u8> {
+    assert_eq!(json.len(), 238);
+    // Frame length 241, packet id 0x00, string length 238
+    let mut bytes = vec![0xf1, 0x01, 0x00, 0xee, 0x01];
+    bytes.extend_from_slice(json.as_bytes());
+    bytes
+}
+
+#[test]
+fn status_then_ping() {
+    let result = run(|mut client| async move {
+        client.send(HANDSHAKE).await;
+        client.send(STATUS_REQUEST).await;
+        client.expect(&status_response()).await;
+
+        client.send(PING_REQUEST).await;
+        client.expect(PONG_RESPONSE).await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn counts_players_online() {
+    let game = &Game::new(MemoryStore::new());
+    let _first = game.join().unwrap();
+    let second = game.join().unwrap();
+    drop(second);
+
+    let json = STATUS_JSON.replace(r#""online":0"#, r#""online":1"#);
+    let result = run_with_game(
+        &test_config(),
+        &mut MockSession::default(),
+        game,
+        |mut client| async move {
+            client.send(HANDSHAKE).await;
+            client.send(STATUS_REQUEST).await;
+            client.expect(&status_response_with(&json)).await;
+        },
+    );
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn answers_status_for_unsupported_versions() {
+    // Protocol 47, which is 1.8
+    let handshake = [
+        0x0f, 0x00, 0x2f, 0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't', 0x63, 0xdd,
+        0x01,
+    ];
+
+    let result = run(|mut client| async move {
+        client.send(&handshake).await;
+        client.send(STATUS_REQUEST).await;
+        // With our own version, which the client shows as incompatible
+        client.expect(&status_response()).await;
+
+        client.send(PING_REQUEST).await;
+        client.expect(PONG_RESPONSE).await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn frames_split_across_reads() {
+    let result = run(|mut client| async move {
+        client.trickle(HANDSHAKE).await;
+        client.trickle(STATUS_REQUEST).await;
+        client.expect(&status_response()).await;
+
+        client.trickle(PING_REQUEST).await;
+        client.expect(PONG_RESPONSE).await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn frames_coalesced_into_one_read() {
+    let result = run(|mut client| async move {
+        client
+            .send(&[HANDSHAKE, STATUS_REQUEST, PING_REQUEST].concat())
+            .await;
+        client.expect(&status_response()).await;
+        client.expect(PONG_RESPONSE).await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn oversized_length_closes_connection() {
+    let result = run(|mut client| async move {
+        client.send(&[0xff, 0xff, 0xff, 0x7f]).await;
+        client.expect_closed().await;
+    });
+
+    assert_eq!(result, Err(ProtocolError::PacketTooLong(0x0fff_ffff)));
+}
+
+#[test]
+fn overlong_length_closes_connection() {
+    let result = run(|mut client| async move {
+        client.send(&[0xff; 6]).await;
+        client.expect_closed().await;
+    });
+
+    assert_eq!(result, Err(ProtocolError::VarIntTooLong));
+}
+
+#[test]
+fn ping_before_handshake_closes_connection() {
+    let result = run(|mut client| async move {
+        client.send(PING_REQUEST).await;
+        client.expect_closed().await;
+    });
+
+    assert_eq!(result, Err(ProtocolError::UnknownPacketId(0x01)));
+}
+
+#[test]
+fn unknown_status_packet_closes_connection() {
+    let result = run(|mut client| async move {
+        client.send(HANDSHAKE).await;
+        client.send(&[0x01, 0x05]).await;
+        client.expect_closed().await;
+    });
+
+    assert_eq!(result, Err(ProtocolError::UnknownPacketId(0x05)));
+}
+
+#[test]
+fn disconnect_between_packets_is_clean() {
+    let result = run(|mut client| async move {
+        client.send(HANDSHAKE).await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn disconnect_inside_frame() {
+    let result = run(|mut client| async move {
+        client.send(&HANDSHAKE[..8]).await;
+    });
+
+    assert_eq!(result, Err(ProtocolError::UnexpectedEof));
+}
+
+#[test]
+fn disconnect_before_response() {
+    let result = run(|mut client| async move {
+        client.send(HANDSHAKE).await;
+        client.send(STATUS_REQUEST).await;
+    });
+
+    assert_eq!(
+        result,
+        Err(ProtocolError::Io(embedded_io_async::ErrorKind::BrokenPipe))
+    );
+}
+
+fn legacy_kick(reason: &str) -> Vec<u8> {
+    let mut bytes = vec![0xff];
+    bytes.extend_from_slice(&(reason.encode_utf16().count() as u16).to_be_bytes());
+    for unit in reason.encode_utf16() {
+        bytes.extend_from_slice(&unit.to_be_bytes());
+    }
+    bytes
+}
+
+#[test]
+fn answers_beta_legacy_ping() {
+    let result = run(|mut client| async move {
+        client.send(&[0xfe]).await;
+        client.expect(&legacy_kick("A PicoCraft server.§0§4")).await;
+        client.expect_closed().await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn answers_legacy_ping_from_1_6() {
+    // 0xFE 0x01, then MC|PingHost for protocol 78, "localhost", port 25565
+    let mut ping = vec![0xfe, 0x01, 0xfa, 0x00, 0x0b];
+    for unit in "MC|PingHost".encode_utf16() {
+        ping.extend_from_slice(&unit.to_be_bytes());
+    }
+    ping.extend_from_slice(&[0x00, 0x16, 0x4e, 0x00, 0x09]);
+    for unit in "localhost".encode_utf16() {
+        ping.extend_from_slice(&unit.to_be_bytes());
+    }
+    ping.extend_from_slice(&25565i32.to_be_bytes());
+
+    let result = run(|mut client| async move {
+        client.send(&ping).await;
+        let reason = "§1\x00127\x001.21.1\x00A §aPicoCraft§r server.\x000\x004";
+        client.expect(&legacy_kick(reason)).await;
+        client.expect_closed().await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
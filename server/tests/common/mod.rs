@@ -0,0 +1,373 @@
+//! An in-memory connection for driving the server the way a real client would.
+
+// Not every test file uses every helper
+#![allow(dead_code)]
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    future::{poll_fn, Future},
+    rc::Rc,
+    sync::OnceLock,
+    task::{Poll, Waker},
+};
+
+use embassy_futures::{block_on, join::join};
+use embedded_io_async::{ErrorKind, ErrorType, Read, Write};
+use picocraft_protocol::{
+    encryption::{CipherStream, SECRET_LENGTH},
+    error::ProtocolError,
+    frame::write_frame,
+    packets::{Packet, PacketInfo, WritePacket, PROTOCOL_VERSION},
+    read::{ReadExtension, Slice},
+};
+use picocraft_server::{
+    encryption::ServerKey, game::Game, serve, session::Connect, store::MemoryStore, Config,
+};
+use rand_core::{CryptoRng, RngCore};
+use rsa::{pkcs8::DecodePublicKey, Pkcs1v15Encrypt, RsaPublicKey};
+
+/// Bytes going one way, waiting to be read.
+#[derive(Default)]
+struct Pipe {
+    buf: VecDeque<u8>,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+/// One end of an in-memory connection. Dropping it closes the connection.
+pub struct End {
+    incoming: Rc<RefCell<Pipe>>,
+    outgoing: Rc<RefCell<Pipe>>,
+}
+
+/// Creates a connection, returning its two ends.
+pub fn duplex() -> (End, End) {
+    let a = Rc::new(RefCell::new(Pipe::default()));
+    let b = Rc::new(RefCell::new(Pipe::default()));
+    (
+        End {
+            incoming: a.clone(),
+            outgoing: b.clone(),
+        },
+        End {
+            incoming: b,
+            outgoing: a,
+        },
+    )
+}
+
+impl End {
+    /// Closes both directions, so the other end reads EOF once it has read everything sent.
+    pub fn close(&mut self) {
+        for pipe in [&self.incoming, &self.outgoing] {
+            let mut pipe = pipe.borrow_mut();
+            pipe.closed = true;
+            if let Some(waker) = pipe.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl Drop for End {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl ErrorType for End {
+    type Error = ErrorKind;
+}
+
+impl Read for End {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        poll_fn(|cx| {
+            let mut pipe = self.incoming.borrow_mut();
+            if !pipe.buf.is_empty() || buf.is_empty() {
+                let len = buf.len().min(pipe.buf.len());
+                for (dst, src) in buf.iter_mut().zip(pipe.buf.drain(..len)) {
+                    *dst = src;
+                }
+                Poll::Ready(Ok(len))
+            } else if pipe.closed {
+                Poll::Ready(Ok(0))
+            } else {
+                pipe.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+impl Write for End {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let mut pipe = self.outgoing.borrow_mut();
+        if pipe.closed {
+            return Err(ErrorKind::BrokenPipe);
+        }
+
+        pipe.buf.extend(buf);
+        if let Some(waker) = pipe.waker.take() {
+            waker.wake();
+        }
+        Ok(buf.len())
+    }
+}
+
+/// A generator that gives the same numbers every run, so tests are repeatable.
+pub struct TestRng(u64);
+
+impl TestRng {
+    pub fn new() -> TestRng {
+        TestRng(0x9e37_79b9_7f4a_7c15)
+    }
+}
+
+impl RngCore for TestRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        // xorshift64
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// Not really, but nothing in the tests needs to be secret
+impl CryptoRng for TestRng {}
+
+/// The key pair every test uses, since generating one takes a few seconds in debug builds.
+pub fn test_key() -> ServerKey {
+    static KEY: OnceLock<ServerKey> = OnceLock::new();
+    KEY.get_or_init(|| ServerKey::generate(&mut TestRng::new()).unwrap())
+        .clone()
+}
+
+/// A session server that gives the same answer to every request.
+#[derive(Default)]
+pub struct MockSession {
+    /// Sent back whole for every request, or `None` to refuse connections.
+    pub response: Option<Vec<u8>>,
+    /// The host and port of every connection, and what was sent on it.
+    pub requests: Vec<(String, u16, Vec<u8>)>,
+}
+
+impl Connect for MockSession {
+    type Connection<'a> = MockConnection<'a>;
+
+    async fn connect(&mut self, host: &str, port: u16) -> Result<MockConnection<'_>, ErrorKind> {
+        if self.response.is_none() {
+            return Err(ErrorKind::ConnectionRefused);
+        }
+        self.requests.push((host.to_string(), port, Vec::new()));
+        Ok(MockConnection {
+            session: self,
+            read: 0,
+        })
+    }
+}
+
+pub struct MockConnection<'a> {
+    session: &'a mut MockSession,
+    read: usize,
+}
+
+impl ErrorType for MockConnection<'_> {
+    type Error = ErrorKind;
+}
+
+impl Read for MockConnection<'_> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let response = &self.session.response.as_ref().unwrap()[self.read..];
+        let len = buf.len().min(response.len());
+        buf[..len].copy_from_slice(&response[..len]);
+        self.read += len;
+        Ok(len)
+    }
+}
+
+impl Write for MockConnection<'_> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let (_, _, request) = self.session.requests.last_mut().unwrap();
+        request.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+}
+
+/// The client side of a connection, with helpers for scripting it.
+pub struct Client(pub CipherStream<End>);
+
+impl Client {
+    /// Encrypts everything after this, like the client does once it sends Encryption Response.
+    pub fn encrypt(&mut self, secret: &[u8; SECRET_LENGTH]) {
+        self.0.enable(secret);
+    }
+
+    /// Reads one uncompressed frame, leaving anything after it to be read.
+    pub async fn read_frame(&mut self) -> Packet {
+        let length = self.0.read_varint().await.unwrap();
+        let mut body = vec![0; length as usize];
+        self.0.read_exact(&mut body).await.unwrap();
+
+        let mut data = Slice::new(body.into_boxed_slice());
+        let id = data.read_varint().await.unwrap();
+        Packet { id, data }
+    }
+
+    /// Reads everything the server sends on joining, up to the player's position, returning the
+    /// ids of the packets and the teleport id the client has to confirm.
+    pub async fn join(&mut self) -> (Vec<i32>, i32) {
+        let mut ids = Vec::new();
+        loop {
+            let mut packet = self.read_frame().await;
+            ids.push(packet.id);
+            // Synchronize Player Position
+            if packet.id == 0x40 {
+                let data = &mut packet.data;
+                // Skip the coordinates, rotation and flags
+                data.read_byte_array(8 * 3 + 4 * 2 + 1).await.unwrap();
+                return (ids, data.read_varint().await.unwrap());
+            }
+        }
+    }
+
+    pub async fn send(&mut self, bytes: &[u8]) {
+        self.0.write_all(bytes).await.unwrap();
+    }
+
+    /// Sends `bytes` one at a time, letting the server run in between.
+    pub async fn trickle(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.send(&[*byte]).await;
+            embassy_futures::yield_now().await;
+        }
+    }
+
+    /// Reads exactly as many bytes as `expected` and checks they match.
+    pub async fn expect(&mut self, expected: &[u8]) {
+        let mut buf = vec![0; expected.len()];
+        self.0.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected);
+    }
+
+    /// Reads and throws away `length` bytes, for responses another test already checks.
+    pub async fn expect_any(&mut self, length: usize) {
+        let mut buf = vec![0; length];
+        self.0.read_exact(&mut buf).await.unwrap();
+    }
+
+    /// Reads the Encryption Request, returning the server's public key, the verify token and
+    /// whether the client should authenticate.
+    pub async fn encryption_request(&mut self) -> (RsaPublicKey, Vec<u8>, bool) {
+        let mut packet = self.read_frame().await;
+        assert_eq!(packet.id, 0x01);
+
+        let data = &mut packet.data;
+        assert_eq!(data.read_string().await.unwrap(), "");
+        let key = data.read_prefixed_byte_array().await.unwrap();
+        let token = data.read_prefixed_byte_array().await.unwrap();
+        let authenticate = data.read_bool().await.unwrap();
+        let key = RsaPublicKey::from_public_key_der(&key).unwrap();
+        (key, token, authenticate)
+    }
+
+    /// Sends an Encryption Response with `secret` and `token` encrypted with `key`, but doesn't
+    /// start encrypting.
+    pub async fn encryption_response(&mut self, key: &RsaPublicKey, secret: &[u8], token: &[u8]) {
+        let mut rng = TestRng::new();
+        let secret = key.encrypt(&mut rng, Pkcs1v15Encrypt, secret).unwrap();
+        let token = key.encrypt(&mut rng, Pkcs1v15Encrypt, token).unwrap();
+
+        let mut body = vec![0x01];
+        for array in [secret, token] {
+            // Both are 128 bytes, which takes two bytes as a VarInt
+            body.extend_from_slice(&[0x80, 0x01]);
+            body.extend(array);
+        }
+        let mut frame = vec![body.len() as u8 | 0x80, (body.len() >> 7) as u8];
+        frame.extend(body);
+        self.send(&frame).await;
+    }
+
+    /// Checks that the server closed the connection without sending anything else.
+    pub async fn expect_closed(&mut self) {
+        let mut buf = [0; 64];
+        let read = self.0.read(&mut buf).await.unwrap();
+        assert_eq!(
+            &buf[..read],
+            &[] as &[u8],
+            "expected the connection to close"
+        );
+    }
+}
+
+/// The settings [`run`] uses.
+///
+/// Compression and encryption are turned off so the expected bytes can be written out by hand.
+pub fn test_config() -> Config {
+    Config {
+        compression_threshold: None,
+        ..Config::default()
+    }
+}
+
+/// Runs the server against a scripted client, returning how the server finished.
+///
+/// The client's end is closed once the script returns.
+pub fn run<F: Future<Output = ()>>(script: impl FnOnce(Client) -> F) -> Result<(), ProtocolError> {
+    run_with(&test_config(), &mut MockSession::default(), script)
+}
+
+/// Like [`run`], but with the server set up by `config` and checking players with `session`.
+pub fn run_with<F: Future<Output = ()>>(
+    config: &Config,
+    session: &mut MockSession,
+    script: impl FnOnce(Client) -> F,
+) -> Result<(), ProtocolError> {
+    run_with_game(config, session, &Game::new(MemoryStore::new()), script)
+}
+
+/// Like [`run_with`], but joining `game`, which only ticks when the script ticks it.
+pub fn run_with_game<F: Future<Output = ()>>(
+    config: &Config,
+    session: &mut MockSession,
+    game: &Game<MemoryStore>,
+    script: impl FnOnce(Client) -> F,
+) -> Result<(), ProtocolError> {
+    let (mut server, client) = duplex();
+
+    let server = async {
+        let result = serve(&mut server, config, game, &mut TestRng::new(), session).await;
+        server.close();
+        result
+    };
+    let (result, ()) = block_on(join(server, script(Client(CipherStream::new(client)))));
+    result
+}
+
+/// Encodes `packet` as a whole frame, for comparing against what the server sent.
+pub async fn frame<P: WritePacket + PacketInfo>(packet: &P) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_frame(&mut buf, PROTOCOL_VERSION, packet)
+        .await
+        .unwrap();
+    buf
+}
@@ -0,0 +1,282 @@
+mod common;
+
+use common::{frame, run, run_with_game, test_config, Client, MockSession};
+use picocraft_protocol::error::ProtocolError;
+use picocraft_protocol::packets::configuration::{
+    ClientboundKnownPacks, FinishConfiguration, KnownPack, RegistryData, UpdateTags,
+};
+use picocraft_protocol::packets::play::StartConfiguration;
+use picocraft_protocol::types::StringList;
+use picocraft_server::{
+    game::Game,
+    registries::{self, BIOME, DIMENSION_TYPE, REGISTRIES},
+    store::MemoryStore,
+};
+
+// Protocol 767, "localhost", port 25565, next state Login
+const HANDSHAKE: &[u8] = &[
+    0x10, 0x00, 0xff, 0x05, 0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't', 0x63, 0xdd,
+    0x02,
+];
+const LOGIN_START: &[u8] = &[
+    0x17, 0x00, 0x05, b'N', b'o', b't', b'c', b'h', 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+    0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+];
+const LOGIN_SUCCESS_LENGTH: usize = 0x19;
+const LOGIN_ACKNOWLEDGED: &[u8] = &[0x01, 0x03];
+
+// minecraft:brand, "PicoCraft"
+const BRAND: &[u8] = &[
+    0x1b, 0x01, 0x0f, b'm', b'i', b'n', b'e', b'c', b'r', b'a', b'f', b't', b':', b'b', b'r', b'a',
+    b'n', b'd', 0x09, b'P', b'i', b'c', b'o', b'C', b'r', b'a', b'f', b't',
+];
+// [minecraft:vanilla]
+const FEATURE_FLAGS: &[u8] = &[
+    0x14, 0x0c, 0x01, 0x11, b'm', b'i', b'n', b'e', b'c', b'r', b'a', b'f', b't', b':', b'v', b'a',
+    b'n', b'i', b'l', b'l', b'a',
+];
+// [minecraft:core:1.21.1]
+const KNOWN_PACKS: &[u8] = &[
+    0x18, 0x0e, 0x01, 0x09, b'm', b'i', b'n', b'e', b'c', b'r', b'a', b'f', b't', 0x04, b'c', b'o',
+    b'r', b'e', 0x06, b'1', b'.', b'2', b'1', b'.', b'1',
+];
+
+// en_us, view distance 8, full chat with colours, all skin parts, right handed
+const CLIENT_INFORMATION: &[u8] = &[
+    0x0e, 0x00, 0x05, b'e', b'n', b'_', b'u', b's', 0x08, 0x00, 0x01, 0x7f, 0x01, 0x00, 0x01,
+];
+// minecraft:brand, "vanilla"
+const CLIENT_BRAND: &[u8] = &[
+    0x19, 0x02, 0x0f, b'm', b'i', b'n', b'e', b'c', b'r', b'a', b'f', b't', b':', b'b', b'r', b'a',
+    b'n', b'd', 0x07, b'v', b'a', b'n', b'i', b'l', b'l', b'a',
+];
+const ACKNOWLEDGE_FINISH: &[u8] = &[0x01, 0x03];
+const CONFIGURATION_ACKNOWLEDGED: &[u8] = &[0x01, 0x0c];
+
+fn known_packs(version: &str) -> Vec<u8> {
+    let mut body = vec![0x07, 0x01, 0x09];
+    body.extend_from_slice(b"minecraft");
+    body.push(0x04);
+    body.extend_from_slice(b"core");
+    body.push(version.len() as u8);
+    body.extend_from_slice(version.as_bytes());
+
+    let mut frame = vec![body.len() as u8];
+    frame.extend(body);
+    frame
+}
+
+/// Logs in and waits for the server to start configuring.
+async fn log_in(client: &mut Client) {
+    client.send(HANDSHAKE).await;
+    client.send(LOGIN_START).await;
+    // The login success is covered by the login tests
+    client.expect_any(LOGIN_SUCCESS_LENGTH + 1).await;
+    client.send(LOGIN_ACKNOWLEDGED).await;
+    expect_start(client).await;
+}
+
+async fn expect_start(client: &mut Client) {
+    client.expect(BRAND).await;
+    client.expect(FEATURE_FLAGS).await;
+    client.expect(KNOWN_PACKS).await;
+}
+
+async fn expect_finish(client: &mut Client) {
+    expect_registries(client, REGISTRIES).await;
+}
+
+async fn expect_registries(client: &mut Client, registries: &[(&str, StringList<'_>)]) {
+    for &(registry, entries) in registries {
+        client
+            .expect(&frame(&RegistryData { registry, entries }).await)
+            .await;
+    }
+    let tags = UpdateTags {
+        registries: Vec::new(),
+    };
+    client.expect(&frame(&tags).await).await;
+    client.expect(&frame(&FinishConfiguration).await).await;
+}
+
+#[test]
+fn configures_client() {
+    let result = run(|mut client| async move {
+        log_in(&mut client).await;
+        client.send(CLIENT_INFORMATION).await;
+        client.send(CLIENT_BRAND).await;
+        client.send(&known_packs("1.21.1")).await;
+        expect_finish(&mut client).await;
+        client.send(ACKNOWLEDGE_FINISH).await;
+        // Joining is covered by the play tests
+        client.join().await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn configures_older_client() {
+    let result = run(|mut client| async move {
+        log_in_766(&mut client).await;
+
+        client.send(&known_packs("1.20.6")).await;
+        // Without the registries 1.21 added
+        let registries = registries::for_version(766);
+        assert_eq!(registries.len(), 8);
+        expect_registries(&mut client, registries).await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+/// Logs in with the same handshake, but for protocol 766, up to the packs the server knows.
+async fn log_in_766(client: &mut Client) {
+    let mut handshake = HANDSHAKE.to_vec();
+    handshake[2..4].copy_from_slice(&[0xfe, 0x05]);
+    client.send(&handshake).await;
+    client.send(LOGIN_START).await;
+    client.expect_any(LOGIN_SUCCESS_LENGTH + 1).await;
+    client.send(LOGIN_ACKNOWLEDGED).await;
+    client.expect(BRAND).await;
+    client.expect(FEATURE_FLAGS).await;
+    let packs = ClientboundKnownPacks {
+        packs: vec![KnownPack::core("1.20.6")],
+    };
+    client.expect(&frame(&packs).await).await;
+}
+
+#[test]
+fn kicks_1_20_5_client() {
+    let result = run(|mut client| async move {
+        log_in_766(&mut client).await;
+        client.send(&known_packs("1.20.5")).await;
+
+        // NBT string
+        let reason =
+            b"This server needs a vanilla 1.20.6 client, not another version with the same protocol";
+        let mut disconnect = vec![reason.len() as u8 + 4, 0x02, 0x08, 0x00, reason.len() as u8];
+        disconnect.extend_from_slice(reason);
+        client.expect(&disconnect).await;
+        client.expect_closed().await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn rejects_finish_before_it_was_sent() {
+    let result = run(|mut client| async move {
+        log_in(&mut client).await;
+        // The registries haven't been sent yet
+        client.send(ACKNOWLEDGE_FINISH).await;
+    });
+
+    assert_eq!(result, Err(ProtocolError::UnknownPacketId(0x03)));
+}
+
+#[test]
+fn kicks_client_without_core_pack() {
+    let result = run(|mut client| async move {
+        log_in(&mut client).await;
+        client.send(&known_packs("1.21")).await;
+
+        // NBT string
+        let reason =
+            b"This server needs a vanilla 1.21.1 client, not another version with the same protocol";
+        let mut disconnect = vec![reason.len() as u8 + 4, 0x02, 0x08, 0x00, reason.len() as u8];
+        disconnect.extend_from_slice(reason);
+        client.expect(&disconnect).await;
+        client.expect_closed().await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn reconfigures_from_play() {
+    let game = &Game::new(MemoryStore::new());
+    let result = run_with_game(
+        &test_config(),
+        &mut MockSession::default(),
+        game,
+        |mut client| async move {
+            log_in(&mut client).await;
+            client.send(&known_packs("1.21.1")).await;
+            expect_finish(&mut client).await;
+            client.send(ACKNOWLEDGE_FINISH).await;
+            client.join().await;
+
+            game.reconfigure();
+            client.expect(&frame(&StartConfiguration).await).await;
+            client.send(CONFIGURATION_ACKNOWLEDGED).await;
+            expect_start(&mut client).await;
+        },
+    );
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn rejoins_after_reconfiguring() {
+    let game = &Game::new(MemoryStore::new());
+    let result = run_with_game(
+        &test_config(),
+        &mut MockSession::default(),
+        game,
+        |mut client| async move {
+            log_in(&mut client).await;
+            client.send(&known_packs("1.21.1")).await;
+            expect_finish(&mut client).await;
+            client.send(ACKNOWLEDGE_FINISH).await;
+            client.join().await;
+
+            game.reconfigure();
+            client.expect(&frame(&StartConfiguration).await).await;
+            client.send(CONFIGURATION_ACKNOWLEDGED).await;
+            expect_start(&mut client).await;
+            client.send(&known_packs("1.21.1")).await;
+            expect_finish(&mut client).await;
+            client.send(ACKNOWLEDGE_FINISH).await;
+            client.join().await;
+        },
+    );
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn rejects_configuration_acknowledged_unless_asked() {
+    let result = run(|mut client| async move {
+        log_in(&mut client).await;
+        client.send(&known_packs("1.21.1")).await;
+        expect_finish(&mut client).await;
+        client.send(ACKNOWLEDGE_FINISH).await;
+        client.join().await;
+
+        client.send(CONFIGURATION_ACKNOWLEDGED).await;
+    });
+
+    assert_eq!(result, Err(ProtocolError::UnknownPacketId(0x0c)));
+}
+
+#[test]
+fn numbers_registry_entries() {
+    assert_eq!(REGISTRIES.len(), 11);
+    assert_eq!(BIOME.len(), 64);
+    assert_eq!(registries::id_of(BIOME, "minecraft:plains"), Some(39));
+    assert_eq!(
+        registries::id_of(DIMENSION_TYPE, "minecraft:overworld"),
+        Some(0)
+    );
+    assert_eq!(registries::id_of(DIMENSION_TYPE, "minecraft:void"), None);
+
+    assert_eq!(registries::for_version(767), REGISTRIES);
+    let older = registries::for_version(766);
+    let (_, trims) = older
+        .iter()
+        .find(|(registry, _)| *registry == "minecraft:trim_pattern")
+        .unwrap();
+    assert_eq!(trims.position("minecraft:bolt"), None);
+    assert_eq!(trims.position("minecraft:coast"), Some(0));
+}
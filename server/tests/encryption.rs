@@ -0,0 +1,96 @@
+mod common;
+
+use common::{run_with, test_config, test_key, MockSession};
+use picocraft_protocol::error::ProtocolError;
+use picocraft_server::Config;
+
+// Protocol 767, "localhost", port 25565, next state Login
+const HANDSHAKE: &[u8] = &[
+    0x10, 0x00, 0xff, 0x05, 0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't', 0x63, 0xdd,
+    0x02,
+];
+// "Notch" and a UUID the server ignores
+const LOGIN_START: &[u8] = &[
+    0x17, 0x00, 0x05, b'N', b'o', b't', b'c', b'h', 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+    0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+];
+const SECRET: [u8; 16] = *b"sixteen byte key";
+
+fn config() -> Config {
+    Config {
+        key: Some(test_key()),
+        ..test_config()
+    }
+}
+
+#[test]
+fn encrypts_after_encryption_response() {
+    let result = run_with(
+        &config(),
+        &mut MockSession::default(),
+        |mut client| async move {
+            client.send(HANDSHAKE).await;
+            client.send(LOGIN_START).await;
+
+            let (key, token, authenticate) = client.encryption_request().await;
+            assert_eq!(token.len(), 4);
+            // Offline mode
+            assert!(!authenticate);
+            client.encryption_response(&key, &SECRET, &token).await;
+            client.encrypt(&SECRET);
+
+            // Login Success for OfflinePlayer:Notch, now encrypted
+            let mut success = vec![0x19, 0x02];
+            success.extend_from_slice(&[
+                0xb5, 0x0a, 0xd3, 0x85, 0x82, 0x9d, 0x31, 0x41, 0xa2, 0x16, 0x7e, 0x7d, 0x75, 0x39,
+                0xba, 0x7f,
+            ]);
+            success.extend_from_slice(&[0x05, b'N', b'o', b't', b'c', b'h', 0x00, 0x00]);
+            client.expect(&success).await;
+
+            // Login Acknowledged, then the start of configuration
+            client.send(&[0x01, 0x03]).await;
+            client.expect(&[0x1b, 0x01, 0x0f]).await;
+        },
+    );
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn rejects_wrong_verify_token() {
+    let result = run_with(
+        &config(),
+        &mut MockSession::default(),
+        |mut client| async move {
+            client.send(HANDSHAKE).await;
+            client.send(LOGIN_START).await;
+
+            let (key, _, _) = client.encryption_request().await;
+            client
+                .encryption_response(&key, &SECRET, &[1, 2, 3, 4])
+                .await;
+            client.expect_closed().await;
+        },
+    );
+
+    assert_eq!(result, Err(ProtocolError::InvalidEncryption));
+}
+
+#[test]
+fn rejects_short_secret() {
+    let result = run_with(
+        &config(),
+        &mut MockSession::default(),
+        |mut client| async move {
+            client.send(HANDSHAKE).await;
+            client.send(LOGIN_START).await;
+
+            let (key, token, _) = client.encryption_request().await;
+            client.encryption_response(&key, &SECRET[..8], &token).await;
+            client.expect_closed().await;
+        },
+    );
+
+    assert_eq!(result, Err(ProtocolError::InvalidEncryption));
+}
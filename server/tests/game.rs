@@ -0,0 +1,180 @@
+use std::{cell::Cell, rc::Rc};
+
+use embassy_futures::{
+    block_on,
+    select::{select, Either},
+};
+use embassy_time::{Duration, Timer};
+use picocraft_protocol::types::Position;
+use picocraft_server::{
+    blocks::{AIR, STONE},
+    game::{Entity, Game, Update, TICK, TICKS_PER_SECOND},
+    store::{BlockEdit, BlockStore, MemoryStore},
+    MAX_PLAYERS,
+};
+
+fn game() -> Game<MemoryStore> {
+    Game::new(MemoryStore::new())
+}
+
+fn edit(x: i32, block: u16) -> BlockEdit {
+    BlockEdit::new(Position { x, y: 70, z: 3 }, block)
+}
+
+/// Sends an update every `interval` ticks, `lives` times.
+struct Blinker {
+    interval: u64,
+    lives: u32,
+    ticks: u64,
+}
+
+impl Entity for Blinker {
+    fn tick(&mut self, broadcast: &mut dyn FnMut(Update)) -> bool {
+        self.ticks += 1;
+        if self.ticks.is_multiple_of(self.interval) {
+            broadcast(Update::Block(edit(self.ticks as i32, STONE)));
+            self.lives -= 1;
+        }
+        self.lives > 0
+    }
+}
+
+#[test]
+fn sends_the_time_every_second() {
+    let game = game();
+    let mut updates = game.join().unwrap();
+
+    for _ in 0..2 * TICKS_PER_SECOND {
+        game.tick();
+    }
+
+    assert_eq!(game.ticks(), 40);
+    assert_eq!(updates.try_next_message_pure(), Some(Update::Time(20)));
+    assert_eq!(updates.try_next_message_pure(), Some(Update::Time(40)));
+    assert_eq!(updates.try_next_message_pure(), None);
+}
+
+#[test]
+fn sends_keep_alives_every_15_seconds() {
+    let game = game();
+    let mut updates = game.join().unwrap();
+
+    let mut keep_alives = Vec::new();
+    for _ in 0..30 * TICKS_PER_SECOND {
+        game.tick();
+        while let Some(update) = updates.try_next_message_pure() {
+            if let Update::KeepAlive(id) = update {
+                keep_alives.push((game.ticks(), id));
+            }
+        }
+    }
+
+    assert_eq!(keep_alives, [(300, 300), (600, 600)]);
+}
+
+#[test]
+fn changes_blocks_on_the_next_tick() {
+    let game = game();
+    let mut first = game.join().unwrap();
+    let mut second = game.join().unwrap();
+
+    game.change_block(edit(1, STONE));
+    game.change_block(edit(1, AIR));
+    assert_eq!(first.try_next_message_pure(), None);
+    let mut kept = Vec::new();
+    game.store
+        .borrow_mut()
+        .edits_in(0, 0, |edit| kept.push(*edit))
+        .unwrap();
+    assert!(kept.is_empty());
+
+    game.tick();
+    for updates in [&mut first, &mut second] {
+        assert_eq!(
+            updates.try_next_message_pure(),
+            Some(Update::Block(edit(1, STONE)))
+        );
+        assert_eq!(
+            updates.try_next_message_pure(),
+            Some(Update::Block(edit(1, AIR)))
+        );
+    }
+    game.store
+        .borrow_mut()
+        .edits_in(0, 0, |edit| kept.push(*edit))
+        .unwrap();
+    assert_eq!(kept, [edit(1, AIR)]);
+}
+
+#[test]
+fn runs_scheduled_tasks_when_they_are_due() {
+    let game = game();
+    let ran = Rc::new(Cell::new(Vec::new()));
+
+    let log = ran.clone();
+    game.schedule(3, move |game| {
+        log.set([log.take(), vec![game.ticks()]].concat());
+        // Rescheduled for later, even at no delay
+        let log = log.clone();
+        game.schedule(0, move |game| {
+            log.set([log.take(), vec![game.ticks()]].concat());
+        });
+    });
+    let log = ran.clone();
+    game.schedule(1, move |game| {
+        log.set([log.take(), vec![game.ticks()]].concat())
+    });
+
+    for _ in 0..10 {
+        game.tick();
+    }
+    assert_eq!(ran.take(), [1, 3, 4]);
+}
+
+#[test]
+fn ticks_entities_until_they_are_gone() {
+    let game = game();
+    let mut updates = game.join().unwrap();
+    game.spawn(Blinker {
+        interval: 2,
+        lives: 2,
+        ticks: 0,
+    });
+
+    let mut blinks = Vec::new();
+    for _ in 0..10 {
+        game.tick();
+        while let Some(Update::Block(edit)) = updates.try_next_message_pure() {
+            blinks.push(edit.position().x);
+        }
+    }
+    assert_eq!(blinks, [2, 4]);
+}
+
+#[test]
+fn limits_players() {
+    let game = game();
+    let players: Vec<_> = (0..MAX_PLAYERS).map(|_| game.join().unwrap()).collect();
+
+    assert!(game.join().is_none());
+    assert_eq!(game.players(), MAX_PLAYERS);
+    drop(players);
+    assert_eq!(game.players(), 0);
+    let _player = game.join().unwrap();
+    assert_eq!(game.players(), 1);
+}
+
+#[test]
+fn measures_ticks_per_second() {
+    let game = game();
+
+    let run = select(game.run(), Timer::after(Duration::from_millis(2200)));
+    let Either::Second(()) = block_on(run);
+
+    let stats = game.stats();
+    // Real time, so only roughly
+    assert!((30..=45).contains(&game.ticks()), "{} ticks", game.ticks());
+    assert!(stats.tps > 10.0 && stats.tps < 25.0, "{} TPS", stats.tps);
+    // Nothing happens in these ticks, so they're quick
+    assert!(stats.max_tick_time < TICK);
+}
@@ -0,0 +1,187 @@
+mod common;
+
+use common::{run, run_with, test_config, MockSession};
+use picocraft_protocol::error::ProtocolError;
+use picocraft_server::Config;
+
+// Protocol 767, "localhost", port 25565, next state Login
+const HANDSHAKE: &[u8] = &[
+    0x10, 0x00, 0xff, 0x05, 0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't', 0x63, 0xdd,
+    0x02,
+];
+const LOGIN_ACKNOWLEDGED: &[u8] = &[0x01, 0x03];
+// Brand, feature flags and known packs
+const CONFIGURATION_START_LENGTH: usize = 28 + 21 + 25;
+
+// OfflinePlayer:Notch
+const NOTCH_UUID: [u8; 16] = [
+    0xb5, 0x0a, 0xd3, 0x85, 0x82, 0x9d, 0x31, 0x41, 0xa2, 0x16, 0x7e, 0x7d, 0x75, 0x39, 0xba, 0x7f,
+];
+
+fn login_start(name: &str) -> Vec<u8> {
+    let mut body = vec![0x00, name.len() as u8];
+    body.extend_from_slice(name.as_bytes());
+    // Whatever UUID the client sends is ignored in offline mode
+    body.extend_from_slice(&[0xaa; 16]);
+
+    let mut frame = vec![body.len() as u8];
+    frame.extend(body);
+    frame
+}
+
+#[test]
+fn logs_in_offline_player() {
+    let result = run(|mut client| async move {
+        client.send(HANDSHAKE).await;
+        client.send(&login_start("Notch")).await;
+
+        let mut success = vec![0x19, 0x02];
+        success.extend_from_slice(&NOTCH_UUID);
+        success.extend_from_slice(&[0x05, b'N', b'o', b't', b'c', b'h']);
+        // No properties, lenient error handling
+        success.extend_from_slice(&[0x00, 0x00]);
+        client.expect(&success).await;
+
+        client.send(LOGIN_ACKNOWLEDGED).await;
+        // The server moves on to configuration, which has its own tests
+        client.expect_any(CONFIGURATION_START_LENGTH).await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn turns_on_compression() {
+    let config = Config {
+        compression_threshold: Some(256),
+        ..test_config()
+    };
+    let result = run_with(
+        &config,
+        &mut MockSession::default(),
+        |mut client| async move {
+            client.send(HANDSHAKE).await;
+            client.send(&login_start("Notch")).await;
+
+            // Set Compression itself isn't compressed
+            client.expect(&[0x03, 0x03, 0x80, 0x02]).await;
+
+            // Login Success is under the threshold, so it only gets a zero data length
+            let mut success = vec![0x1a, 0x00, 0x02];
+            success.extend_from_slice(&NOTCH_UUID);
+            success.extend_from_slice(&[0x05, b'N', b'o', b't', b'c', b'h']);
+            success.extend_from_slice(&[0x00, 0x00]);
+            client.expect(&success).await;
+
+            // The client has to compress too
+            client.send(&[0x02, 0x00, 0x03]).await;
+            client.expect_any(CONFIGURATION_START_LENGTH + 3).await;
+        },
+    );
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn kicks_invalid_username() {
+    let result = run(|mut client| async move {
+        client.send(HANDSHAKE).await;
+        client.send(&login_start("bad name!")).await;
+
+        let json = br#"{"text":"Invalid username"}"#;
+        let mut disconnect = vec![json.len() as u8 + 2, 0x00, json.len() as u8];
+        disconnect.extend_from_slice(json);
+        client.expect(&disconnect).await;
+        client.expect_closed().await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn rejects_overlong_username() {
+    let result = run(|mut client| async move {
+        client.send(HANDSHAKE).await;
+        client.send(&login_start("seventeen_letters")).await;
+        client.expect_closed().await;
+    });
+
+    assert_eq!(
+        result,
+        Err(ProtocolError::StringTooLong {
+            length: 17,
+            max: 16
+        })
+    );
+}
+
+#[test]
+fn rejects_second_login_start() {
+    let result = run(|mut client| async move {
+        client.send(HANDSHAKE).await;
+        client.send(&login_start("Notch")).await;
+        // Login Success
+        client.expect_any(26).await;
+
+        client.send(&login_start("Herobrine")).await;
+        client.expect_closed().await;
+    });
+
+    assert_eq!(result, Err(ProtocolError::UnknownPacketId(0x00)));
+}
+
+#[test]
+fn rejects_acknowledgement_before_login() {
+    let result = run(|mut client| async move {
+        client.send(HANDSHAKE).await;
+        client.send(LOGIN_ACKNOWLEDGED).await;
+        client.expect_closed().await;
+    });
+
+    assert_eq!(result, Err(ProtocolError::UnknownPacketId(0x03)));
+}
+
+fn login_handshake(protocol_version: &[u8]) -> Vec<u8> {
+    let mut body = vec![0x00];
+    body.extend_from_slice(protocol_version);
+    body.extend_from_slice(&[0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't']);
+    body.extend_from_slice(&[0x63, 0xdd, 0x02]);
+
+    let mut frame = vec![body.len() as u8];
+    frame.extend(body);
+    frame
+}
+
+fn disconnect(json: &[u8]) -> Vec<u8> {
+    let mut frame = vec![json.len() as u8 + 2, 0x00, json.len() as u8];
+    frame.extend_from_slice(json);
+    frame
+}
+
+#[test]
+fn kicks_outdated_client() {
+    // Protocol 47, which is 1.8
+    let result = run(|mut client| async move {
+        client.send(&login_handshake(&[0x2f])).await;
+        let json =
+            br#"{"translate":"multiplayer.disconnect.outdated_client","with":[{"text":"1.20.6-1.21.1"}]}"#;
+        client.expect(&disconnect(json)).await;
+        client.expect_closed().await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn kicks_client_newer_than_server() {
+    // Protocol 768, which is 1.21.2
+    let result = run(|mut client| async move {
+        client.send(&login_handshake(&[0x80, 0x06])).await;
+        let json =
+            br#"{"translate":"multiplayer.disconnect.outdated_server","with":[{"text":"1.20.6-1.21.1"}]}"#;
+        client.expect(&disconnect(json)).await;
+        client.expect_closed().await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
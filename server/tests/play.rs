@@ -0,0 +1,376 @@
+mod common;
+
+use common::{frame, run, run_with_game, test_config, Client, MockSession};
+use picocraft_protocol::{
+    packets::{
+        play::{
+            AcknowledgeBlockChange, BlockUpdate, ChunkDataAndUpdateLight, PlayDisconnect,
+            PlayerAction, UpdateTime,
+        },
+        Packet,
+    },
+    read::ReadExtension,
+    text::TextComponent,
+    types::Position,
+};
+use picocraft_server::{
+    blocks::{AIR, OAK_LOG},
+    game::{Game, Update, TICKS_PER_SECOND},
+    play::{MIN_Y, VIEW_DISTANCE},
+    registries::{self, BIOME},
+    store::{BlockEdit, BlockStore, MemoryStore},
+    MAX_PLAYERS,
+};
+
+// Protocol 767, "localhost", port 25565, next state Login
+const HANDSHAKE: &[u8] = &[
+    0x10, 0x00, 0xff, 0x05, 0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't', 0x63, 0xdd,
+    0x02,
+];
+const LOGIN_START: &[u8] = &[
+    0x17, 0x00, 0x05, b'N', b'o', b't', b'c', b'h', 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+    0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+];
+const LOGIN_SUCCESS_LENGTH: usize = 0x19;
+const LOGIN_ACKNOWLEDGED: &[u8] = &[0x01, 0x03];
+// Brand, feature flags and known packs
+const CONFIGURATION_START_LENGTH: usize = 28 + 21 + 25;
+// [minecraft:core:1.21.1]
+const KNOWN_PACKS: &[u8] = &[
+    0x18, 0x07, 0x01, 0x09, b'm', b'i', b'n', b'e', b'c', b'r', b'a', b'f', b't', 0x04, b'c', b'o',
+    b'r', b'e', 0x06, b'1', b'.', b'2', b'1', b'.', b'1',
+];
+const ACKNOWLEDGE_FINISH: &[u8] = &[0x01, 0x03];
+
+// Login (play), Update Time, Set Default Spawn Position, Game Event and Set Center Chunk
+const JOIN_START: [i32; 5] = [0x2b, 0x64, 0x56, 0x22, 0x54];
+const UPDATE_TIME: i32 = 0x64;
+const CHUNK_DATA: i32 = 0x27;
+const SYNCHRONIZE_PLAYER_POSITION: i32 = 0x40;
+
+fn client_information(view_distance: u8) -> Vec<u8> {
+    // en_us, full chat with colours, all skin parts, right handed
+    let mut frame = vec![
+        0x0e, 0x00, 0x05, b'e', b'n', b'_', b'u', b's', 0x00, 0x00, 0x01, 0x7f, 0x01, 0x00, 0x01,
+    ];
+    frame[8] = view_distance;
+    frame
+}
+
+fn confirm_teleportation(id: i32) -> Vec<u8> {
+    vec![0x02, 0x00, id as u8]
+}
+
+fn keep_alive(id: i64) -> Vec<u8> {
+    let mut frame = vec![0x09, 0x18];
+    frame.extend_from_slice(&id.to_be_bytes());
+    frame
+}
+
+/// Moves `game` on by `ticks`, letting the server send what changed after each one.
+async fn tick(game: &Game<MemoryStore>, ticks: u64) {
+    for _ in 0..ticks {
+        game.tick();
+        embassy_futures::yield_now().await;
+    }
+}
+
+/// Reads the next frame that isn't the time, which is sent every second.
+async fn skip_time(client: &mut Client) -> Packet {
+    loop {
+        let packet = client.read_frame().await;
+        if packet.id != UPDATE_TIME {
+            return packet;
+        }
+    }
+}
+
+/// Logs in and configures the client, up to the server sending the world.
+async fn configure(client: &mut Client, view_distance: Option<u8>) {
+    client.send(HANDSHAKE).await;
+    client.send(LOGIN_START).await;
+    client.expect_any(LOGIN_SUCCESS_LENGTH + 1).await;
+    client.send(LOGIN_ACKNOWLEDGED).await;
+    client.expect_any(CONFIGURATION_START_LENGTH).await;
+
+    if let Some(view_distance) = view_distance {
+        client.send(&client_information(view_distance)).await;
+    }
+    client.send(KNOWN_PACKS).await;
+    // The registries are covered by the configuration tests
+    loop {
+        // Finish Configuration
+        if client.read_frame().await.id == 0x03 {
+            break;
+        }
+    }
+    client.send(ACKNOWLEDGE_FINISH).await;
+}
+
+fn expected_join(view_distance: i32) -> Vec<i32> {
+    let chunks = (2 * view_distance + 1) * (2 * view_distance + 1);
+    let mut ids = JOIN_START.to_vec();
+    ids.extend((0..chunks).map(|_| CHUNK_DATA));
+    ids.push(SYNCHRONIZE_PLAYER_POSITION);
+    ids
+}
+
+#[test]
+fn joins_world() {
+    let result = run(|mut client| async move {
+        configure(&mut client, Some(8)).await;
+
+        let (ids, teleport_id) = client.join().await;
+        // The client asked for more than we send
+        assert_eq!(ids, expected_join(VIEW_DISTANCE));
+        client.send(&confirm_teleportation(teleport_id)).await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn sends_login_play() {
+    let result = run(|mut client| async move {
+        configure(&mut client, None).await;
+
+        let mut login = client.read_frame().await;
+        assert_eq!(login.id, 0x2b);
+        let data = &mut login.data;
+        // Entity id, not hardcore
+        assert_eq!(data.read_i32().await.unwrap(), 1);
+        assert!(!data.read_bool().await.unwrap());
+        // Dimension names
+        assert_eq!(data.read_varint().await.unwrap(), 1);
+        assert_eq!(data.read_string().await.unwrap(), "minecraft:overworld");
+        // Max players, view distance and simulation distance
+        assert_eq!(data.read_varint().await.unwrap(), 4);
+        assert_eq!(data.read_varint().await.unwrap(), VIEW_DISTANCE);
+        assert_eq!(data.read_varint().await.unwrap(), VIEW_DISTANCE);
+        // Debug info, respawn screen and limited crafting
+        data.read_byte_array(3).await.unwrap();
+        // The overworld's dimension type
+        assert_eq!(data.read_varint().await.unwrap(), 0);
+        assert_eq!(data.read_string().await.unwrap(), "minecraft:overworld");
+        client.join().await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn sends_fewer_chunks_to_short_view_distance() {
+    let result = run(|mut client| async move {
+        configure(&mut client, Some(2)).await;
+
+        let (ids, _) = client.join().await;
+        assert_eq!(ids, expected_join(2));
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn ignores_unknown_packets_in_play() {
+    let result = run(|mut client| async move {
+        configure(&mut client, None).await;
+        let (_, teleport_id) = client.join().await;
+
+        // Set Player Position, at the spawn and on the ground
+        let mut position = vec![0x1a, 0x1a];
+        for coordinate in [0.5f64, 64.0, 0.5] {
+            position.extend_from_slice(&coordinate.to_be_bytes());
+        }
+        position.push(0x01);
+        client.send(&position).await;
+        // Teleports that aren't pending are ignored too
+        client.send(&confirm_teleportation(teleport_id + 1)).await;
+        client.send(&confirm_teleportation(teleport_id)).await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn kicks_wrong_keep_alive() {
+    let result = run(|mut client| async move {
+        configure(&mut client, None).await;
+        client.join().await;
+
+        // None has been sent yet
+        client.send(&keep_alive(42)).await;
+        let reason = TextComponent::translate("disconnect.timeout", Vec::new());
+        client
+            .expect(&frame(&PlayDisconnect { reason }).await)
+            .await;
+        client.expect_closed().await;
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn sends_the_time_every_second() {
+    let game = &Game::new(MemoryStore::new());
+    let result = run_with_game(
+        &test_config(),
+        &mut MockSession::default(),
+        game,
+        |mut client| async move {
+            configure(&mut client, None).await;
+            client.join().await;
+
+            tick(game, TICKS_PER_SECOND).await;
+            let time = UpdateTime {
+                world_age: 20,
+                time_of_day: 20,
+            };
+            client.expect(&frame(&time).await).await;
+        },
+    );
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn kicks_client_that_stops_answering_keep_alives() {
+    let game = &Game::new(MemoryStore::new());
+    let result = run_with_game(
+        &test_config(),
+        &mut MockSession::default(),
+        game,
+        |mut client| async move {
+            configure(&mut client, None).await;
+            client.join().await;
+
+            // Every 15 seconds
+            tick(game, 300).await;
+            let mut packet = skip_time(&mut client).await;
+            assert_eq!(packet.id, 0x26);
+            assert_eq!(packet.data.read_i64().await.unwrap(), 300);
+            client.send(&keep_alive(300)).await;
+            tick(game, 300).await;
+            assert_eq!(skip_time(&mut client).await.id, 0x26);
+
+            // Not answered in time
+            tick(game, 300).await;
+            assert_eq!(skip_time(&mut client).await.id, 0x1d);
+            client.expect_closed().await;
+        },
+    );
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn kicks_players_when_the_world_is_full() {
+    let game = &Game::new(MemoryStore::new());
+    let players: Vec<_> = (0..MAX_PLAYERS).map(|_| game.join().unwrap()).collect();
+    let result = run_with_game(
+        &test_config(),
+        &mut MockSession::default(),
+        game,
+        |mut client| async move {
+            configure(&mut client, None).await;
+            let reason = TextComponent::translate("multiplayer.disconnect.server_full", Vec::new());
+            client
+                .expect(&frame(&PlayDisconnect { reason }).await)
+                .await;
+            client.expect_closed().await;
+        },
+    );
+
+    assert_eq!(result, Ok(()));
+    drop(players);
+}
+
+#[test]
+fn breaks_blocks_for_everyone() {
+    let game = &Game::new(MemoryStore::new());
+    let mut other = game.join().unwrap();
+    let position = Position {
+        x: -3,
+        y: 40,
+        z: 17,
+    };
+    let result = run_with_game(
+        &test_config(),
+        &mut MockSession::default(),
+        game,
+        |mut client| async move {
+            configure(&mut client, None).await;
+            let (_, teleport_id) = client.join().await;
+            client.send(&confirm_teleportation(teleport_id)).await;
+
+            let action = PlayerAction {
+                status: PlayerAction::STARTED_DIGGING,
+                position,
+                face: 1,
+                sequence: 7,
+            };
+            client.send(&frame(&action).await).await;
+            client
+                .expect(&frame(&AcknowledgeBlockChange { sequence: 7 }).await)
+                .await;
+
+            // Nothing changes until the next tick
+            tick(game, 1).await;
+            let update = BlockUpdate {
+                position,
+                block: AIR as i32,
+            };
+            client.expect(&frame(&update).await).await;
+        },
+    );
+
+    assert_eq!(result, Ok(()));
+    let edit = BlockEdit::new(position, AIR);
+    assert_eq!(other.try_next_message_pure(), Some(Update::Block(edit)));
+    let mut edits = Vec::new();
+    game.store
+        .borrow_mut()
+        .edits_in(-1, 1, |edit| edits.push(*edit))
+        .unwrap();
+    assert_eq!(edits, [edit]);
+}
+
+#[test]
+fn sends_chunks_with_edits() {
+    let config = test_config();
+    let game = &Game::new(MemoryStore::new());
+    // In the first chunk sent, above the ground
+    let edit = BlockEdit::new(
+        Position {
+            x: -64,
+            y: 200,
+            z: -60,
+        },
+        OAK_LOG,
+    );
+    game.store.borrow_mut().record(&edit).unwrap();
+
+    let mut chunk = config.world.chunk(
+        -4,
+        -4,
+        registries::id_of(BIOME, "minecraft:plains").unwrap() as u32,
+    );
+    chunk.set_block(0, (200 - MIN_Y) as usize, 4, OAK_LOG);
+    let result = run_with_game(
+        &config,
+        &mut MockSession::default(),
+        game,
+        |mut client| async move {
+            configure(&mut client, None).await;
+            for _ in JOIN_START {
+                client.read_frame().await;
+            }
+
+            let packet = ChunkDataAndUpdateLight::new(-4, -4, &chunk, BIOME.len());
+            client.expect(&frame(&packet).await).await;
+            // The rest of the chunks
+            client.join().await;
+        },
+    );
+
+    assert_eq!(result, Ok(()));
+}
@@ -0,0 +1,194 @@
+mod common;
+
+use common::{frame, run_with, test_config, test_key, MockSession};
+use embassy_futures::block_on;
+use picocraft_protocol::{
+    error::ProtocolError,
+    packets::login::{LoginDisconnect, LoginSuccess, Property},
+    text::TextComponent,
+    types::Uuid,
+};
+use picocraft_server::{encryption::server_hash, Config};
+
+// Protocol 767, "localhost", port 25565, next state Login
+const HANDSHAKE: &[u8] = &[
+    0x10, 0x00, 0xff, 0x05, 0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't', 0x63, 0xdd,
+    0x02,
+];
+// "Notch" and a UUID the server ignores
+const LOGIN_START: &[u8] = &[
+    0x17, 0x00, 0x05, b'N', b'o', b't', b'c', b'h', 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+    0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+];
+const SECRET: [u8; 16] = *b"sixteen byte key";
+
+// Laid out like Mojang's, with an `=` escaped the way Gson does it
+const PROFILE: &str = r#"{
+  "id" : "069a79f444e94726a5befca90e38aaf5",
+  "name" : "Notch",
+  "properties" : [ {
+    "name" : "textures",
+    "value" : "dGV4dHVyZXM\u003d",
+    "signature" : "c2lnbmF0dXJl"
+  } ],
+  "profileActions" : [ ]
+}"#;
+
+fn online_config() -> Config {
+    Config {
+        key: Some(test_key()),
+        check_sessions: true,
+        session_server: "http://session.test:8080/mock/".to_string(),
+        ..test_config()
+    }
+}
+
+fn response(status: &str, body: &str) -> Vec<u8> {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+    .into_bytes()
+}
+
+/// Logs in as Notch, checked with the session server, expecting `expected` once the connection is encrypted.
+fn log_in(session: &mut MockSession, expected: Vec<u8>) {
+    let result = run_with(&online_config(), session, |mut client| async move {
+        client.send(HANDSHAKE).await;
+        client.send(LOGIN_START).await;
+
+        let (key, token, authenticate) = client.encryption_request().await;
+        assert!(authenticate);
+        client.encryption_response(&key, &SECRET, &token).await;
+        client.encrypt(&SECRET);
+        client.expect(&expected).await;
+    });
+    assert_eq!(result, Ok(()));
+}
+
+fn kick(key: &str) -> Vec<u8> {
+    let reason = TextComponent::translate(key, Vec::new());
+    block_on(frame(&LoginDisconnect { reason }))
+}
+
+#[test]
+fn hashes_like_java() {
+    // Examples are taken from wiki.vg
+    assert_eq!(
+        server_hash("Notch", &[], &[]),
+        "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48"
+    );
+    assert_eq!(
+        server_hash("jeb_", &[], &[]),
+        "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"
+    );
+    assert_eq!(
+        server_hash("simon", &[], &[]),
+        "88e16a1019277b15d58faf0541e11910eb756f6"
+    );
+}
+
+#[test]
+fn logs_in_with_profile_from_session_server() {
+    let mut session = MockSession {
+        response: Some(response("200 OK", PROFILE)),
+        ..MockSession::default()
+    };
+    let success = LoginSuccess {
+        uuid: Uuid(0x069a79f444e94726a5befca90e38aaf5),
+        username: "Notch".to_string(),
+        properties: vec![Property {
+            name: "textures".to_string(),
+            value: "dGV4dHVyZXM=".to_string(),
+            signature: Some("c2lnbmF0dXJl".to_string()),
+        }],
+        strict_error_handling: false,
+    };
+    log_in(&mut session, block_on(frame(&success)));
+
+    let [(host, port, request)] = &session.requests[..] else {
+        panic!("expected one request, got {:?}", session.requests);
+    };
+    assert_eq!((host.as_str(), *port), ("session.test", 8080));
+    let hash = server_hash("", &SECRET, test_key().public_der());
+    let expected = format!(
+        "GET /mock/session/minecraft/hasJoined?username=Notch&serverId={} HTTP/1.0\r\n\
+         Host: session.test\r\n",
+        hash
+    );
+    let request = String::from_utf8(request.clone()).unwrap();
+    assert!(request.starts_with(&expected), "{}", request);
+    assert!(request.ends_with("\r\n\r\n"));
+}
+
+#[test]
+fn kicks_player_who_did_not_join() {
+    let mut session = MockSession {
+        response: Some(response("204 No Content", "")),
+        ..MockSession::default()
+    };
+    log_in(
+        &mut session,
+        kick("multiplayer.disconnect.unverified_username"),
+    );
+}
+
+#[test]
+fn kicks_when_session_server_is_down() {
+    let mut session = MockSession::default();
+    log_in(
+        &mut session,
+        kick("multiplayer.disconnect.authservers_down"),
+    );
+}
+
+#[test]
+fn kicks_on_error_status() {
+    let mut session = MockSession {
+        response: Some(response("500 Internal Server Error", "")),
+        ..MockSession::default()
+    };
+    log_in(
+        &mut session,
+        kick("multiplayer.disconnect.authservers_down"),
+    );
+}
+
+#[test]
+fn checking_sessions_needs_a_key() {
+    let config = Config {
+        key: None,
+        ..online_config()
+    };
+    let expected = kick("multiplayer.disconnect.authservers_down");
+    let result = run_with(
+        &config,
+        &mut MockSession::default(),
+        |mut client| async move {
+            client.send(HANDSHAKE).await;
+            client.send(LOGIN_START).await;
+            client.expect(&expected).await;
+            client.expect_closed().await;
+        },
+    );
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn rejects_login_start_while_encrypting() {
+    let result = run_with(
+        &online_config(),
+        &mut MockSession::default(),
+        |mut client| async move {
+            client.send(HANDSHAKE).await;
+            client.send(LOGIN_START).await;
+            client.encryption_request().await;
+
+            client.send(LOGIN_START).await;
+            client.expect_closed().await;
+        },
+    );
+    assert_eq!(result, Err(ProtocolError::UnknownPacketId(0x00)));
+}
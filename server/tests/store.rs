@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+
+use embedded_storage::nor_flash::{
+    check_erase, check_read, check_write, ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash,
+};
+use picocraft_protocol::types::Position;
+use picocraft_server::{
+    blocks::{BlockState, AIR, DIRT, OAK_LOG, STONE},
+    store::{
+        BlockEdit, BlockStore, FlashStore, MemoryStore, StoreError, HEADER_LENGTH, RECORD_LENGTH,
+    },
+};
+
+const SECTOR: usize = 4096;
+// One sector in each bank
+const SIZE: u32 = 2 * SECTOR as u32;
+// Less the header
+const RECORDS: usize = (SECTOR - HEADER_LENGTH) / RECORD_LENGTH;
+const SEED: u64 = 0x1234_5678_9abc_def0;
+
+/// Flash in RAM that can lose power part way through writing or erasing.
+struct MockFlash {
+    data: Vec<u8>,
+    /// How many more bytes can be written, or sectors erased, before the power goes.
+    power: usize,
+}
+
+impl MockFlash {
+    fn new() -> MockFlash {
+        MockFlash {
+            data: vec![0xff; SIZE as usize],
+            power: usize::MAX,
+        }
+    }
+
+    /// Turns the power back on.
+    fn restore(mut self) -> MockFlash {
+        self.power = usize::MAX;
+        self
+    }
+}
+
+impl ErrorType for MockFlash {
+    type Error = NorFlashErrorKind;
+}
+
+impl ReadNorFlash for MockFlash {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        check_read(self, offset, bytes.len())?;
+        let offset = offset as usize;
+        bytes.copy_from_slice(&self.data[offset..offset + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl NorFlash for MockFlash {
+    const WRITE_SIZE: usize = 1;
+    const ERASE_SIZE: usize = SECTOR;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        check_erase(self, from, to)?;
+        for sector in self.data[from as usize..to as usize].chunks_mut(SECTOR) {
+            if self.power == 0 {
+                return Err(NorFlashErrorKind::Other);
+            }
+            self.power -= 1;
+            sector.fill(0xff);
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        check_write(self, offset, bytes.len())?;
+        for (old, new) in self.data[offset as usize..].iter_mut().zip(bytes) {
+            if self.power == 0 {
+                return Err(NorFlashErrorKind::Other);
+            }
+            self.power -= 1;
+            *old &= new;
+        }
+        Ok(())
+    }
+}
+
+fn edit(x: i32, y: i32, z: i32, block: BlockState) -> BlockEdit {
+    BlockEdit::new(Position { x, y, z }, block)
+}
+
+/// The blocks changed in the chunk, after applying its edits in order.
+fn blocks(store: &mut impl BlockStore, chunk_x: i32, chunk_z: i32) -> HashMap<(u8, u16, u8), u16> {
+    let mut blocks = HashMap::new();
+    store
+        .edits_in(chunk_x, chunk_z, |edit| {
+            blocks.insert((edit.x, edit.y, edit.z), edit.block);
+        })
+        .unwrap();
+    blocks
+}
+
+#[test]
+fn places_edits_in_chunks() {
+    let edit = edit(-1, -64, 33, STONE);
+
+    assert_eq!((edit.chunk_x, edit.chunk_z), (-1, 2));
+    assert_eq!((edit.x, edit.y, edit.z), (15, 0, 1));
+}
+
+#[test]
+fn keeps_latest_edit_in_memory() {
+    let mut store = MemoryStore::new();
+    store.record(&edit(1, 2, 3, STONE)).unwrap();
+    store.record(&edit(1, 2, 3, AIR)).unwrap();
+    store.record(&edit(20, 2, 3, DIRT)).unwrap();
+
+    let mut edits = Vec::new();
+    store.edits_in(0, 0, |edit| edits.push(*edit)).unwrap();
+    assert_eq!(edits, [edit(1, 2, 3, AIR)]);
+}
+
+#[test]
+fn keeps_edits_when_reopened() {
+    let mut store = FlashStore::open(MockFlash::new(), 0, SIZE, SEED).unwrap();
+    store.record(&edit(1, 2, 3, STONE)).unwrap();
+    store.record(&edit(11, 70, 9, OAK_LOG)).unwrap();
+    store.record(&edit(1, 2, 3, AIR)).unwrap();
+    store.record(&edit(40, 2, 3, DIRT)).unwrap();
+    assert_eq!(store.space(), RECORDS - 4);
+
+    let mut store = FlashStore::open(store.into_inner(), 0, SIZE, SEED).unwrap();
+    assert_eq!(store.space(), RECORDS - 4);
+    let chunk = blocks(&mut store, 0, 0);
+    assert_eq!(chunk.len(), 2);
+    assert_eq!(chunk[&(1, 66, 3)], AIR);
+    assert_eq!(chunk[&(11, 134, 9)], OAK_LOG);
+    assert_eq!(blocks(&mut store, 2, 0)[&(8, 66, 3)], DIRT);
+    assert!(blocks(&mut store, 1, 0).is_empty());
+}
+
+#[test]
+fn keeps_seed_when_reopened() {
+    let mut store = FlashStore::open(MockFlash::new(), 0, SIZE, SEED).unwrap();
+    assert_eq!(store.seed(), SEED);
+    store.record(&edit(1, 2, 3, STONE)).unwrap();
+
+    let mut store = FlashStore::open(store.into_inner(), 0, SIZE, 7).unwrap();
+    assert_eq!(store.seed(), SEED);
+    assert_eq!(blocks(&mut store, 0, 0)[&(1, 66, 3)], STONE);
+}
+
+#[test]
+fn forgets_edits_when_reset() {
+    let mut store = FlashStore::open(MockFlash::new(), 0, SIZE, SEED).unwrap();
+    store.record(&edit(1, 2, 3, STONE)).unwrap();
+    store.reset(7).unwrap();
+    assert_eq!(store.seed(), 7);
+    assert_eq!(store.space(), RECORDS);
+    assert!(blocks(&mut store, 0, 0).is_empty());
+
+    let mut store = FlashStore::open(store.into_inner(), 0, SIZE, SEED).unwrap();
+    assert_eq!(store.seed(), 7);
+    assert!(blocks(&mut store, 0, 0).is_empty());
+}
+
+#[test]
+fn compacts_when_full() {
+    let mut store = FlashStore::open(MockFlash::new(), 0, SIZE, SEED).unwrap();
+    // Far more edits than fit, but only to a few blocks
+    for i in 0..10 * RECORDS {
+        let block = [STONE, DIRT, OAK_LOG][i % 3];
+        store.record(&edit((i % 4) as i32, 10, 0, block)).unwrap();
+    }
+
+    let mut store = FlashStore::open(store.into_inner(), 0, SIZE, SEED).unwrap();
+    let chunk = blocks(&mut store, 0, 0);
+    assert_eq!(chunk.len(), 4);
+    for x in 0..4 {
+        let last = (10 * RECORDS - 4..10 * RECORDS)
+            .find(|i| i % 4 == x)
+            .unwrap();
+        assert_eq!(chunk[&(x as u8, 74, 0)], [STONE, DIRT, OAK_LOG][last % 3]);
+    }
+}
+
+#[test]
+fn fills_up_with_different_blocks() {
+    let mut store = FlashStore::open(MockFlash::new(), 0, SIZE, SEED).unwrap();
+    for i in 0..RECORDS {
+        store.record(&edit(0, i as i32, 0, STONE)).unwrap();
+    }
+
+    assert_eq!(store.record(&edit(0, 300, 0, STONE)), Err(StoreError::Full));
+    // Overruling an edit doesn't make room either, as the new edit has to be kept somewhere
+    assert_eq!(store.record(&edit(0, 0, 0, AIR)), Err(StoreError::Full));
+    let mut store = FlashStore::open(store.into_inner(), 0, SIZE, SEED).unwrap();
+    assert_eq!(blocks(&mut store, 0, 0).len(), RECORDS);
+}
+
+#[test]
+fn skips_records_cut_short() {
+    let mut store = FlashStore::open(MockFlash::new(), 0, SIZE, SEED).unwrap();
+    store.record(&edit(1, 2, 3, STONE)).unwrap();
+
+    let mut flash = store.into_inner();
+    flash.power = RECORD_LENGTH / 2;
+    let mut store = FlashStore::open(flash, 0, SIZE, SEED).unwrap();
+    assert!(store.record(&edit(4, 5, 6, DIRT)).is_err());
+
+    let mut store = FlashStore::open(store.into_inner().restore(), 0, SIZE, SEED).unwrap();
+    assert_eq!(blocks(&mut store, 0, 0).len(), 1);
+    // The half written record isn't written over
+    assert_eq!(store.space(), RECORDS - 2);
+    store.record(&edit(4, 5, 6, DIRT)).unwrap();
+    assert_eq!(blocks(&mut store, 0, 0)[&(4, 69, 6)], DIRT);
+}
+
+#[test]
+fn survives_losing_power_while_compacting() {
+    // A full store, with every block edited many times over
+    let mut full = FlashStore::open(MockFlash::new(), 0, SIZE, SEED).unwrap();
+    for i in 0..RECORDS {
+        full.record(&edit((i % 4) as i32, 0, 0, i as BlockState))
+            .unwrap();
+    }
+    let full = full.into_inner().data;
+    let expected: HashMap<_, _> = (RECORDS - 4..RECORDS)
+        .map(|i| (((i % 4) as u8, 64, 0), i as BlockState))
+        .collect();
+
+    // Cut the power at every point through the compaction and the record after it
+    for power in 0.. {
+        let flash = MockFlash {
+            data: full.clone(),
+            power,
+        };
+        let mut store = FlashStore::open(flash, 0, SIZE, SEED).unwrap();
+        let finished = store.record(&edit(0, 0, 0, AIR)).is_ok();
+
+        let mut store = FlashStore::open(store.into_inner().restore(), 0, SIZE, SEED).unwrap();
+        let mut expected = expected.clone();
+        if finished {
+            expected.insert((0, 64, 0), AIR);
+        }
+        assert_eq!(
+            blocks(&mut store, 0, 0),
+            expected,
+            "losing power after {}",
+            power
+        );
+        // Whatever happened, the store still works
+        store.record(&edit(9, 0, 0, DIRT)).unwrap();
+
+        if finished {
+            break;
+        }
+    }
+}
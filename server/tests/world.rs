@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use embassy_futures::block_on;
+use picocraft_protocol::{
+    chunk::ChunkColumn,
+    packets::{play::ChunkDataAndUpdateLight, WritePacket},
+};
+use picocraft_server::{
+    blocks::{BlockState, AIR, BEDROCK, DIRT, GRASS_BLOCK, OAK_LEAVES, OAK_LOG, SAND, WATER},
+    play::MIN_Y,
+    registries::BIOME,
+    world::{Chunk, Generator, World, CLASSIC_FLAT},
+};
+
+// The biome doesn't change which blocks are generated
+const BIOME_ID: u32 = 0;
+
+fn terrain(seed: u64) -> World {
+    World {
+        generator: Generator::Terrain,
+        seed,
+    }
+}
+
+/// The chunk as it's sent to clients.
+fn encode(x: i32, z: i32, chunk: &Chunk) -> Vec<u8> {
+    let packet = ChunkDataAndUpdateLight::new(x, z, chunk, BIOME.len());
+    let mut out = Vec::new();
+    block_on(packet.write_packet(&mut out)).unwrap();
+    out
+}
+
+/// FNV-1a, to pin down a chunk without keeping all of it in the test.
+fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Looks up blocks by world coordinates, generating chunks as they're needed.
+struct Blocks {
+    world: World,
+    chunks: HashMap<(i32, i32), Chunk>,
+}
+
+impl Blocks {
+    fn get(&mut self, x: i32, y: i32, z: i32) -> BlockState {
+        let world = self.world;
+        let chunk = self
+            .chunks
+            .entry((x >> 4, z >> 4))
+            .or_insert_with(|| world.chunk(x >> 4, z >> 4, BIOME_ID));
+        chunk.block((x & 15) as usize, (y - MIN_Y) as usize, (z & 15) as usize) as BlockState
+    }
+}
+
+#[test]
+fn generates_the_same_chunk_every_time() {
+    let first = encode(3, -5, &terrain(42).chunk(3, -5, BIOME_ID));
+    let second = encode(3, -5, &terrain(42).chunk(3, -5, BIOME_ID));
+
+    assert_eq!(first, second);
+}
+
+#[test]
+fn generates_the_same_chunk_as_before() {
+    // Changing the generator changes every world, so this should only change on purpose
+    let chunk = encode(0, 0, &terrain(42).chunk(0, 0, BIOME_ID));
+    assert_eq!(checksum(&chunk), 8760837815215673709);
+    let chunk = encode(-7, 12, &terrain(1234).chunk(-7, 12, BIOME_ID));
+    assert_eq!(checksum(&chunk), 7967098966458669953);
+}
+
+#[test]
+fn generates_different_worlds_from_different_seeds() {
+    let first = encode(0, 0, &terrain(1).chunk(0, 0, BIOME_ID));
+    let second = encode(0, 0, &terrain(2).chunk(0, 0, BIOME_ID));
+
+    assert_ne!(first, second);
+}
+
+#[test]
+fn generates_different_chunks() {
+    let world = terrain(42);
+    let first = encode(0, 0, &world.chunk(0, 0, BIOME_ID));
+    let second = encode(0, 0, &world.chunk(1, 0, BIOME_ID));
+
+    assert_ne!(first, second);
+}
+
+#[test]
+fn lays_superflat_layers() {
+    let world = World {
+        generator: Generator::Superflat(CLASSIC_FLAT),
+        seed: 42,
+    };
+    let chunk = world.chunk(-3, 8, BIOME_ID);
+
+    for (x, z) in [(0, 0), (7, 3), (15, 15)] {
+        let column: Vec<u32> = (0..5).map(|y| chunk.block(x, y, z)).collect();
+        assert_eq!(
+            column,
+            [BEDROCK, DIRT, DIRT, GRASS_BLOCK, AIR].map(|block| block as u32)
+        );
+        assert_eq!(chunk.height(x, z), 4);
+    }
+    assert_eq!(world.spawn().y, MIN_Y + 4);
+}
+
+#[test]
+fn generates_nothing_in_the_void() {
+    let world = World {
+        generator: Generator::Void,
+        seed: 42,
+    };
+    let chunk = world.chunk(0, 0, BIOME_ID);
+
+    assert!((0..chunk.sections() * 16).all(|y| chunk.block(8, y, 8) == AIR as u32));
+    assert_eq!(chunk.height(8, 8), 0);
+    assert_eq!(world.spawn().y, 64);
+}
+
+#[test]
+fn covers_terrain_in_grass_sand_and_water() {
+    let chunk = terrain(42).chunk(2, 2, BIOME_ID);
+
+    for x in 0..16 {
+        for z in 0..16 {
+            assert_eq!(chunk.block(x, 0, z), BEDROCK as u32);
+            let top = chunk.block(x, chunk.height(x, z) - 1, z) as BlockState;
+            assert!(
+                [GRASS_BLOCK, SAND, WATER, OAK_LEAVES].contains(&top),
+                "{} is on top at {}, {}",
+                top,
+                x,
+                z
+            );
+            assert_eq!(chunk.block(x, chunk.height(x, z), z), AIR as u32);
+        }
+    }
+}
+
+#[test]
+fn spawns_on_top_of_the_terrain() {
+    let world = terrain(42);
+    let spawn = world.spawn();
+    let chunk = world.chunk(0, 0, BIOME_ID);
+    let y = (spawn.y - MIN_Y) as usize;
+
+    assert_ne!(chunk.block(0, y - 1, 0), AIR as u32);
+    assert_eq!(chunk.block(0, y, 0), AIR as u32);
+}
+
+#[test]
+fn grows_whole_trees_across_chunk_edges() {
+    let mut blocks = Blocks {
+        world: terrain(42),
+        chunks: HashMap::new(),
+    };
+
+    let mut trees = 0;
+    for x in -32..32 {
+        for z in -32..32 {
+            for y in 60..120 {
+                // The top of a trunk
+                if blocks.get(x, y, z) != OAK_LOG || blocks.get(x, y + 1, z) == OAK_LOG {
+                    continue;
+                }
+                trees += 1;
+                assert_eq!(blocks.get(x, y + 1, z), OAK_LEAVES);
+                for (dx, dz) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                    let block = blocks.get(x + dx, y, z + dz);
+                    assert!(
+                        block == OAK_LEAVES || block == OAK_LOG,
+                        "no leaves next to the trunk at {}, {}, {}",
+                        x,
+                        y,
+                        z
+                    );
+                }
+            }
+        }
+    }
+    assert!(trees > 0);
+}
+
+#[test]
+fn finds_generators_by_name() {
+    assert_eq!(Generator::from_name("void"), Some(Generator::Void));
+    assert_eq!(
+        Generator::from_name("flat"),
+        Some(Generator::Superflat(CLASSIC_FLAT))
+    );
+    assert_eq!(Generator::from_name("terrain"), Some(Generator::Terrain));
+    assert_eq!(Generator::from_name("amplified"), None);
+}
+
+#[test]
+fn changes_blocks_in_chunks() {
+    let world = World {
+        generator: Generator::Superflat(CLASSIC_FLAT),
+        seed: 42,
+    };
+    let mut chunk = world.chunk(0, 0, BIOME_ID);
+
+    chunk.set_block(3, 10, 5, OAK_LOG);
+    assert_eq!(chunk.block(3, 10, 5), OAK_LOG as u32);
+    assert_eq!(chunk.height(3, 5), 11);
+    // Taking it away again lowers the column back onto the grass
+    chunk.set_block(3, 10, 5, AIR);
+    assert_eq!(chunk.height(3, 5), 4);
+    chunk.set_block(3, 3, 5, AIR);
+    assert_eq!(chunk.block(3, 3, 5), AIR as u32);
+    assert_eq!(chunk.height(3, 5), 3);
+    assert_eq!(chunk.block(4, 3, 5), GRASS_BLOCK as u32);
+}
@@ -0,0 +1,231 @@
+//! Runs PicoCraft on a computer, so it can be tried out without flashing a Pico.
+//!
+//! Listens on `0.0.0.0:25565` unless another address is given as the first argument. Set
+//! `RUST_LOG=info` to see what the server is doing.
+//!
+//! Set `CHECK_SESSIONS=1` to check players with Mojang, or with the session server at
+//! `SESSION_SERVER` if that's set too.
+//!
+//! Worlds are generated from a random seed unless `SEED` is set, with the generator named by
+//! `WORLD` if that's set too, like `flat` or `void`.
+//!
+//! The blocks players change are forgotten when the simulator stops, unless `STORE` names a file
+//! to keep them in like the Pico keeps them in flash. Keep `SEED` the same to find them again.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    rc::Rc,
+};
+
+use embedded_io_adapters::tokio_1::FromTokio;
+use embedded_io_async::{Error, ErrorKind};
+use embedded_storage::nor_flash::{
+    check_erase, check_read, check_write, ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash,
+};
+use log::{info, warn};
+use picocraft_server::{
+    encryption::ServerKey,
+    game::Game,
+    serve,
+    session::Connect,
+    store::FlashStore,
+    world::{Generator, World},
+    Config,
+};
+use rand_core::{OsRng, RngCore};
+use tokio::{
+    io::{AsyncWriteExt, BufStream},
+    net::{TcpListener, TcpStream},
+    task::LocalSet,
+};
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> std::io::Result<()> {
+    env_logger::init();
+
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "0.0.0.0:25565".to_string());
+    info!("Generating the server's key pair...");
+    let mut config = Config {
+        key: Some(ServerKey::generate(&mut OsRng).expect("couldn't generate a key pair")),
+        check_sessions: std::env::var_os("CHECK_SESSIONS").is_some(),
+        ..Config::default()
+    };
+    if let Ok(session_server) = std::env::var("SESSION_SERVER") {
+        config.session_server = session_server;
+    }
+    let flash = match std::env::var("STORE") {
+        Ok(path) => FileFlash::open(&path)?,
+        Err(_) => FileFlash::default(),
+    };
+    let mut store = FlashStore::open(flash, 0, STORE_SIZE as u32, OsRng.next_u64())
+        .expect("couldn't open the block store");
+    info!("{} edits until the block store is compacted", store.space());
+
+    // The world the edits were made in is used again, unless SEED picks another
+    if let Ok(seed) = std::env::var("SEED") {
+        let seed = seed.parse().expect("SEED isn't a number");
+        if seed != store.seed() {
+            warn!("SEED has changed, forgetting the edits made under the old one");
+            store.reset(seed).expect("couldn't reset the block store");
+        }
+    }
+    config.world = World {
+        generator: match std::env::var("WORLD") {
+            Ok(name) => Generator::from_name(&name).expect("WORLD isn't a generator"),
+            Err(_) => Generator::Terrain,
+        },
+        seed: store.seed(),
+    };
+    info!("Generating worlds from seed {}", config.world.seed);
+    let config = Rc::new(config);
+
+    let game = Rc::new(Game::new(store));
+
+    let listener = TcpListener::bind(&addr).await?;
+    info!("Listening on TCP:{}...", listener.local_addr()?);
+
+    // Like the Pico's executor, connections share one thread with the game loop
+    LocalSet::new()
+        .run_until(async move {
+            tokio::task::spawn_local({
+                let game = game.clone();
+                async move { game.run().await }
+            });
+            loop {
+                let (socket, remote) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        warn!("accept error: {:?}", e);
+                        continue;
+                    }
+                };
+
+                info!("Received connection from {}", remote);
+                tokio::task::spawn_local(handle_conn(socket, config.clone(), game.clone()));
+            }
+        })
+        .await
+}
+
+async fn handle_conn(socket: TcpStream, config: Rc<Config>, game: Rc<Game<Store>>) {
+    info!("Handling connection");
+
+    // Packets are written a field at a time, so buffer them like the Pico's TCP stack does
+    let mut socket = FromTokio::new(BufStream::new(socket));
+    // A bad packet only takes down the connection it came from
+    if let Err(err) = serve(&mut socket, &config, &game, &mut OsRng, &mut TokioConnect).await {
+        warn!("Closing connection: {}", err);
+    }
+
+    let _ = socket.into_inner().shutdown().await;
+}
+
+/// Connects to the session server through the host's network stack.
+struct TokioConnect;
+
+impl Connect for TokioConnect {
+    type Connection<'a> = FromTokio<TcpStream>;
+
+    async fn connect(&mut self, host: &str, port: u16) -> Result<Self::Connection<'_>, ErrorKind> {
+        match TcpStream::connect((host, port)).await {
+            Ok(socket) => Ok(FromTokio::new(socket)),
+            Err(err) => Err(Error::kind(&err)),
+        }
+    }
+}
+
+/// As big as the Pico's block store.
+const STORE_SIZE: usize = 128 * 1024;
+
+type Store = FlashStore<FileFlash>;
+
+/// Flash kept in RAM, and written through to a file if there is one.
+struct FileFlash {
+    data: Vec<u8>,
+    file: Option<File>,
+}
+
+impl FileFlash {
+    /// Opens the flash kept in the file at `path`, which is made erased if it doesn't exist.
+    fn open(path: &str) -> std::io::Result<FileFlash> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        data.resize(STORE_SIZE, 0xff);
+        Ok(FileFlash {
+            data,
+            file: Some(file),
+        })
+    }
+
+    fn write_through(&mut self, from: usize, to: usize) -> Result<(), NorFlashErrorKind> {
+        let Some(file) = &mut self.file else {
+            return Ok(());
+        };
+        file.seek(SeekFrom::Start(from as u64))
+            .and_then(|_| file.write_all(&self.data[from..to]))
+            .map_err(|err| {
+                warn!("Couldn't write the block store: {}", err);
+                NorFlashErrorKind::Other
+            })
+    }
+}
+
+impl Default for FileFlash {
+    fn default() -> FileFlash {
+        FileFlash {
+            data: vec![0xff; STORE_SIZE],
+            file: None,
+        }
+    }
+}
+
+impl ErrorType for FileFlash {
+    type Error = NorFlashErrorKind;
+}
+
+impl ReadNorFlash for FileFlash {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        check_read(self, offset, bytes.len())?;
+        let offset = offset as usize;
+        bytes.copy_from_slice(&self.data[offset..offset + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl NorFlash for FileFlash {
+    // Like the Pico's flash
+    const WRITE_SIZE: usize = 1;
+    const ERASE_SIZE: usize = 4096;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        check_erase(self, from, to)?;
+        let (from, to) = (from as usize, to as usize);
+        self.data[from..to].fill(0xff);
+        self.write_through(from, to)
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        check_write(self, offset, bytes.len())?;
+        let offset = offset as usize;
+        // Writing can only clear bits
+        for (old, new) in self.data[offset..].iter_mut().zip(bytes) {
+            *old &= new;
+        }
+        self.write_through(offset, offset + bytes.len())
+    }
+}